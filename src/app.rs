@@ -0,0 +1,594 @@
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Write};
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Local};
+use colored::*;
+use weather::output::{self, AirQualityView, ErrorView, ForecastView, ObservationView, OutputFormat, RecordWriter, WarningDetails, WarningView};
+use weather::{render, AirQuality, BlockingClient, Fetched, Forecast, Location, Observation, Origin, Place, PlaceQuery, Units, WeatherError};
+use crate::cli::{AirArgs, CheckArgs, ForecastArgs, WatchArgs};
+use crate::rules::{CheckView, RuleSet, RuleView};
+
+// Exit status of `weather check` when a rule fires, apart from 1 for failed lookups and 2 for setup errors
+pub const RULE_FIRED: u8 = 3;
+
+// Core struct responsible for retrieving and displaying weather data
+pub struct WeatherApp {
+    pub client: BlockingClient,                // Lookups through the cache and the rate limiter
+    pub units: Units,                          // Units readings are shown in; providers always report metric
+    pub output: OutputFormat,                  // How reports and errors are printed
+    pub notices: Option<mpsc::Sender<String>>, // Receives warnings instead of stderr, while a full-screen view owns the terminal
+}
+
+impl WeatherApp {
+    // Constructs a new instance of WeatherApp
+    pub fn initialize(client: BlockingClient, units: Units, output: OutputFormat) -> Self {
+        WeatherApp { client, units, output, notices: None }
+    }
+
+    // Name of the weather service in use, for messages
+    pub fn provider_name(&self) -> &'static str {
+        self.client.client().provider_name()
+    }
+
+    // Looks up the places matching a location, failing when there are none
+    pub fn locate(&self, location: &Location) -> Result<Vec<Place>, WeatherError> {
+        Ok(self.located(self.client.locate(location)?))
+    }
+
+    // Warns when the quota is getting used up by a lookup. Where the places came from is not worth
+    // mentioning, as they hardly ever change.
+    pub fn located<T>(&self, fetched: Fetched<T>) -> T {
+        if let Some(warning) = fetched.quota_warning {
+            self.warn("quota", warning, None);
+        }
+        fetched.value
+    }
+
+    // Prints a fetch error as advice, or as a JSON object for structured output
+    pub fn report_error(&self, error: &WeatherError) {
+        match self.output {
+            OutputFormat::Text => eprintln!("{}", UserInteraction::describe_error(self.provider_name(), error)),
+            _ => output::print_error(&ErrorView::from(error)),
+        }
+    }
+
+    // Retrieves weather data for a geocoded place, from the cache while it is fresh
+    pub fn obtain_weather(&self, place: &Place) -> Result<Observation, WeatherError> {
+        Ok(self.announce(self.client.current(place)?, "report"))
+    }
+
+    // Retrieves the multi-day forecast, from the cache while it is fresh
+    pub fn obtain_forecast(&self, place: &Place) -> Result<Forecast, WeatherError> {
+        Ok(self.announce(self.client.forecast(place)?, "forecast"))
+    }
+
+    pub fn obtain_air_quality(&self, place: &Place) -> Result<AirQuality, WeatherError> {
+        Ok(self.announce(self.client.air_quality(place)?, "air quality report"))
+    }
+
+    pub fn obtain_air_forecast(&self, place: &Place) -> Result<AirQuality, WeatherError> {
+        Ok(self.announce(self.client.air_forecast(place)?, "air quality forecast"))
+    }
+
+    // Warns when the quota is getting used up, or when a report is older than it looks: served
+    // offline or in place of a failed fetch
+    pub fn announce<T>(&self, fetched: Fetched<T>, what: &str) -> T {
+        if let Some(warning) = fetched.quota_warning {
+            self.warn("quota", warning, None);
+        }
+        let (code, message, age) = match fetched.origin {
+            Origin::Offline(age) => ("offline", format!("Offline: showing the {} cached {} ago.", what, render::format_age(age)), age),
+            Origin::Stale(age, e) => (
+                "stale",
+                format!("{} could not be reached ({}); showing the {} cached {} ago.", self.provider_name(), e, what, render::format_age(age)),
+                age,
+            ),
+            Origin::Fetched | Origin::Cached => return fetched.value,
+        };
+        self.warn(code, message, Some(age));
+        fetched.value
+    }
+
+    // Prints a warning on stderr, as a JSON object for structured output
+    pub fn warn(&self, code: &'static str, message: String, age: Option<Duration>) {
+        if let Some(notices) = &self.notices {
+            let _ = notices.send(message);
+            return;
+        }
+        match self.output {
+            OutputFormat::Text => eprintln!("{}", message.yellow()),
+            _ => output::print_warning(&WarningView {
+                warning: WarningDetails { code, message, age_seconds: age.map(|age| age.as_secs()) },
+            }),
+        }
+    }
+
+    // Displays the weather details in a formatted way
+    pub fn render_weather_info(&self, weather_info: &Observation, detailed: bool) {
+        println!("{}", render::weather_text(&self.units, weather_info, detailed, None));
+    }
+
+    // Displays one line per local day, optionally followed by each forecast step of that day
+    pub fn render_forecast(&self, forecast: &Forecast, hourly: bool) {
+        for line in render::forecast_lines(&self.units, forecast, hourly) {
+            println!("{}", line);
+        }
+    }
+}
+
+// Handles interactions with the user in the terminal
+pub struct UserInteraction;
+
+impl UserInteraction {
+    // Reads a line from stdin, or None once it is closed
+    fn read_line() -> Option<String> {
+        let mut line = String::new();
+        match io::stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line),
+        }
+    }
+
+    // Prompts the user for a location, asking for the country code when only a name was given;
+    // None when stdin is closed
+    fn acquire_user_input() -> Option<Location> {
+        let location = loop {
+            println!("{}", "Enter a city, coordinates (52.52,13.40), zip:CODE,CC, id:CITYID or @saved:".bright_green());
+            let text = Self::read_line()?;
+            match text.parse::<Location>() {
+                Ok(location) => break location,
+                Err(e) => println!("{}", format!("That is not a location: {}", e).bright_red()),
+            }
+        };
+        let Location::Named(PlaceQuery { name, state, country: None }) = location else {
+            return Some(location);
+        };
+
+        println!("{}", "Enter the country code (e.g., US for United States):".bright_green());
+        let country = Self::read_line().unwrap_or_default().trim().to_string();
+
+        Some(Location::Named(PlaceQuery {
+            name,
+            state,
+            country: Some(country).filter(|country| !country.is_empty()),
+        }))
+    }
+
+    // Asks the user to pick one of several places by number
+    fn choose_place(location: &Location, mut candidates: Vec<Place>) -> Option<Place> {
+        println!("{}", format!("\"{}\" matches several places:", location).bright_green());
+        for (number, place) in candidates.iter().enumerate() {
+            println!("  {}. {}", number + 1, place);
+        }
+
+        loop {
+            println!("{}", format!("Enter a number from 1 to {} (blank to cancel):", candidates.len()).bright_green());
+            let choice = Self::read_line().unwrap_or_default();
+            let choice = choice.trim();
+            if choice.is_empty() {
+                return None;
+            }
+            match choice.parse::<usize>() {
+                Ok(number) if (1..=candidates.len()).contains(&number) => return Some(candidates.swap_remove(number - 1)),
+                _ => println!("{}", "That is not one of the listed numbers.".bright_red()),
+            }
+        }
+    }
+
+    // Resolves a location to a single place; several matches are an error unless the user can choose
+    pub fn pick_place(weather_app: &WeatherApp, location: &Location, interactive: bool) -> Result<Option<Place>, WeatherError> {
+        let mut candidates = weather_app.locate(location)?;
+        if candidates.len() == 1 {
+            return Ok(candidates.pop());
+        }
+        if interactive {
+            return Ok(Self::choose_place(location, candidates));
+        }
+        Err(WeatherError::Ambiguous { query: location.to_string(), candidates })
+    }
+
+    // Fetches and displays a single report, reporting failure through the exit code
+    pub fn report_once(weather_app: &WeatherApp, location: &Location, detailed: bool) -> ExitCode {
+        let place = match Self::pick_place(weather_app, location, false) {
+            Ok(place) => place.expect("non-interactive lookups never cancel"),
+            Err(e) => {
+                weather_app.report_error(&e);
+                return ExitCode::FAILURE;
+            }
+        };
+        match weather_app.obtain_weather(&place) {
+            Ok(weather_info) => {
+                match weather_app.output {
+                    OutputFormat::Text => weather_app.render_weather_info(&weather_info, detailed),
+                    #[cfg(feature = "json")]
+                    OutputFormat::Json => output::print_json(&ObservationView::new(&weather_info, &weather_app.units)),
+                    format => {
+                        let view = ObservationView::new(&weather_info, &weather_app.units);
+                        if let Err(e) = RecordWriter::new(format).write(&view) {
+                            eprintln!("Could not write the report: {}", e);
+                            return ExitCode::FAILURE;
+                        }
+                    }
+                }
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                weather_app.report_error(&e);
+                ExitCode::FAILURE
+            }
+        }
+    }
+
+    // Fetches and displays the forecast, reporting failure through the exit code
+    pub fn forecast_once(weather_app: &WeatherApp, args: &ForecastArgs) -> ExitCode {
+        let place = match Self::pick_place(weather_app, &args.location.location(), false) {
+            Ok(place) => place.expect("non-interactive lookups never cancel"),
+            Err(e) => {
+                weather_app.report_error(&e);
+                return ExitCode::FAILURE;
+            }
+        };
+        match weather_app.obtain_forecast(&place) {
+            Ok(forecast) => {
+                match weather_app.output {
+                    OutputFormat::Text => weather_app.render_forecast(&forecast, args.hourly),
+                    #[cfg(feature = "json")]
+                    OutputFormat::Json => output::print_json(&ForecastView::new(&forecast, &weather_app.units)),
+                    format => {
+                        let view = ForecastView::new(&forecast, &weather_app.units);
+                        if let Err(e) = view.write_rows(&mut RecordWriter::new(format), args.hourly) {
+                            eprintln!("Could not write the forecast: {}", e);
+                            return ExitCode::FAILURE;
+                        }
+                    }
+                }
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                weather_app.report_error(&e);
+                ExitCode::FAILURE
+            }
+        }
+    }
+
+    // Prints the current air quality followed by its forecast
+    pub fn air_once(weather_app: &WeatherApp, args: &AirArgs) -> ExitCode {
+        let place = match Self::pick_place(weather_app, &args.location.location(), false) {
+            Ok(place) => place.expect("non-interactive lookups never cancel"),
+            Err(e) => {
+                weather_app.report_error(&e);
+                return ExitCode::FAILURE;
+            }
+        };
+        let air = weather_app.obtain_air_quality(&place).and_then(|current| Ok((current, weather_app.obtain_air_forecast(&place)?)));
+        match air {
+            Ok((current, forecast)) => {
+                // The current air is the first reading; a provider answering with none has nothing to show
+                let Some(reading) = current.readings.first() else {
+                    weather_app.report_error(&WeatherError::NotFound { message: format!("no air pollution readings for {}", current.location) });
+                    return ExitCode::FAILURE;
+                };
+                match weather_app.output {
+                    OutputFormat::Text => {
+                        println!("{}", render::air_quality_text(&current.location, reading));
+                        for line in render::air_forecast_lines(&forecast, args.hourly) {
+                            println!("{}", line);
+                        }
+                    }
+                    #[cfg(feature = "json")]
+                    OutputFormat::Json => output::print_json(&AirQualityView::new(&current.location, reading, &forecast)),
+                    format => {
+                        let view = AirQualityView::new(&current.location, reading, &forecast);
+                        if let Err(e) = view.write_rows(&mut RecordWriter::new(format)) {
+                            eprintln!("Could not write the air quality: {}", e);
+                            return ExitCode::FAILURE;
+                        }
+                    }
+                }
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                weather_app.report_error(&e);
+                ExitCode::FAILURE
+            }
+        }
+    }
+
+    // Tests every rule against the current conditions, exiting with RULE_FIRED when any of them is true
+    pub fn check(weather_app: &WeatherApp, args: &CheckArgs, rules: &RuleSet) -> ExitCode {
+        let place = match Self::pick_place(weather_app, &args.location.location(), false) {
+            Ok(place) => place.expect("non-interactive lookups never cancel"),
+            Err(e) => {
+                weather_app.report_error(&e);
+                return ExitCode::FAILURE;
+            }
+        };
+        let weather_info = match weather_app.obtain_weather(&place) {
+            Ok(weather_info) => weather_info,
+            Err(e) => {
+                weather_app.report_error(&e);
+                return ExitCode::FAILURE;
+            }
+        };
+
+        let results: Vec<RuleView> = rules
+            .rules
+            .iter()
+            .map(|rule| RuleView {
+                location: weather_info.location.clone(),
+                rule: rule.name.clone(),
+                when: rule.when.to_string(),
+                fired: rule.when.evaluate(&weather_info),
+            })
+            .collect();
+        let fired = results.iter().filter(|result| result.fired).count();
+
+        match weather_app.output {
+            OutputFormat::Text => {
+                println!("{}", format!("Rules for {}:", weather_info.location).bold());
+                for result in &results {
+                    let line = format!("  {} {}: {}", if result.fired { "FIRED" } else { "ok   " }, result.rule, result.when);
+                    println!("{}", if result.fired { line.bright_red() } else { line.normal() });
+                }
+                println!("{} of {} rules fired.", fired, results.len());
+            }
+            #[cfg(feature = "json")]
+            OutputFormat::Json => output::print_json(&CheckView {
+                observation: ObservationView::new(&weather_info, &weather_app.units),
+                rules: results,
+            }),
+            format => {
+                let mut records = RecordWriter::new(format);
+                if let Err(e) = results.iter().try_for_each(|result| records.write(result)) {
+                    eprintln!("Could not write the results: {}", e);
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+
+        if fired > 0 {
+            ExitCode::from(RULE_FIRED)
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+
+    // Starts the commands of the rules a reading fires, skipping rules whose command ran less than
+    // their cooldown ago. Returns the names of every rule that fired.
+    fn run_rules(weather_app: &WeatherApp, rules: &RuleSet, weather_info: &Observation, last_run: &mut HashMap<usize, Instant>) -> Vec<String> {
+        let mut fired = Vec::new();
+        for (index, rule) in rules.rules.iter().enumerate().filter(|(_, rule)| rule.when.evaluate(weather_info)) {
+            fired.push(rule.name.clone());
+            if rule.command.is_none() || last_run.get(&index).is_some_and(|at| at.elapsed() < rule.cooldown) {
+                continue;
+            }
+            // The reading goes into the environment as WEATHER_TEMPERATURE, WEATHER_LOCATION_NAME, ...
+            let view = ObservationView::new(weather_info, &weather_app.units);
+            let env = output::flat_fields(&view).into_iter().map(|(name, value)| (format!("WEATHER_{}", name.to_uppercase()), value));
+            tracing::info!(rule = %rule.name, "running rule command");
+            rule.run_command(env.collect());
+            last_run.insert(index, Instant::now());
+        }
+        fired
+    }
+
+    // Fetches and redraws the current conditions on an interval until Ctrl-C or --count updates.
+    // Failed updates keep the last report on screen and wait longer before the next attempt.
+    pub fn watch(weather_app: &WeatherApp, args: &WatchArgs, rules: &RuleSet) -> ExitCode {
+        let place = match Self::pick_place(weather_app, &args.location.location(), false) {
+            Ok(place) => place.expect("non-interactive lookups never cancel"),
+            Err(e) => {
+                weather_app.report_error(&e);
+                return ExitCode::FAILURE;
+            }
+        };
+
+        // The first Ctrl-C ends the wait for the next update; a second one gives up on a request in flight
+        let (stop_sender, stop) = mpsc::channel();
+        let handler_sender = stop_sender.clone();
+        let stopping = AtomicBool::new(false);
+        if let Err(e) = ctrlc::set_handler(move || {
+            if stopping.swap(true, Ordering::SeqCst) {
+                std::process::exit(130);
+            }
+            let _ = handler_sender.send(());
+        }) {
+            tracing::warn!(error = %e, "could not install the Ctrl-C handler");
+        }
+
+        let client = weather_app.client.client();
+        if let Some(cache) = client.cache().filter(|cache| cache.ttl > args.every && !client.is_offline()) {
+            let message = format!(
+                "Reports are cached for {}, so updating every {} shows the same reading in between; lower cache_ttl in the config file for fresher ones.",
+                render::format_age(cache.ttl),
+                render::format_age(args.every)
+            );
+            weather_app.warn("cache", message, None);
+        }
+
+        let in_place = weather_app.output == OutputFormat::Text && io::stdout().is_terminal();
+        let mut records = RecordWriter::new(weather_app.output);
+        let mut shown: Option<Observation> = None;
+        let mut previous: Option<Observation> = None;
+        let mut updated_at: Option<DateTime<Local>> = None;
+        let mut failures = 0;
+        let mut updates = 0;
+        let mut last_run = HashMap::new();
+
+        let succeeded = loop {
+            let wait = match weather_app.obtain_weather(&place) {
+                Ok(weather_info) => {
+                    failures = 0;
+                    // A cached reading comes back unchanged; keep comparing against the one before it
+                    if shown.as_ref().is_some_and(|shown| shown.observed_at.is_none() || shown.observed_at != weather_info.observed_at) {
+                        previous = shown.take();
+                    }
+                    let fired = Self::run_rules(weather_app, rules, &weather_info, &mut last_run);
+                    let now = Local::now();
+                    updated_at = Some(now);
+                    let mut footer = format!("Last updated {}; next update in {}.", now.format("%H:%M:%S"), render::format_age(args.every));
+                    if !fired.is_empty() {
+                        footer.push_str(&format!("\nRules fired: {}", fired.join(", ")));
+                    }
+                    match weather_app.output {
+                        OutputFormat::Text => {
+                            let text = render::weather_text(&weather_app.units, &weather_info, args.detailed, previous.as_ref());
+                            Self::draw(in_place, &text, &footer.dimmed());
+                        }
+                        #[cfg(feature = "json")]
+                        OutputFormat::Json => output::print_json(&ObservationView::new(&weather_info, &weather_app.units)),
+                        _ => {
+                            if let Err(e) = records.write(&ObservationView::new(&weather_info, &weather_app.units)) {
+                                eprintln!("Could not write the report: {}", e);
+                                return ExitCode::FAILURE;
+                            }
+                        }
+                    }
+                    shown = Some(weather_info);
+                    args.every
+                }
+                Err(e) => {
+                    failures += 1;
+                    let wait = Self::watch_backoff(args.every, failures, &e);
+                    if weather_app.output.is_structured() {
+                        weather_app.report_error(&e);
+                    } else {
+                        let last = updated_at.map_or("never".to_string(), |at| at.format("%H:%M:%S").to_string());
+                        let footer = format!(
+                            "Last updated {}; update failed at {}: {}\nRetrying in {}.",
+                            last,
+                            Local::now().format("%H:%M:%S"),
+                            Self::describe_error(weather_app.provider_name(), &e),
+                            render::format_age(wait)
+                        );
+                        match (&shown, in_place) {
+                            (Some(shown), true) => {
+                                let text = render::weather_text(&weather_app.units, shown, args.detailed, previous.as_ref());
+                                Self::draw(true, &text, &footer.bright_red());
+                            }
+                            _ => eprintln!("{}", footer.bright_red()),
+                        }
+                    }
+                    wait
+                }
+            };
+
+            updates += 1;
+            if args.count.is_some_and(|count| updates >= count) {
+                break failures == 0;
+            }
+            if stop.recv_timeout(wait).is_ok() {
+                break true;
+            }
+        };
+        // Held until here so that waiting never sees a closed channel, even without a Ctrl-C handler
+        drop(stop_sender);
+
+        if succeeded {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        }
+    }
+
+    // Shows a report and its status line, replacing the previous ones on a terminal
+    fn draw(in_place: bool, text: &ColoredString, footer: &ColoredString) {
+        if in_place {
+            // Move to the top left corner and clear the screen
+            print!("\x1b[H\x1b[2J");
+            println!("{}\n\n{}", text, footer);
+        } else {
+            println!("{}\n{}\n", text, footer);
+        }
+        let _ = io::stdout().flush();
+    }
+
+    // Time until the next attempt after `failures` failed updates in a row: the interval, doubled for
+    // every further failure up to an hour (or the interval, if longer), and never sooner than the
+    // service asked for
+    fn watch_backoff(every: Duration, failures: u32, error: &WeatherError) -> Duration {
+        let ceiling = every.max(Duration::from_secs(60 * 60));
+        let wait = every.saturating_mul(2u32.saturating_pow(failures - 1)).min(ceiling);
+        match error {
+            WeatherError::RateLimited { retry_after: Some(retry_after) } => wait.max(*retry_after),
+            _ => wait,
+        }
+    }
+
+    // Turns a fetch error into advice the user can act on
+    pub fn describe_error(provider: &str, error: &WeatherError) -> String {
+        match error {
+            WeatherError::Auth { message } => format!(
+                "{} rejected the API key ({}). Check which key is used with `weather config`; new keys can take a couple of hours to activate.",
+                provider, message
+            ),
+            WeatherError::NotFound { message } => format!(
+                "No weather found for that location ({}). Check the spelling of the city and the country code.",
+                message
+            ),
+            WeatherError::Ambiguous { query, candidates } => {
+                let listed: Vec<String> = candidates.iter().map(|place| format!("\n  - {}", place)).collect();
+                format!(
+                    "\"{}\" matches {} places. Add the state and country, e.g. \"{}\", to pick one of:{}",
+                    query,
+                    candidates.len(),
+                    candidates[0],
+                    listed.concat()
+                )
+            }
+            WeatherError::RateLimited { retry_after: Some(wait) } => format!(
+                "Too many requests to {}. Try again in {} seconds.",
+                provider,
+                wait.as_secs()
+            ),
+            WeatherError::RateLimited { retry_after: None } => {
+                format!("Too many requests to {}. Wait a minute and try again.", provider)
+            }
+            WeatherError::Network(e) => format!("Could not reach {}; check your internet connection. ({})", provider, e),
+            WeatherError::Timeout(_) => format!("{} did not answer in time. Try again shortly.", provider),
+            WeatherError::Decode(e) => format!("{} sent a response this version cannot read: {}", provider, e),
+            WeatherError::Provider { code, message } => format!("{} returned error {}: {}", provider, code, message),
+            WeatherError::Unsupported { feature } => format!(
+                "{} does not offer a {}. Choose another service with --provider.",
+                provider, feature
+            ),
+            WeatherError::QuotaExceeded { period, used, limit } => format!(
+                "The {} quota is used up ({} of {} calls). Wait for it to reset or raise quota.{}_limit in the config file.",
+                period, used, limit, period
+            ),
+            WeatherError::NotCached { what } => format!(
+                "There is no cached {}. Run the same command without --offline first.",
+                what
+            ),
+        }
+    }
+
+    // Main execution loop to fetch weather data and handle user prompts
+    pub fn execute_app(weather_app: &WeatherApp, detailed: bool) {
+        println!("{}", "Welcome to Weather App!".bright_yellow());
+
+        // A closed stdin ends the session like "no" does
+        while let Some(location) = Self::acquire_user_input() {
+            let show_error = |e: WeatherError| eprintln!("{}", Self::describe_error(weather_app.provider_name(), &e).bright_red());
+
+            match Self::pick_place(weather_app, &location, true) {
+                Ok(Some(place)) => match weather_app.obtain_weather(&place) {
+                    Ok(weather_info) => weather_app.render_weather_info(&weather_info, detailed),
+                    Err(e) => show_error(e),
+                },
+                Ok(None) => {}
+                Err(e) => show_error(e),
+            }
+
+            println!("{}", "Would you like to check the weather for another location? (yes/no):".bright_green());
+            let user_choice = Self::read_line().unwrap_or_default();
+            if user_choice.trim().to_lowercase() != "yes" {
+                println!("Thank you for using Weather App!");
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! The US EPA Air Quality Index, computed locally from pollutant concentrations, and the names of
+//! OpenWeatherMap's own 1–5 index.
+//!
+//! The EPA averages most pollutants over 8 or 24 hours; providers report the concentration at one
+//! moment, so the index here is an estimate of the official one.
+
+use std::fmt;
+use chrono::NaiveDate;
+use serde::Serialize;
+use crate::model::{AirReading, Pollutants};
+
+/// The six bands of the US AQI, each with the color the EPA gives it
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    Good,                        // 0–50, green
+    Moderate,                    // 51–100, yellow
+    UnhealthyForSensitiveGroups, // 101–150, orange
+    Unhealthy,                   // 151–200, red
+    VeryUnhealthy,               // 201–300, purple
+    Hazardous,                   // 301 and above, maroon
+}
+
+impl Category {
+    /// The band an index value falls in
+    pub fn from_aqi(aqi: u16) -> Self {
+        match aqi {
+            0..=50 => Category::Good,
+            51..=100 => Category::Moderate,
+            101..=150 => Category::UnhealthyForSensitiveGroups,
+            151..=200 => Category::Unhealthy,
+            201..=300 => Category::VeryUnhealthy,
+            _ => Category::Hazardous,
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Category::Good => "Good",
+            Category::Moderate => "Moderate",
+            Category::UnhealthyForSensitiveGroups => "Unhealthy for Sensitive Groups",
+            Category::Unhealthy => "Unhealthy",
+            Category::VeryUnhealthy => "Very Unhealthy",
+            Category::Hazardous => "Hazardous",
+        })
+    }
+}
+
+/// A US AQI value with the pollutant that set it
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UsAqi {
+    pub value: u16,              // 0 to 500; higher readings are capped
+    pub pollutant: &'static str, // Pollutant with the highest sub-index, e.g. "PM2.5"
+    pub category: Category,      // Band the value falls in
+}
+
+// One band of a breakpoint table: concentrations from `low` to `high` map onto the index from
+// `index_low` to `index_high`
+struct Band {
+    low: f64,
+    high: f64,
+    index_low: u16,
+    index_high: u16,
+}
+
+const fn band(low: f64, high: f64, index_low: u16, index_high: u16) -> Band {
+    Band { low, high, index_low, index_high }
+}
+
+// PM2.5 in µg/m³, 24-hour, as revised in 2024
+const PM2_5: [Band; 6] = [
+    band(0.0, 9.0, 0, 50),
+    band(9.1, 35.4, 51, 100),
+    band(35.5, 55.4, 101, 150),
+    band(55.5, 125.4, 151, 200),
+    band(125.5, 225.4, 201, 300),
+    band(225.5, 325.4, 301, 500),
+];
+
+// PM10 in µg/m³, 24-hour
+const PM10: [Band; 6] = [
+    band(0.0, 54.0, 0, 50),
+    band(55.0, 154.0, 51, 100),
+    band(155.0, 254.0, 101, 150),
+    band(255.0, 354.0, 151, 200),
+    band(355.0, 424.0, 201, 300),
+    band(425.0, 604.0, 301, 500),
+];
+
+// Ozone in ppm, 8-hour. That scale ends at 0.200 ppm; anything above counts as hazardous, up to
+// 0.604 ppm where the 1-hour scale ends.
+const O3: [Band; 6] = [
+    band(0.0, 0.054, 0, 50),
+    band(0.055, 0.070, 51, 100),
+    band(0.071, 0.085, 101, 150),
+    band(0.086, 0.105, 151, 200),
+    band(0.106, 0.200, 201, 300),
+    band(0.201, 0.604, 301, 500),
+];
+
+// Nitrogen dioxide in ppb, 1-hour
+const NO2: [Band; 6] = [
+    band(0.0, 53.0, 0, 50),
+    band(54.0, 100.0, 51, 100),
+    band(101.0, 360.0, 101, 150),
+    band(361.0, 649.0, 151, 200),
+    band(650.0, 1249.0, 201, 300),
+    band(1250.0, 2049.0, 301, 500),
+];
+
+// Sulphur dioxide in ppb, 1-hour
+const SO2: [Band; 6] = [
+    band(0.0, 35.0, 0, 50),
+    band(36.0, 75.0, 51, 100),
+    band(76.0, 185.0, 101, 150),
+    band(186.0, 304.0, 151, 200),
+    band(305.0, 604.0, 201, 300),
+    band(605.0, 1004.0, 301, 500),
+];
+
+// Carbon monoxide in ppm, 8-hour
+const CO: [Band; 6] = [
+    band(0.0, 4.4, 0, 50),
+    band(4.5, 9.4, 51, 100),
+    band(9.5, 12.4, 101, 150),
+    band(12.5, 15.4, 151, 200),
+    band(15.5, 30.4, 201, 300),
+    band(30.5, 50.4, 301, 500),
+];
+
+// Converts µg/m³ to ppb at 25 °C and 1 atm, given the gas's molar mass in g/mol
+fn ppb(micrograms: f64, molar_mass: f64) -> f64 {
+    micrograms * 24.45 / molar_mass
+}
+
+// Sub-index of one pollutant. The concentration is first truncated to the precision of its table,
+// which leaves no gaps between the bands.
+fn sub_index(concentration: f64, decimals: i32, bands: &[Band]) -> u16 {
+    let scale = 10f64.powi(decimals);
+    let concentration = (concentration.max(0.0) * scale).floor() / scale;
+    let Some(band) = bands.iter().find(|band| concentration <= band.high + 1e-9) else {
+        return 500;
+    };
+    let span = f64::from(band.index_high - band.index_low);
+    let position = (concentration - band.low) / (band.high - band.low);
+    (f64::from(band.index_low) + span * position.max(0.0)).round() as u16
+}
+
+/// Computes the US AQI: the highest sub-index of PM2.5, PM10, ozone, nitrogen dioxide, sulphur
+/// dioxide and carbon monoxide. Ammonia has no place in it.
+pub fn us_aqi(pollutants: &Pollutants) -> UsAqi {
+    let sub_indices = [
+        ("PM2.5", sub_index(pollutants.pm2_5, 1, &PM2_5)),
+        ("PM10", sub_index(pollutants.pm10, 0, &PM10)),
+        ("O3", sub_index(ppb(pollutants.o3, 48.00) / 1000.0, 3, &O3)),
+        ("NO2", sub_index(ppb(pollutants.no2, 46.01), 0, &NO2)),
+        ("SO2", sub_index(ppb(pollutants.so2, 64.07), 0, &SO2)),
+        ("CO", sub_index(ppb(pollutants.co, 28.01) / 1000.0, 1, &CO)),
+    ];
+    // The first pollutant wins a tie
+    let (pollutant, value) = sub_indices.into_iter().rev().max_by_key(|(_, value)| *value).expect("six pollutants");
+    UsAqi { value, pollutant, category: Category::from_aqi(value) }
+}
+
+/// Name of a level of OpenWeatherMap's index, which runs from 1 (good) to 5 (very poor)
+pub fn index_name(index: u8) -> &'static str {
+    match index {
+        1 => "Good",
+        2 => "Fair",
+        3 => "Moderate",
+        4 => "Poor",
+        5 => "Very Poor",
+        _ => "Unknown",
+    }
+}
+
+/// The worst air of one UTC calendar day of a forecast
+#[derive(Debug, Clone)]
+pub struct AirDay {
+    pub date: NaiveDate,   // Day in UTC
+    pub us_aqi: UsAqi,     // Highest US AQI of the day
+    pub index: Option<u8>, // Highest provider index of the day
+}
+
+/// Groups forecast readings into UTC calendar days, earliest first. Air pollution forecasts carry
+/// no time zone, so the days cannot follow local time.
+pub fn daily(readings: &[AirReading]) -> Vec<AirDay> {
+    let mut days: Vec<AirDay> = Vec::new();
+    for reading in readings {
+        let date = reading.time.date_naive();
+        let us_aqi = us_aqi(&reading.pollutants);
+        match days.last_mut() {
+            Some(day) if day.date == date => {
+                if us_aqi.value > day.us_aqi.value {
+                    day.us_aqi = us_aqi;
+                }
+                day.index = day.index.max(reading.index);
+            }
+            _ => days.push(AirDay { date, us_aqi, index: reading.index }),
+        }
+    }
+    days
+}
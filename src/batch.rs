@@ -0,0 +1,254 @@
+use std::path::Path;
+use std::process::ExitCode;
+use colored::*;
+use futures::stream::{self, StreamExt};
+use serde::Deserialize;
+use weather::error::WeatherError;
+use weather::model::{Location, Observation};
+use serde_json::Value;
+use weather::output::{self, ComparisonView, ErrorView, ObservationView, OutputFormat, RecordWriter};
+use weather::render;
+use crate::cli::NowArgs;
+use crate::config::{SavedFields, SavedLocation};
+use crate::app::WeatherApp;
+
+// A row of a --from-file CSV; other columns, such as a label, are ignored
+#[derive(Deserialize, Debug, Default)]
+struct Row {
+    location: Option<String>,
+    city: Option<String>,
+    state: Option<String>,
+    country: Option<String>,
+    lat: Option<f64>,
+    lon: Option<f64>,
+    zip: Option<String>,
+    id: Option<u64>,
+}
+
+// Columns that name a location in a --from-file CSV
+const LOCATION_COLUMNS: [&str; 6] = ["location", "city", "lat", "lon", "zip", "id"];
+
+// The locations of `weather now`: those on the command line, then those in --from-file
+pub fn locations(args: &NowArgs) -> Result<Vec<Location>, String> {
+    let mut locations = args.locations();
+    if let Some(path) = &args.from_file {
+        locations.extend(read_file(path)?);
+    }
+    Ok(locations)
+}
+
+// Reads locations from a CSV file with a header row, skipping blank rows and lines starting with #
+#[cfg(feature = "csv")]
+fn read_file(path: &Path) -> Result<Vec<Location>, String> {
+    let failed = |e: &dyn std::fmt::Display| format!("could not read {}: {}", path.display(), e);
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .comment(Some(b'#'))
+        .flexible(true)
+        .from_path(path)
+        .map_err(|e| failed(&e))?;
+    let headers: csv::StringRecord = reader.headers().map_err(|e| failed(&e))?.iter().map(str::to_lowercase).collect();
+    if !headers.iter().any(|header| LOCATION_COLUMNS.contains(&header)) {
+        return Err(format!(
+            "{} needs a header row with a `location` column, or `city`, `state` and `country`, `lat` and `lon`, `zip` or `id` columns",
+            path.display()
+        ));
+    }
+
+    let mut locations = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| failed(&e))?;
+        let line = record.position().map_or(0, |position| position.line());
+        let row: Row = record.deserialize(Some(&headers)).map_err(|e| format!("{} line {}: {}", path.display(), line, e))?;
+        let location = match row {
+            Row { location: Some(text), .. } => text.parse(),
+            Row { location: None, city: None, state: None, country: None, lat: None, lon: None, zip: None, id: None } => continue,
+            Row { location: None, city, state, country, lat, lon, zip, id } => {
+                SavedLocation::try_from(SavedFields { city, state, country, lat, lon, zip, id }).map(|saved| saved.0)
+            }
+        };
+        locations.push(location.map_err(|e| format!("{} line {}: {}", path.display(), line, e))?);
+    }
+    Ok(locations)
+}
+
+#[cfg(not(feature = "csv"))]
+fn read_file(path: &Path) -> Result<Vec<Location>, String> {
+    Err(format!("cannot read {}: this build has no CSV support; rebuild with the csv feature", path.display()))
+}
+
+// Fetches the current conditions of every location, `jobs` at a time on the shared runtime, in the
+// order given. Each location fails on its own; the others are fetched regardless.
+pub fn fetch_all(app: &WeatherApp, locations: &[Location], jobs: usize) -> Vec<Result<Observation, WeatherError>> {
+    let client = app.client.client();
+    let lookups = stream::iter(locations).map(|location| async move {
+        let place = client.place(location).await?;
+        let current = client.current(&place.value).await?;
+        Ok((place, current))
+    });
+    let results: Vec<Result<_, WeatherError>> = app.client.block_on(lookups.buffered(jobs.max(1)).collect());
+    // Warnings wait until every lookup is done, so they are not interleaved with each other
+    results
+        .into_iter()
+        .map(|result| {
+            result.map(|(place, current)| {
+                app.located(place);
+                app.announce(current, "report")
+            })
+        })
+        .collect()
+}
+
+// A location with its report or why there is none
+type Compared<'a> = (&'a Location, Result<&'a Observation, &'a WeatherError>);
+
+// Fetches several locations and prints them side by side, each failure on its own row; exits with 1
+// when any location failed
+pub fn compare(app: &WeatherApp, locations: &[Location], args: &NowArgs) -> ExitCode {
+    let results = fetch_all(app, locations, args.jobs);
+    let mut rows: Vec<Compared> = locations.iter().zip(&results).map(|(location, result)| (location, result.as_ref())).collect();
+    if let Some(key) = args.sort {
+        // A stable sort keeps the given order among equal readings; failures have none, so they go last
+        rows.sort_by(|(_, a), (_, b)| match (a, b) {
+            (Ok(a), Ok(b)) => key.compare(a, b),
+            (a, b) => b.is_ok().cmp(&a.is_ok()),
+        });
+    }
+    let failures: Vec<(&Location, &WeatherError)> =
+        rows.iter().filter_map(|(location, result)| result.err().map(|e| (*location, e))).collect();
+
+    match app.output {
+        OutputFormat::Text => print_table(app, &rows, args.detailed),
+        #[cfg(feature = "json")]
+        OutputFormat::Json => output::print_json(&records(app, &rows)),
+        format => {
+            let mut writer = RecordWriter::new(format);
+            if let Err(e) = records(app, &rows).iter().try_for_each(|record| writer.write(record)) {
+                eprintln!("Could not write the reports: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    if app.output.is_structured() {
+        for (location, error) in &failures {
+            let mut view = ErrorView::from(*error);
+            view.error.location = Some(location.to_string());
+            output::print_error(&view);
+        }
+    }
+
+    if failures.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+// The rows as structured records, a failed location's with the error and blank readings
+fn records(app: &WeatherApp, rows: &[Compared]) -> Vec<Value> {
+    let record = |(location, result): &Compared| {
+        let view = ComparisonView::new(location, result.map(|info| ObservationView::new(info, &app.units)));
+        serde_json::to_value(view).expect("reports always serialize")
+    };
+    let records: Vec<Value> = rows.iter().map(record).collect();
+    let Some(blank) = rows.iter().zip(&records).find(|((_, result), _)| result.is_ok()).map(|(_, report)| output::blank(report)) else {
+        return records;
+    };
+    records
+        .into_iter()
+        .map(|record| match record {
+            Value::Object(mut fields) if fields["error_code"].is_string() => {
+                for (key, value) in blank.as_object().expect("records are objects") {
+                    fields.entry(key.clone()).or_insert_with(|| value.clone());
+                }
+                Value::Object(fields)
+            }
+            record => record,
+        })
+        .collect()
+}
+
+// Prints one aligned row per location, colored by its conditions, with failures in red
+fn print_table(app: &WeatherApp, rows: &[Compared], detailed: bool) {
+    let units = &app.units;
+    let reading = render::format_reading;
+    let mut header = vec!["Location", "Temp", "Feels Like", "Humidity", "Pressure", "Wind", "Gusts", "Clouds"];
+    if detailed {
+        header.extend(["Min", "Max", "Visibility", "Rain (1h)", "Snow (1h)"]);
+    }
+    header.push("Conditions");
+
+    let readings: Vec<&Observation> = rows.iter().filter_map(|(_, result)| result.ok()).collect();
+    let cells: Vec<Vec<String>> = readings
+        .iter()
+        .map(|info| {
+            let mut row = vec![
+                match &info.country {
+                    Some(country) => format!("{}, {}", info.location, country),
+                    None => info.location.clone(),
+                },
+                units.temperature(info.temperature),
+                reading(info.feels_like.map(|celsius| units.temperature(celsius))),
+                reading(info.humidity.map(|humidity| format!("{:.1}%", humidity))),
+                reading(info.pressure.map(|pressure| units.pressure(pressure))),
+                reading(info.wind_speed.map(|speed| units.speed(speed))),
+                reading(info.wind_gust.map(|speed| units.speed(speed))),
+                reading(info.cloud_cover.map(|cover| format!("{:.0}%", cover))),
+            ];
+            if detailed {
+                row.extend([
+                    reading(info.temp_min.map(|celsius| units.temperature(celsius))),
+                    reading(info.temp_max.map(|celsius| units.temperature(celsius))),
+                    reading(info.visibility.map(|metres| units.distance(metres))),
+                    reading(info.rain_1h.map(|amount| units.precipitation(amount))),
+                    reading(info.snow_1h.map(|amount| units.precipitation(amount))),
+                ]);
+            }
+            row.push(info.description.clone());
+            row
+        })
+        .collect();
+
+    // Widths count characters, so symbols such as ° line up like any other letter
+    let mut widths: Vec<usize> = header.iter().map(|title| title.chars().count()).collect();
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for (location, _) in rows.iter().filter(|(_, result)| result.is_err()) {
+        widths[0] = widths[0].max(location.to_string().chars().count());
+    }
+    // Readings are right-aligned; the location and the conditions are text
+    let line = |cells: &[String]| {
+        let last = cells.len() - 1;
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(column, (cell, width))| match column {
+                0 => format!("{:<width$}", cell, width = width),
+                column if column == last => cell.clone(),
+                _ => format!("{:>width$}", cell, width = width),
+            })
+            .collect();
+        padded.join("  ")
+    };
+
+    let titles: Vec<String> = header.iter().map(|title| title.to_string()).collect();
+    println!("{}", line(&titles).bold());
+    let mut cells = cells.iter();
+    for (location, result) in rows {
+        match result {
+            Ok(info) => println!("{}", render::colorize_weather_output(info.condition, &line(cells.next().expect("a row per reading")))),
+            Err(error) => {
+                let text = format!("{:<width$}  failed: {}", location.to_string(), error, width = widths[0]);
+                println!("{}", text.bright_red());
+            }
+        }
+    }
+    let failed = rows.len() - readings.len();
+    if failed > 0 {
+        eprintln!("{}", format!("{} of {} locations could not be fetched.", failed, rows.len()).bright_red());
+    }
+}
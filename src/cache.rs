@@ -0,0 +1,120 @@
+//! Fetched reports and lookups kept on disk, so repeated runs do not call the provider again.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use crate::model::{Location, Place};
+use crate::providers::ProviderKind;
+
+/// Reports are reused for this long unless the config file sets `cache_ttl`
+pub const DEFAULT_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Geocoding results hardly ever change, so they are kept for a month
+pub const LOCATION_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+// Providers always fetch metric readings and convert them for display, so every key carries
+// "metric"; should that ever change, old entries stop matching instead of showing wrong values
+const FETCH_UNITS: &str = "metric";
+
+/// A cached value together with the time it was fetched
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Entry<T> {
+    pub fetched_at: DateTime<Utc>,
+    pub value: T,
+}
+
+impl<T> Entry<T> {
+    pub fn age(&self) -> Duration {
+        (Utc::now() - self.fetched_at).to_std().unwrap_or_default()
+    }
+}
+
+/// Fetched data stored as JSON files under `$XDG_CACHE_HOME/weather/<provider>/`
+#[derive(Debug)]
+pub struct Cache {
+    pub dir: PathBuf,
+    pub ttl: Duration, // How long reports count as fresh
+}
+
+impl Cache {
+    /// The cache for one provider; None when the platform has no cache directory
+    pub fn open(provider: ProviderKind, ttl: Duration) -> Option<Self> {
+        let dir = dirs::cache_dir()?.join("weather").join(provider.to_string());
+        Some(Cache { dir, ttl })
+    }
+
+    /// Key of the current conditions at a place
+    pub fn current_key(place: &Place) -> String {
+        format!("current-{}-{}", Self::coordinates(place), FETCH_UNITS)
+    }
+
+    /// Key of the forecast for a place
+    pub fn forecast_key(place: &Place) -> String {
+        format!("forecast-{}-{}", Self::coordinates(place), FETCH_UNITS)
+    }
+
+    /// Key of the current air quality at a place; concentrations have no unit system
+    pub fn air_key(place: &Place) -> String {
+        format!("air-{}", Self::coordinates(place))
+    }
+
+    /// Key of the air quality forecast for a place
+    pub fn air_forecast_key(place: &Place) -> String {
+        format!("air-forecast-{}", Self::coordinates(place))
+    }
+
+    /// Key of the places a location resolved to
+    pub fn location_key(location: &Location) -> String {
+        let text: String = location
+            .to_string()
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+            .collect();
+        format!("location-{}", text)
+    }
+
+    // Four decimals pin a place down to about ten metres
+    fn coordinates(place: &Place) -> String {
+        format!("{:.4},{:.4}", place.coordinates.latitude, place.coordinates.longitude)
+    }
+
+    /// Reads an entry of any age; unreadable entries count as missing
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<Entry<T>> {
+        let text = fs::read_to_string(self.path(key)).ok()?;
+        match serde_json::from_str(&text) {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                tracing::warn!(key, error = %e, "ignoring unreadable cache entry");
+                None
+            }
+        }
+    }
+
+    /// Stores a value fetched just now; failures are logged, the report is still shown
+    pub fn put<T: Serialize>(&self, key: &str, value: &T) {
+        if let Err(e) = self.write(key, &Entry { fetched_at: Utc::now(), value }) {
+            tracing::warn!(key, error = %e, "could not write cache entry");
+        }
+    }
+
+    fn write<T: Serialize>(&self, key: &str, entry: &Entry<T>) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        // Write then rename, so a concurrent reader never sees half an entry. Every write has a
+        // partial file of its own, since threads of one process may store the same key at once.
+        static WRITES: AtomicU64 = AtomicU64::new(0);
+        let path = self.path(key);
+        let partial = path.with_extension(format!("json.{}.{}", std::process::id(), WRITES.fetch_add(1, Ordering::Relaxed)));
+        fs::write(&partial, serde_json::to_vec(entry).map_err(io::Error::other)?)?;
+        fs::rename(partial, path)
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
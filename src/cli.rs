@@ -0,0 +1,312 @@
+use std::path::PathBuf;
+use std::time::Duration;
+use clap::{Args, Parser, Subcommand};
+use weather::model::{Location, PlaceQuery};
+use weather::output::OutputFormat;
+use weather::providers::ProviderKind;
+use weather::units::{PrecipitationUnit, PressureUnit, SpeedUnit, TemperatureUnit, UnitSystem};
+use crate::rules::SortKey;
+
+// Command-line definition for the weather tool
+#[derive(Parser, Debug)]
+#[command(
+    name = "weather",
+    version,
+    about = "Weather reports from OpenWeatherMap, Open-Meteo, MET Norway or the US NWS",
+    after_help = "Without a command, shows the current conditions for `default_location` from the config file, or starts the interactive prompt when there is none."
+)]
+pub struct Cli {
+    /// OpenWeatherMap API key (overrides the environment and config file)
+    #[arg(long, global = true, value_name = "KEY")]
+    pub api_key: Option<String>,
+    /// Path to the config file [default: $XDG_CONFIG_HOME/weather/config.toml]
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// Weather service to query [default: openweathermap, or `provider` in the config file]
+    #[arg(long, global = true, value_enum)]
+    pub provider: Option<ProviderKind>,
+    /// Unit system for readings [default: metric, or `units` in the config file]
+    #[arg(long, global = true, value_enum)]
+    pub units: Option<UnitSystem>,
+    /// Temperature unit, overriding the unit system
+    #[arg(long, global = true, value_enum)]
+    pub temperature_unit: Option<TemperatureUnit>,
+    /// Wind speed unit, overriding the unit system
+    #[arg(long, global = true, value_enum)]
+    pub wind_unit: Option<SpeedUnit>,
+    /// Pressure unit, overriding the unit system
+    #[arg(long, global = true, value_enum)]
+    pub pressure_unit: Option<PressureUnit>,
+    /// Precipitation unit, overriding the unit system
+    #[arg(long, global = true, value_enum)]
+    pub precipitation_unit: Option<PrecipitationUnit>,
+    /// Show cached reports instead of contacting the weather service, whatever their age
+    #[arg(long, global = true)]
+    pub offline: bool,
+    /// Output format; structured formats print errors as JSON on stderr [default: text]
+    #[arg(long, global = true, value_enum)]
+    pub output: Option<OutputFormat>,
+    /// Log requests to stderr (-v for debug, -vv for trace); WEATHER_LOG overrides
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Cli {
+    // Parses the process arguments. Negative coordinates such as -33.87,151.21 would read as short
+    // options, and allow_hyphen_values can't tell them apart in a list of locations, where it would
+    // also take every option after them; a leading space, which parsing a location trims, sets them apart
+    pub fn parse_args() -> Self {
+        Cli::parse_from(std::env::args_os().map(|arg| match arg.to_str() {
+            Some(text) if is_negative_coordinates(text) => format!(" {}", text).into(),
+            _ => arg,
+        }))
+    }
+}
+
+// Whether an argument looks like "lat,lon" with a negative latitude
+fn is_negative_coordinates(text: &str) -> bool {
+    text.strip_prefix('-')
+        .and_then(|rest| rest.split_once(','))
+        .is_some_and(|(lat, lon)| lat.trim().parse::<f64>().is_ok() && lon.trim().parse::<f64>().is_ok())
+}
+
+// Top-level subcommands; running without one shows the default location, or starts the
+// interactive prompt when none is configured
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Print the current conditions for a location and exit
+    Now(NowArgs),
+    /// Print the multi-day forecast for a location and exit
+    Forecast(ForecastArgs),
+    /// Print the air quality for a location with its forecast: pollutant levels, the provider's index and the US AQI
+    Air(AirArgs),
+    /// Keep the current conditions for a location on screen, updating them on an interval
+    Watch(WatchArgs),
+    /// Test the alert rules against the current conditions; exits with 3 when any rule fires
+    Check(CheckArgs),
+    /// Serve current conditions and forecasts as JSON over HTTP, sharing one key, cache and rate limit
+    Serve(ServeArgs),
+    /// Poll a list of locations and publish the readings as Prometheus metrics
+    Exporter(ExporterArgs),
+    /// Show a full-screen dashboard of saved locations with their current conditions and forecast
+    Tui(TuiArgs),
+    /// Prompt for locations in a loop
+    Interactive(InteractiveArgs),
+    /// Add, remove or list the locations saved in the config file, used as @NAME
+    #[command(subcommand)]
+    Locations(LocationsCommand),
+    /// Show the effective configuration
+    Config,
+    /// Show how much of the rate limit and call quota has been used
+    Quota,
+}
+
+// Actions of the locations subcommand
+#[derive(Subcommand, Debug)]
+pub enum LocationsCommand {
+    /// Save a location under a name, replacing any saved before under it
+    Add(AddLocationArgs),
+    /// Forget a saved location
+    #[command(alias = "remove")]
+    Rm {
+        /// Name of the saved location, with or without the @
+        name: String,
+    },
+    /// List the saved locations
+    #[command(alias = "list")]
+    Ls,
+}
+
+// Options of `locations add`
+#[derive(Args, Debug)]
+pub struct AddLocationArgs {
+    /// Name to use as @NAME: letters, digits, '-' and '_'
+    pub name: String,
+    #[command(flatten)]
+    pub location: LocationArgs,
+    /// Show this location when `weather` runs without arguments
+    #[arg(long)]
+    pub default: bool,
+}
+
+// Location selection shared by the one-shot subcommands
+#[derive(Args, Debug)]
+pub struct LocationArgs {
+    /// Place to look up: "city[, state][, country code]", "lat,lon", "zip:CODE[,CC]", "id:CITYID" or "@saved"
+    #[arg(value_name = "LOCATION", required_unless_present = "city", conflicts_with = "city", allow_hyphen_values = true)]
+    pub location: Option<Location>,
+    /// Name of the city, e.g. Berlin
+    #[arg(long)]
+    pub city: Option<String>,
+    /// State or region, to tell apart places with the same name
+    #[arg(long, requires = "city")]
+    pub state: Option<String>,
+    /// Country code, e.g. DE
+    #[arg(long, requires = "city")]
+    pub country: Option<String>,
+}
+
+impl LocationArgs {
+    // The location given either positionally or through --city/--state/--country
+    pub fn location(&self) -> Location {
+        match (&self.location, &self.city) {
+            (Some(location), _) => location.clone(),
+            (None, city) => Location::Named(PlaceQuery {
+                name: city.clone().unwrap_or_default(),
+                state: self.state.clone(),
+                country: self.country.clone(),
+            }),
+        }
+    }
+}
+
+// Options of the forecast subcommand
+#[derive(Args, Debug)]
+pub struct ForecastArgs {
+    #[command(flatten)]
+    pub location: LocationArgs,
+    /// List every forecast step under its day; with csv or ndjson, one record per step instead of per day
+    #[arg(long)]
+    pub hourly: bool,
+}
+
+// Options of the air subcommand
+#[derive(Args, Debug)]
+pub struct AirArgs {
+    #[command(flatten)]
+    pub location: LocationArgs,
+    /// List every forecast hour under its day
+    #[arg(long)]
+    pub hourly: bool,
+}
+
+// Options of the now subcommand; several locations are compared in one table
+#[derive(Args, Debug)]
+pub struct NowArgs {
+    /// Places to look up: "city[, state][, country code]", "lat,lon", "zip:CODE[,CC]", "id:CITYID" or "@saved"
+    #[arg(
+        value_name = "LOCATION",
+        required_unless_present_any = ["city", "from_file"],
+        conflicts_with = "city"
+    )]
+    pub locations: Vec<Location>,
+    /// Name of the city, e.g. Berlin
+    #[arg(long)]
+    pub city: Option<String>,
+    /// State or region, to tell apart places with the same name
+    #[arg(long, requires = "city")]
+    pub state: Option<String>,
+    /// Country code, e.g. DE
+    #[arg(long, requires = "city")]
+    pub country: Option<String>,
+    /// Also compare the locations in a CSV file with a `location` column, or `city`, `state` and `country`, `lat` and `lon`, `zip` or `id` columns
+    #[arg(long, value_name = "PATH")]
+    pub from_file: Option<PathBuf>,
+    /// Sort the comparison by a field, e.g. temp, humidity or wind_speed; prefix it with - to sort descending
+    #[arg(long, value_name = "FIELD", allow_hyphen_values = true)]
+    pub sort: Option<SortKey>,
+    /// Number of locations fetched at the same time
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    pub jobs: usize,
+    /// Also show feels-like, visibility, clouds, gusts, precipitation, sunrise and sunset; adds min/max, visibility, rain and snow columns to a comparison
+    #[arg(long)]
+    pub detailed: bool,
+}
+
+impl NowArgs {
+    // The locations given on the command line, either positionally or through --city/--state/--country
+    pub fn locations(&self) -> Vec<Location> {
+        match &self.city {
+            Some(city) => vec![Location::Named(PlaceQuery {
+                name: city.clone(),
+                state: self.state.clone(),
+                country: self.country.clone(),
+            })],
+            None => self.locations.clone(),
+        }
+    }
+}
+
+// Options of the watch subcommand
+#[derive(Args, Debug)]
+pub struct WatchArgs {
+    #[command(flatten)]
+    pub location: LocationArgs,
+    /// Time between updates, e.g. 30s, 10m or 1h
+    #[arg(long, value_name = "INTERVAL", default_value = "10m", value_parser = parse_interval)]
+    pub every: Duration,
+    /// Stop after this many updates instead of running until Ctrl-C
+    #[arg(long, value_name = "N")]
+    pub count: Option<u64>,
+    /// Also show feels-like, visibility, clouds, gusts, precipitation, sunrise and sunset
+    #[arg(long)]
+    pub detailed: bool,
+    /// Rules whose commands run when they fire [default: `rules_file` in the config file, or rules.toml next to it]
+    #[arg(long, value_name = "PATH")]
+    pub rules: Option<PathBuf>,
+}
+
+// Options of the check subcommand
+#[derive(Args, Debug)]
+pub struct CheckArgs {
+    #[command(flatten)]
+    pub location: LocationArgs,
+    /// Rules file to evaluate [default: `rules_file` in the config file, or rules.toml next to it]
+    #[arg(long, value_name = "PATH")]
+    pub rules: Option<PathBuf>,
+}
+
+// Parses a human-readable interval of at least one second
+fn parse_interval(text: &str) -> Result<Duration, String> {
+    let interval = humantime::parse_duration(text).map_err(|e| e.to_string())?;
+    if interval < Duration::from_secs(1) {
+        return Err("the interval must be at least 1s".to_string());
+    }
+    Ok(interval)
+}
+
+// Options of the serve subcommand
+#[derive(Args, Debug)]
+pub struct ServeArgs {
+    /// Address and port to listen on
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+    pub bind: String,
+    /// Number of requests answered at the same time
+    #[arg(long, value_name = "N", default_value_t = 8)]
+    pub workers: usize,
+}
+
+// Options of the exporter subcommand; each falls back to the [exporter] table of the config file
+#[derive(Args, Debug)]
+pub struct ExporterArgs {
+    /// Location to poll; repeat for several [default: `locations` in the config file]
+    #[arg(long = "location", value_name = "LOCATION", allow_hyphen_values = true)]
+    pub locations: Vec<Location>,
+    /// Time between two polls of every location, e.g. 5m [default: 10m]
+    #[arg(long, value_name = "INTERVAL", value_parser = parse_interval)]
+    pub every: Option<Duration>,
+    /// Address and port /metrics listens on [default: 127.0.0.1:9101]
+    #[arg(long, value_name = "ADDR")]
+    pub bind: Option<String>,
+}
+
+// Options of the tui subcommand
+#[derive(Args, Debug)]
+pub struct TuiArgs {
+    /// Locations to show below the saved ones, without saving them
+    #[arg(value_name = "LOCATION")]
+    pub locations: Vec<Location>,
+    /// Time between background refreshes of every location, e.g. 5m
+    #[arg(long, value_name = "INTERVAL", default_value = "10m", value_parser = parse_interval)]
+    pub every: Duration,
+}
+
+// Options of the interactive subcommand
+#[derive(Args, Debug, Default)]
+pub struct InteractiveArgs {
+    /// Show the expanded report for every location
+    #[arg(long)]
+    pub detailed: bool,
+}
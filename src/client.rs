@@ -0,0 +1,222 @@
+//! The client that looks locations up and fetches reports through the cache and the rate limiter.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::runtime::{self, Runtime};
+use crate::cache::{self, Cache};
+use crate::error::WeatherError;
+use crate::model::{AirQuality, Forecast, Location, Observation, Place};
+use crate::providers::WeatherProvider;
+use crate::quota::Limiter;
+
+/// Where a value came from
+#[derive(Debug)]
+pub enum Origin {
+    Fetched,                       // Straight from the provider, or needing no lookup at all
+    Cached,                        // From the cache while still fresh
+    Offline(Duration),             // From the cache in offline mode, whatever its age
+    Stale(Duration, WeatherError), // From an expired cache entry because fetching failed
+}
+
+/// A value from the client, with what the caller may want to tell the user about it
+#[derive(Debug)]
+pub struct Fetched<T> {
+    pub value: T,
+    pub origin: Origin,
+    pub quota_warning: Option<String>, // Raised when a daily or monthly limit is getting close
+}
+
+/// Looks locations up and fetches reports through the cache and the rate limiter. Every method is
+/// async; BlockingClient runs them for code that is not.
+pub struct WeatherClient {
+    provider: Box<dyn WeatherProvider>, // Backend the reports are fetched from
+    cache: Option<Cache>,               // Recently fetched reports, if there is a cache directory
+    offline: bool,                      // Serve everything from the cache, never touching the network
+    limiter: Option<Arc<Limiter>>,      // The provider's rate limiter, whose warnings come with the results
+    saved: BTreeMap<String, Location>,  // Locations from the config file, used as @name
+}
+
+impl WeatherClient {
+    /// `limiter` is the one the provider was built with, which takes every call it makes
+    pub fn new(
+        provider: Box<dyn WeatherProvider>,
+        cache: Option<Cache>,
+        offline: bool,
+        limiter: Option<Arc<Limiter>>,
+        saved: BTreeMap<String, Location>,
+    ) -> Self {
+        WeatherClient { provider, cache, offline, limiter, saved }
+    }
+
+    /// Name of the weather service in use, for messages
+    pub fn provider_name(&self) -> &'static str {
+        self.provider.name()
+    }
+
+    pub fn cache(&self) -> Option<&Cache> {
+        self.cache.as_ref()
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline
+    }
+
+    /// Looks up what a saved @name stands for; other locations stand for themselves
+    pub fn resolve<'a>(&'a self, location: &'a Location) -> Result<&'a Location, WeatherError> {
+        let Location::Saved(name) = location else {
+            return Ok(location);
+        };
+        self.saved.get(name).ok_or_else(|| WeatherError::NotFound {
+            message: format!("no saved location named \"{}\"; `weather locations ls` lists them", name),
+        })
+    }
+
+    /// Looks up the places matching a location, failing when there are none
+    pub async fn locate(&self, location: &Location) -> Result<Fetched<Vec<Place>>, WeatherError> {
+        let location = self.resolve(location)?;
+        let fetched = match location {
+            // Coordinates need no lookup, so they work offline even when nothing is cached
+            Location::Coordinates(at) => Fetched { value: vec![Place::at(*at)], origin: Origin::Fetched, quota_warning: None },
+            _ => {
+                let key = Cache::location_key(location);
+                let what = format!("lookup of \"{}\"", location);
+                self.cached(&key, cache::LOCATION_TTL, &what, || self.provider.locate(location)).await?
+            }
+        };
+        if fetched.value.is_empty() {
+            return Err(WeatherError::NotFound { message: format!("no place matches \"{}\"", location) });
+        }
+        Ok(fetched)
+    }
+
+    /// Looks up the one place a location stands for, failing when it is ambiguous
+    pub async fn place(&self, location: &Location) -> Result<Fetched<Place>, WeatherError> {
+        let Fetched { mut value, origin, quota_warning } = self.locate(location).await?;
+        if value.len() > 1 {
+            return Err(WeatherError::Ambiguous { query: location.to_string(), candidates: value });
+        }
+        Ok(Fetched { value: value.remove(0), origin, quota_warning })
+    }
+
+    /// Retrieves the current conditions at a geocoded place, from the cache while they are fresh
+    pub async fn current(&self, place: &Place) -> Result<Fetched<Observation>, WeatherError> {
+        let what = format!("report for {}", place);
+        self.cached(&Cache::current_key(place), self.ttl(), &what, || self.provider.current(place)).await
+    }
+
+    /// Retrieves the multi-day forecast, from the cache while it is fresh
+    pub async fn forecast(&self, place: &Place) -> Result<Fetched<Forecast>, WeatherError> {
+        let what = format!("forecast for {}", place);
+        self.cached(&Cache::forecast_key(place), self.ttl(), &what, || self.provider.forecast(place)).await
+    }
+
+    /// Retrieves the current air pollution at a geocoded place, from the cache while it is fresh
+    pub async fn air_quality(&self, place: &Place) -> Result<Fetched<AirQuality>, WeatherError> {
+        let what = format!("air quality for {}", place);
+        self.cached(&Cache::air_key(place), self.ttl(), &what, || self.provider.air_quality(place)).await
+    }
+
+    /// Retrieves the air pollution forecast, from the cache while it is fresh
+    pub async fn air_forecast(&self, place: &Place) -> Result<Fetched<AirQuality>, WeatherError> {
+        let what = format!("air quality forecast for {}", place);
+        self.cached(&Cache::air_forecast_key(place), self.ttl(), &what, || self.provider.air_forecast(place)).await
+    }
+
+    // How long reports count as fresh
+    fn ttl(&self) -> Duration {
+        self.cache.as_ref().map_or(Duration::ZERO, |cache| cache.ttl)
+    }
+
+    // Serves a cache entry younger than `ttl`, or fetches and stores a new one. When fetching fails
+    // for a reason that may pass, an expired entry is served instead. Offline, any entry will do;
+    // `what` names the data in the error when there is none.
+    async fn cached<T, F>(&self, key: &str, ttl: Duration, what: &str, fetch: impl FnOnce() -> F) -> Result<Fetched<T>, WeatherError>
+    where
+        T: Serialize + DeserializeOwned,
+        F: Future<Output = Result<T, WeatherError>>,
+    {
+        let entry = self.cache.as_ref().and_then(|cache| cache.get::<T>(key));
+        if self.offline {
+            let entry = entry.ok_or_else(|| WeatherError::NotCached { what: what.to_string() })?;
+            let age = entry.age();
+            return Ok(Fetched { value: entry.value, origin: Origin::Offline(age), quota_warning: None });
+        }
+        let entry = match entry {
+            Some(entry) if entry.age() < ttl => {
+                let age = entry.age();
+                tracing::info!(key, ?age, "serving from cache");
+                return Ok(Fetched { value: entry.value, origin: Origin::Cached, quota_warning: None });
+            }
+            entry => entry,
+        };
+
+        let fetched = fetch().await;
+        let quota_warning = self.limiter.as_ref().and_then(|limiter| limiter.take_warning());
+        match fetched {
+            Ok(value) => {
+                if let Some(cache) = &self.cache {
+                    cache.put(key, &value);
+                }
+                Ok(Fetched { value, origin: Origin::Fetched, quota_warning })
+            }
+            Err(e) if e.is_transient() => match entry {
+                Some(entry) => {
+                    let age = entry.age();
+                    Ok(Fetched { value: entry.value, origin: Origin::Stale(age, e), quota_warning })
+                }
+                None => Err(e),
+            },
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Runs the async client to completion for callers outside the runtime: the prompt, watch mode and
+/// the threads of the gateway, the exporter and the dashboard. They all share one runtime, and
+/// through it one connection pool.
+pub struct BlockingClient {
+    client: WeatherClient,
+    runtime: Runtime,
+}
+
+impl BlockingClient {
+    pub fn new(client: WeatherClient) -> io::Result<Self> {
+        let runtime = runtime::Builder::new_multi_thread().enable_all().thread_name("weather-io").build()?;
+        Ok(BlockingClient { client, runtime })
+    }
+
+    /// The async client, for callers that run several lookups at once with `block_on`
+    pub fn client(&self) -> &WeatherClient {
+        &self.client
+    }
+
+    /// Runs a future on the shared runtime; must not be called from inside it
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    pub fn locate(&self, location: &Location) -> Result<Fetched<Vec<Place>>, WeatherError> {
+        self.block_on(self.client.locate(location))
+    }
+
+    pub fn current(&self, place: &Place) -> Result<Fetched<Observation>, WeatherError> {
+        self.block_on(self.client.current(place))
+    }
+
+    pub fn forecast(&self, place: &Place) -> Result<Fetched<Forecast>, WeatherError> {
+        self.block_on(self.client.forecast(place))
+    }
+
+    pub fn air_quality(&self, place: &Place) -> Result<Fetched<AirQuality>, WeatherError> {
+        self.block_on(self.client.air_quality(place))
+    }
+
+    pub fn air_forecast(&self, place: &Place) -> Result<Fetched<AirQuality>, WeatherError> {
+        self.block_on(self.client.air_forecast(place))
+    }
+}
@@ -0,0 +1,383 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+use serde::Deserialize;
+use thiserror::Error;
+use toml_edit::{DocumentMut, InlineTable};
+use weather::http::HttpSettings;
+use weather::model::{Location, PlaceQuery};
+use weather::providers::{Endpoints, ProviderKind};
+use weather::quota::QuotaSettings;
+use weather::redact::ApiKey;
+use weather::units::{PrecipitationUnit, PressureUnit, SpeedUnit, TemperatureUnit, UnitSystem};
+use crate::exporter::ExporterSettings;
+
+// Environment variable consulted for the API key after the --api-key flag
+pub const API_KEY_ENV: &str = "OPENWEATHER_API_KEY";
+
+// Settings read from the TOML config file
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub api_key: Option<ApiKey>,                       // Key stored inline in the config file
+    pub key_file: Option<PathBuf>,                     // File whose first line is the key
+    pub key_command: Option<String>,                   // Shell command whose stdout is the key
+    pub base_url: Option<String>,                      // Alternative OpenWeatherMap endpoint, short for endpoints.openweathermap
+    pub endpoints: Endpoints,                          // Alternative endpoint of every service, the [endpoints] table
+    pub provider: Option<ProviderKind>,                // Weather service used when --provider is not given
+    pub units: Option<UnitSystem>,                     // Unit system used when --units is not given
+    pub temperature_unit: Option<TemperatureUnit>,     // Overrides the system's temperature unit
+    pub wind_unit: Option<SpeedUnit>,                  // Overrides the system's wind speed unit
+    pub pressure_unit: Option<PressureUnit>,           // Overrides the system's pressure unit
+    pub precipitation_unit: Option<PrecipitationUnit>, // Overrides the system's precipitation unit
+    #[serde(with = "humantime_serde")]
+    pub cache_ttl: Option<Duration>,                   // How long fetched reports are reused, e.g. "10m"
+    pub quota: QuotaSettings,                          // Rate limit and call budget, the [quota] table
+    pub http: HttpSettings,                            // Timeouts, retries and proxy, the [http] table
+    pub rules_file: Option<PathBuf>,                   // Alert rules for `check` and `watch`, instead of rules.toml
+    pub exporter: ExporterSettings,                    // Locations and interval of `exporter`, the [exporter] table
+    pub default_location: Option<String>,              // Saved location shown by `weather` without arguments
+    pub locations: BTreeMap<String, SavedLocation>,    // Locations used as @name, the [locations] table
+}
+
+impl Config {
+    // Endpoints to send requests to; the [endpoints] table wins over base_url
+    pub fn endpoints(&self) -> Endpoints {
+        Endpoints {
+            openweathermap: self.endpoints.openweathermap.clone().or_else(|| self.base_url.clone()),
+            ..self.endpoints.clone()
+        }
+    }
+}
+
+// Fields of an entry in the [locations] table, such as {city = "Amsterdam", country = "NL"}
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SavedFields {
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub country: Option<String>,
+    pub lat: Option<f64>,
+    pub lon: Option<f64>,
+    pub zip: Option<String>,
+    pub id: Option<u64>,
+}
+
+// A location saved under a name: a city with optional state and country, coordinates,
+// a postal code with optional country, or a city ID
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(try_from = "SavedFields")]
+pub struct SavedLocation(pub Location);
+
+impl TryFrom<SavedFields> for SavedLocation {
+    type Error = String;
+
+    fn try_from(fields: SavedFields) -> Result<Self, Self::Error> {
+        let location = match fields {
+            SavedFields { city: Some(name), state, country, lat: None, lon: None, zip: None, id: None } if !name.trim().is_empty() => {
+                Location::Named(PlaceQuery { name, state, country })
+            }
+            SavedFields { lat: Some(lat), lon: Some(lon), city: None, state: None, country: None, zip: None, id: None } => {
+                format!("{},{}", lat, lon).parse()?
+            }
+            SavedFields { zip: Some(code), country, city: None, state: None, lat: None, lon: None, id: None } => {
+                Location::Postal { code, country }
+            }
+            SavedFields { id: Some(id), city: None, state: None, country: None, lat: None, lon: None, zip: None } => {
+                Location::CityId(id)
+            }
+            _ => return Err("a saved location needs `city` (with optional `state` and `country`), `lat` and `lon`, `zip` (with optional `country`) or `id`".to_string()),
+        };
+        Ok(SavedLocation(location))
+    }
+}
+
+impl SavedLocation {
+    // The entry written to the [locations] table; saved locations cannot point at each other
+    pub fn table(location: &Location) -> Option<InlineTable> {
+        let mut table = InlineTable::new();
+        let mut optional = |key: &str, value: &Option<String>| {
+            if let Some(value) = value {
+                table.insert(key, value.as_str().into());
+            }
+        };
+        match location {
+            Location::Named(query) => {
+                optional("city", &Some(query.name.clone()));
+                optional("state", &query.state);
+                optional("country", &query.country);
+            }
+            Location::Postal { code, country } => {
+                optional("zip", &Some(code.clone()));
+                optional("country", country);
+            }
+            Location::Coordinates(at) => {
+                table.insert("lat", at.latitude.into());
+                table.insert("lon", at.longitude.into());
+            }
+            Location::CityId(id) => {
+                table.insert("id", (*id as i64).into());
+            }
+            Location::Saved(_) => return None,
+        }
+        Some(table)
+    }
+}
+
+// Errors raised while loading the config file or resolving the API key
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("could not read config file {}: {source}", path.display())]
+    Read { path: PathBuf, source: std::io::Error },
+    #[error("invalid config file {}: {source}", path.display())]
+    Parse { path: PathBuf, source: toml::de::Error },
+    #[error("could not read key_file {}: {source}", path.display())]
+    KeyFile { path: PathBuf, source: std::io::Error },
+    #[error("key_command `{command}` failed: {reason}")]
+    KeyCommand { command: String, reason: String },
+    #[error("no OpenWeatherMap API key found; looked in:\n{}", searched.iter().map(|place| format!("  - {}", place)).collect::<Vec<_>>().join("\n"))]
+    MissingKey { searched: Vec<String> },
+    #[error("could not write config file {}: {source}", path.display())]
+    Write { path: PathBuf, source: std::io::Error },
+    #[error("invalid config file {}: {source}", path.display())]
+    Edit { path: PathBuf, source: toml_edit::TomlError },
+    #[error("there is no config directory on this platform; pass --config")]
+    NoConfigDir,
+}
+
+impl ConfigError {
+    // Stable identifier for scripts, used in structured error output
+    pub fn code(&self) -> &'static str {
+        match self {
+            ConfigError::Read { .. } => "config_read",
+            ConfigError::Parse { .. } | ConfigError::Edit { .. } => "config_parse",
+            ConfigError::Write { .. } | ConfigError::NoConfigDir => "config_write",
+            ConfigError::KeyFile { .. } => "key_file",
+            ConfigError::KeyCommand { .. } => "key_command",
+            ConfigError::MissingKey { .. } => "missing_key",
+        }
+    }
+}
+
+// Where the resolved API key came from
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeySource {
+    Flag,
+    Environment,
+    KeyFile(PathBuf),
+    KeyCommand(String),
+    ConfigFile(PathBuf),
+}
+
+impl fmt::Display for KeySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeySource::Flag => write!(f, "--api-key flag"),
+            KeySource::Environment => write!(f, "{} environment variable", API_KEY_ENV),
+            KeySource::KeyFile(path) => write!(f, "key_file {}", path.display()),
+            KeySource::KeyCommand(command) => write!(f, "key_command `{}`", command),
+            KeySource::ConfigFile(path) => write!(f, "api_key in {}", path.display()),
+        }
+    }
+}
+
+// An API key together with the place it was found
+#[derive(Debug)]
+pub struct ResolvedKey {
+    pub key: ApiKey,
+    pub source: KeySource,
+}
+
+// The loaded config together with the path it was (or would have been) read from
+#[derive(Debug)]
+pub struct LoadedConfig {
+    pub path: Option<PathBuf>,
+    pub exists: bool,
+    pub config: Config,
+}
+
+impl LoadedConfig {
+    // Loads the config from an explicit path or the default XDG location.
+    // A missing default file is not an error; a missing explicit file is.
+    pub fn load(explicit: Option<&Path>) -> Result<Self, ConfigError> {
+        let path = match explicit {
+            Some(path) => Some(path.to_path_buf()),
+            None => default_config_path(),
+        };
+        let Some(path) = path else {
+            return Ok(LoadedConfig { path: None, exists: false, config: Config::default() });
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && explicit.is_none() => {
+                return Ok(LoadedConfig { path: Some(path), exists: false, config: Config::default() });
+            }
+            Err(source) => return Err(ConfigError::Read { path, source }),
+        };
+        let config = toml::from_str(&contents).map_err(|source| ConfigError::Parse { path: path.clone(), source })?;
+
+        Ok(LoadedConfig { path: Some(path), exists: true, config })
+    }
+
+    // Resolves the API key in priority order: flag, environment, key_file, key_command, config file
+    pub fn resolve_api_key(&self, flag: Option<&str>) -> Result<ResolvedKey, ConfigError> {
+        if let Some(key) = non_empty(flag) {
+            return Ok(ResolvedKey { key, source: KeySource::Flag });
+        }
+        if let Some(key) = non_empty(std::env::var(API_KEY_ENV).ok().as_deref()) {
+            return Ok(ResolvedKey { key, source: KeySource::Environment });
+        }
+        if let Some(path) = &self.config.key_file {
+            let path = expand_home(path);
+            let contents = fs::read_to_string(&path).map_err(|source| ConfigError::KeyFile { path: path.clone(), source })?;
+            if let Some(key) = non_empty(contents.lines().next()) {
+                return Ok(ResolvedKey { key, source: KeySource::KeyFile(path) });
+            }
+        }
+        if let Some(command) = &self.config.key_command {
+            if let Some(key) = non_empty(Some(&run_key_command(command)?)) {
+                return Ok(ResolvedKey { key, source: KeySource::KeyCommand(command.clone()) });
+            }
+        }
+        if let Some(key) = non_empty(self.config.api_key.as_ref().map(ApiKey::expose)) {
+            let path = self.path.clone().unwrap_or_default();
+            return Ok(ResolvedKey { key, source: KeySource::ConfigFile(path) });
+        }
+
+        Err(ConfigError::MissingKey { searched: self.searched_locations() })
+    }
+
+    // Saves a location under `name`, replacing any saved before under that name, and optionally
+    // makes it the default. Returns whether it replaced one. The loaded config follows the file,
+    // so that later changes in the same run see this one.
+    pub fn save_location(&mut self, name: &str, location: &Location, default: bool) -> Result<bool, ConfigError> {
+        let table = SavedLocation::table(location).expect("saved locations never point at other saved locations");
+        let replaced = self.edit(|document| {
+            let locations = document.entry("locations").or_insert(toml_edit::table());
+            let replaced = locations.as_table_like_mut().expect("[locations] parsed as a table").insert(name, toml_edit::value(table)).is_some();
+            if default {
+                document["default_location"] = toml_edit::value(name);
+            }
+            replaced
+        })?;
+        self.config.locations.insert(name.to_string(), SavedLocation(location.clone()));
+        if default {
+            self.config.default_location = Some(name.to_string());
+        }
+        Ok(replaced)
+    }
+
+    // Forgets the location saved under `name`, and the default if it was that one.
+    // Returns whether there was one.
+    pub fn remove_location(&mut self, name: &str) -> Result<bool, ConfigError> {
+        if !self.config.locations.contains_key(name) {
+            return Ok(false);
+        }
+        self.edit(|document| {
+            if let Some(locations) = document.get_mut("locations").and_then(toml_edit::Item::as_table_like_mut) {
+                locations.remove(name);
+            }
+            if document.get("default_location").and_then(toml_edit::Item::as_str) == Some(name) {
+                document.remove("default_location");
+            }
+            true
+        })?;
+        self.config.locations.remove(name);
+        if self.config.default_location.as_deref() == Some(name) {
+            self.config.default_location = None;
+        }
+        Ok(true)
+    }
+
+    // Rewrites the config file, keeping its comments and layout; creates it when there is none yet
+    fn edit(&mut self, change: impl FnOnce(&mut DocumentMut) -> bool) -> Result<bool, ConfigError> {
+        let Some(path) = &self.path else {
+            return Err(ConfigError::NoConfigDir);
+        };
+        let text = match self.exists {
+            true => fs::read_to_string(path).map_err(|source| ConfigError::Read { path: path.clone(), source })?,
+            false => String::new(),
+        };
+        let mut document: DocumentMut = text.parse().map_err(|source| ConfigError::Edit { path: path.clone(), source })?;
+        let changed = change(&mut document);
+
+        let written = path.parent().map_or(Ok(()), fs::create_dir_all).and_then(|()| fs::write(path, document.to_string()));
+        written.map_err(|source| ConfigError::Write { path: path.clone(), source })?;
+        self.exists = true;
+        Ok(changed)
+    }
+
+    // Describes every place a key was looked for, used when none is found
+    fn searched_locations(&self) -> Vec<String> {
+        let mut searched = vec![
+            "--api-key flag (not given)".to_string(),
+            format!("{} environment variable (not set)", API_KEY_ENV),
+        ];
+
+        let path = match &self.path {
+            Some(path) if self.exists => path.display(),
+            Some(path) => {
+                searched.push(format!("config file {} (not found)", path.display()));
+                return searched;
+            }
+            None => {
+                searched.push("config file (no config directory on this platform)".to_string());
+                return searched;
+            }
+        };
+        searched.push(match &self.config.key_file {
+            Some(key_file) => format!("key_file {} (empty)", expand_home(key_file).display()),
+            None => format!("key_file setting in {} (not set)", path),
+        });
+        searched.push(match &self.config.key_command {
+            Some(command) => format!("key_command `{}` (printed nothing)", command),
+            None => format!("key_command setting in {} (not set)", path),
+        });
+        searched.push(format!("api_key setting in {} (not set)", path));
+        searched
+    }
+}
+
+// Default config file location, e.g. ~/.config/weather/config.toml
+pub fn default_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("weather").join("config.toml"))
+}
+
+// Prepares a command line to run through the platform shell
+pub fn shell_command(command: &str) -> Command {
+    let mut shell = if cfg!(windows) { Command::new("cmd") } else { Command::new("sh") };
+    shell.args([if cfg!(windows) { "/C" } else { "-c" }, command]);
+    shell
+}
+
+// Runs key_command through the platform shell and returns its trimmed stdout
+fn run_key_command(command: &str) -> Result<String, ConfigError> {
+    let output = shell_command(command).output();
+    let output = output.map_err(|e| ConfigError::KeyCommand { command: command.to_string(), reason: e.to_string() })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(ConfigError::KeyCommand {
+            command: command.to_string(),
+            reason: format!("{} {}", output.status, stderr.trim()).trim_end().to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// Expands a leading `~/` to the user's home directory
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), dirs::home_dir()) {
+        (Ok(rest), Some(home)) => home.join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+// Trims a candidate key, treating blank values as absent
+fn non_empty(value: Option<&str>) -> Option<ApiKey> {
+    value.map(str::trim).filter(|value| !value.is_empty()).map(ApiKey::new)
+}
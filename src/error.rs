@@ -0,0 +1,141 @@
+//! What can go wrong while fetching, and how provider error responses map onto it.
+
+use std::time::Duration;
+use reqwest::{Response, StatusCode};
+use serde::Deserialize;
+use thiserror::Error;
+use crate::model::Place;
+use crate::redact;
+
+/// Everything that can go wrong while fetching a weather report
+#[derive(Error, Debug)]
+pub enum WeatherError {
+    #[error("API key rejected: {message}")]
+    Auth { message: String },
+    #[error("location not found: {message}")]
+    NotFound { message: String },
+    #[error("\"{query}\" matches {} places", candidates.len())]
+    Ambiguous { query: String, candidates: Vec<Place> },
+    #[error("rate limited by the API{}", retry_after.map(|d| format!(", retry after {}s", d.as_secs())).unwrap_or_default())]
+    RateLimited { retry_after: Option<Duration> },
+    #[error("network error: {0}")]
+    Network(#[source] reqwest::Error),
+    #[error("request timed out: {0}")]
+    Timeout(#[source] reqwest::Error),
+    #[error("could not decode the API response: {0}")]
+    Decode(#[source] serde_json::Error),
+    #[error("API error {code}: {message}")]
+    Provider { code: String, message: String },
+    #[error("{feature} is not offered by this provider")]
+    Unsupported { feature: &'static str },
+    #[error("offline and no cached {what}")]
+    NotCached { what: String },
+    #[error("{period} quota used up: {used} of {limit} calls")]
+    QuotaExceeded { period: &'static str, used: u64, limit: u64 },
+}
+
+impl WeatherError {
+    /// Stable identifier for scripts, used in structured error output
+    pub fn code(&self) -> &'static str {
+        match self {
+            WeatherError::Auth { .. } => "auth",
+            WeatherError::NotFound { .. } => "not_found",
+            WeatherError::Ambiguous { .. } => "ambiguous",
+            WeatherError::RateLimited { .. } => "rate_limited",
+            WeatherError::Network(_) => "network",
+            WeatherError::Timeout(_) => "timeout",
+            WeatherError::Decode(_) => "decode",
+            WeatherError::Provider { .. } => "provider",
+            WeatherError::Unsupported { .. } => "unsupported",
+            WeatherError::NotCached { .. } => "not_cached",
+            WeatherError::QuotaExceeded { .. } => "quota_exceeded",
+        }
+    }
+
+    /// Failures that may go away on their own: the network, timeouts, rate limits, quotas and server errors
+    pub fn is_transient(&self) -> bool {
+        match self {
+            WeatherError::Network(_) | WeatherError::Timeout(_) | WeatherError::RateLimited { .. } => true,
+            WeatherError::QuotaExceeded { .. } => true,
+            WeatherError::Provider { code, .. } => code.len() == 3 && code.starts_with('5'),
+            _ => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for WeatherError {
+    fn from(error: reqwest::Error) -> Self {
+        let error = redact::redact_error(error);
+        if error.is_timeout() {
+            WeatherError::Timeout(error)
+        } else {
+            WeatherError::Network(error)
+        }
+    }
+}
+
+// Error body sent by a provider. OpenWeatherMap sends {"cod":"404","message":"city not found"},
+// where `cod` is a string on some endpoints and a number on others; Open-Meteo uses `reason`
+// and the NWS uses `detail` for the message.
+#[derive(Deserialize, Debug)]
+struct ApiErrorBody {
+    cod: Option<serde_json::Value>,
+    #[serde(alias = "reason", alias = "detail", default)]
+    message: String,
+}
+
+impl ApiErrorBody {
+    fn code(&self) -> Option<String> {
+        match self.cod.as_ref()? {
+            serde_json::Value::String(code) => Some(code.clone()),
+            other => Some(other.to_string()),
+        }
+    }
+}
+
+/// Reads a response body, mapping error statuses and error bodies onto WeatherError
+pub async fn read_json<T: serde::de::DeserializeOwned>(response: Response) -> Result<T, WeatherError> {
+    let status = response.status();
+    let retry_after = retry_after(&response);
+    let body = response.text().await?;
+
+    if status.is_success() {
+        return serde_json::from_str(&body).map_err(|decode_error| {
+            // Some endpoints report failures with a 200 status and an error body
+            match serde_json::from_str::<ApiErrorBody>(&body) {
+                Ok(api_error) if api_error.code().is_some_and(|code| code != "200") => {
+                    from_status(status, retry_after, Some(api_error))
+                }
+                _ => WeatherError::Decode(decode_error),
+            }
+        });
+    }
+
+    Err(from_status(status, retry_after, serde_json::from_str(&body).ok()))
+}
+
+// Picks the WeatherError variant for an error status, preferring the code in the body
+fn from_status(status: StatusCode, retry_after: Option<Duration>, body: Option<ApiErrorBody>) -> WeatherError {
+    let code = body.as_ref().and_then(ApiErrorBody::code).unwrap_or_else(|| status.as_u16().to_string());
+    let message = body
+        .map(|body| redact::redact(&body.message))
+        .filter(|message| !message.is_empty())
+        .unwrap_or_else(|| status.canonical_reason().unwrap_or("unknown error").to_string());
+
+    match code.as_str() {
+        "401" => WeatherError::Auth { message },
+        "404" => WeatherError::NotFound { message },
+        "429" => WeatherError::RateLimited { retry_after },
+        _ => WeatherError::Provider { code, message },
+    }
+}
+
+/// Parses a Retry-After header, given either in seconds or as an HTTP date
+pub fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(reqwest::header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some((at.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().unwrap_or_default())
+}
@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use chrono::Utc;
+use serde::Deserialize;
+use tiny_http::{Header, Method, Response, Server};
+use weather::model::{Location, Observation, PlaceQuery};
+use crate::app::{UserInteraction, WeatherApp};
+
+// Settings from the [exporter] table of the config file; the command-line flags take precedence
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExporterSettings {
+    pub locations: Vec<String>, // Locations to poll, in any format the command line accepts
+    #[serde(with = "humantime_serde")]
+    pub interval: Duration,     // Time between two polls of every location
+    pub bind: String,           // Address the /metrics endpoint listens on
+}
+
+impl Default for ExporterSettings {
+    fn default() -> Self {
+        ExporterSettings {
+            locations: Vec::new(),
+            interval: Duration::from_secs(10 * 60),
+            bind: "127.0.0.1:9101".to_string(),
+        }
+    }
+}
+
+// The locations to poll: those given with --location, or else the configured ones. Two locations
+// with the same labels would write the same series, so they are refused.
+pub fn locations(flags: &[Location], settings: &ExporterSettings) -> Result<Vec<Location>, String> {
+    let locations = if !flags.is_empty() {
+        flags.to_vec()
+    } else if settings.locations.is_empty() {
+        return Err("no locations to poll; pass --location or list them under `locations` in the [exporter] table".to_string());
+    } else {
+        settings
+            .locations
+            .iter()
+            .map(|text| text.parse::<Location>().map_err(|e| format!("invalid location \"{}\" in the [exporter] table: {}", text, e)))
+            .collect::<Result<_, _>>()?
+    };
+    let mut seen = BTreeMap::new();
+    for location in &locations {
+        let (city, country) = labels(location);
+        if let Some(first) = seen.insert((city.clone(), country.clone()), location) {
+            return Err(format!(
+                "\"{}\" and \"{}\" would both be published as city=\"{}\",country=\"{}\"; list each location once",
+                first, location, city, country
+            ));
+        }
+    }
+    Ok(locations)
+}
+
+// What the last polls of one location found
+#[derive(Debug, Default)]
+struct Target {
+    city: String,                            // Label values, fixed at startup
+    country: String,
+    reading: Option<Observation>,            // Latest successful reading
+    up: Option<bool>,                        // Whether the latest poll succeeded, once there was one
+    last_success: Option<f64>,               // Unix time of the latest successful poll
+    poll_seconds: f64,                       // How long the latest poll took
+    polls: u64,                              // Polls so far
+    errors: BTreeMap<&'static str, u64>,     // Failed polls by error code
+}
+
+// Polls every location on an interval and serves the readings as Prometheus metrics
+pub fn run(app: Arc<WeatherApp>, locations: Vec<Location>, interval: Duration, bind: &str) -> ExitCode {
+    let server = match Server::http(bind) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("Could not listen on {}: {}", bind, e);
+            return ExitCode::from(2);
+        }
+    };
+    match server.server_addr().to_ip() {
+        Some(address) => println!("Listening on http://{}/metrics", address),
+        None => println!("Listening on {}", bind),
+    }
+
+    let targets: Arc<Vec<Mutex<Target>>> = Arc::new(
+        locations
+            .iter()
+            .map(|location| {
+                let (city, country) = labels(location);
+                Mutex::new(Target { city, country, ..Target::default() })
+            })
+            .collect(),
+    );
+    let polled = Arc::clone(&targets);
+    thread::spawn(move || loop {
+        for (location, target) in locations.iter().zip(polled.iter()) {
+            poll(&app, location, target);
+        }
+        thread::sleep(interval);
+    });
+
+    let mut scrapes = 0u64;
+    for request in server.incoming_requests() {
+        let response = match (request.method(), request.url()) {
+            (Method::Get, "/metrics") => {
+                scrapes += 1;
+                Response::from_string(render(&targets, scrapes))
+                    .with_header(Header::from_bytes("Content-Type", "text/plain; version=0.0.4").expect("valid header"))
+            }
+            (Method::Get, "/") => Response::from_string("weather exporter; metrics are at /metrics\n"),
+            _ => Response::from_string("not found\n").with_status_code(404),
+        };
+        if let Err(e) = request.respond(response) {
+            tracing::warn!(error = %e, "could not send the response");
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+// The city and country labels of a location, taken from how it was configured. They never change
+// afterwards: a series that changed labels would be a new one to Prometheus, its counters reset.
+fn labels(location: &Location) -> (String, String) {
+    match location {
+        Location::Named(PlaceQuery { name, state: Some(state), country }) => {
+            (format!("{}, {}", name, state), country.as_deref().unwrap_or_default().to_uppercase())
+        }
+        Location::Named(PlaceQuery { name, state: None, country }) => (name.clone(), country.as_deref().unwrap_or_default().to_uppercase()),
+        Location::Postal { code, country } => (code.clone(), country.as_deref().unwrap_or_default().to_uppercase()),
+        other => (other.to_string(), String::new()),
+    }
+}
+
+// Fetches one location through the cache and the rate limiter and records the outcome
+fn poll(app: &WeatherApp, location: &Location, target: &Mutex<Target>) {
+    let started = Instant::now();
+    let result = UserInteraction::pick_place(app, location, false)
+        .map(|place| place.expect("non-interactive lookups never cancel"))
+        .and_then(|place| app.obtain_weather(&place));
+
+    let mut target = target.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    target.polls += 1;
+    target.poll_seconds = started.elapsed().as_secs_f64();
+    match result {
+        Ok(reading) => {
+            target.reading = Some(reading);
+            target.up = Some(true);
+            target.last_success = Some(Utc::now().timestamp_millis() as f64 / 1000.0);
+        }
+        Err(e) => {
+            tracing::warn!(location = %location, error = %e, "poll failed");
+            target.up = Some(false);
+            *target.errors.entry(e.code()).or_default() += 1;
+        }
+    }
+}
+
+// Writes metrics in the Prometheus text exposition format
+struct Exposition(String);
+
+impl Exposition {
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.0, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(name, value)| format!("{}=\"{}\"", name, value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")))
+            .collect();
+        if labels.is_empty() {
+            let _ = writeln!(self.0, "{} {}", name, value);
+        } else {
+            let _ = writeln!(self.0, "{}{{{}}} {}", name, labels.join(","), value);
+        }
+    }
+}
+
+// Picks one reading out of an observation
+type Reading = fn(&Observation) -> Option<f64>;
+
+// Readings published per location, always in metric units
+const READINGS: [(&str, &str, Reading); 6] = [
+    ("weather_temperature_celsius", "Air temperature", |info| Some(info.temperature)),
+    ("weather_feels_like_celsius", "Apparent temperature", |info| info.feels_like),
+    ("weather_humidity_percent", "Relative humidity", |info| info.humidity),
+    ("weather_pressure_hpa", "Atmospheric pressure", |info| info.pressure),
+    ("weather_wind_speed_meters_per_second", "Wind speed", |info| info.wind_speed),
+    ("weather_wind_gust_meters_per_second", "Wind gust speed", |info| info.wind_gust),
+];
+
+fn render(targets: &[Mutex<Target>], scrapes: u64) -> String {
+    let targets: Vec<_> = targets.iter().map(|target| target.lock().unwrap_or_else(|poisoned| poisoned.into_inner())).collect();
+    let mut out = Exposition(String::new());
+
+    for (name, help, read) in READINGS {
+        out.family(name, "gauge", help);
+        for target in &targets {
+            if let Some(value) = target.reading.as_ref().and_then(read) {
+                out.sample(name, &[("city", &target.city), ("country", &target.country)], value);
+            }
+        }
+    }
+    out.family("weather_observation_timestamp_seconds", "gauge", "When the provider observed the latest reading");
+    for target in &targets {
+        if let Some(observed_at) = target.reading.as_ref().and_then(|reading| reading.observed_at) {
+            out.sample("weather_observation_timestamp_seconds", &[("city", &target.city), ("country", &target.country)], observed_at.timestamp() as f64);
+        }
+    }
+
+    out.family("weather_up", "gauge", "Whether the latest poll of the location succeeded");
+    for target in &targets {
+        if let Some(up) = target.up {
+            out.sample("weather_up", &[("city", &target.city), ("country", &target.country)], if up { 1.0 } else { 0.0 });
+        }
+    }
+    out.family("weather_last_success_timestamp_seconds", "gauge", "When the location was last polled successfully");
+    for target in &targets {
+        if let Some(at) = target.last_success {
+            out.sample("weather_last_success_timestamp_seconds", &[("city", &target.city), ("country", &target.country)], at);
+        }
+    }
+    out.family("weather_poll_duration_seconds", "gauge", "How long the latest poll of the location took");
+    for target in targets.iter().filter(|target| target.polls > 0) {
+        out.sample("weather_poll_duration_seconds", &[("city", &target.city), ("country", &target.country)], target.poll_seconds);
+    }
+    out.family("weather_polls_total", "counter", "Polls of the location");
+    for target in &targets {
+        out.sample("weather_polls_total", &[("city", &target.city), ("country", &target.country)], target.polls as f64);
+    }
+    out.family("weather_api_errors_total", "counter", "Failed polls of the location by error code");
+    for target in &targets {
+        for (code, count) in &target.errors {
+            out.sample("weather_api_errors_total", &[("city", &target.city), ("country", &target.country), ("code", code)], *count as f64);
+        }
+    }
+    out.family("weather_exporter_scrapes_total", "counter", "Requests for /metrics");
+    out.sample("weather_exporter_scrapes_total", &[], scrapes as f64);
+    out.0
+}
@@ -0,0 +1,154 @@
+//! The HTTP client shared by the providers, with timeouts, proxies, retries and the rate limiter.
+
+use std::panic;
+use std::sync::Arc;
+use std::time::Duration;
+use rand::Rng;
+use reqwest::{Client, IntoUrl, NoProxy, Proxy, RequestBuilder, Response, StatusCode};
+use serde::Deserialize;
+use crate::error::{self, WeatherError};
+use crate::providers::USER_AGENT;
+use crate::quota::Limiter;
+
+/// Connection settings from the `[http]` table of the config file
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpSettings {
+    #[serde(with = "humantime_serde")]
+    pub connect_timeout: Duration, // Longest wait for a connection to the weather service
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,         // Longest wait for a whole request, connection included
+    pub retries: u32,              // Extra attempts after a server error, a refused connection or a 429
+    #[serde(with = "humantime_serde")]
+    pub backoff: Duration,         // Delay before the first retry; it doubles with every attempt
+    #[serde(with = "humantime_serde")]
+    pub max_backoff: Duration,     // Longest delay between attempts, Retry-After included
+    pub proxy: Option<String>,     // Proxy for every request, replacing HTTP_PROXY and friends
+    pub no_proxy: Option<String>,  // Comma-separated hosts that bypass `proxy`, like NO_PROXY
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        HttpSettings {
+            connect_timeout: Duration::from_secs(10),
+            timeout: Duration::from_secs(30),
+            retries: 3,
+            backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            proxy: None,
+            no_proxy: None,
+        }
+    }
+}
+
+/// The HTTP client shared by every provider: timeouts, proxies, retries with backoff and the rate
+/// limiter. Clones share one connection pool.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: Client,
+    limiter: Option<Arc<Limiter>>, // Rate limit and call budget, taken for every attempt
+    retries: u32,
+    backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl HttpClient {
+    /// Without `proxy` in the config, reqwest picks HTTP_PROXY, HTTPS_PROXY, ALL_PROXY and NO_PROXY up itself
+    pub fn new(settings: &HttpSettings, limiter: Option<Arc<Limiter>>) -> Result<Self, WeatherError> {
+        let mut builder = Client::builder()
+            .user_agent(USER_AGENT)
+            .connect_timeout(settings.connect_timeout)
+            .timeout(settings.timeout);
+        if let Some(proxy) = &settings.proxy {
+            let no_proxy = settings.no_proxy.as_deref().and_then(NoProxy::from_string);
+            builder = builder.proxy(Proxy::all(proxy)?.no_proxy(no_proxy));
+        }
+
+        Ok(HttpClient {
+            client: builder.build()?,
+            limiter,
+            retries: settings.retries,
+            backoff: settings.backoff,
+            max_backoff: settings.max_backoff,
+        })
+    }
+
+    pub fn get<U: IntoUrl>(&self, url: U) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    /// Sends a request, retrying server errors, refused connections and 429s with jittered
+    /// exponential backoff. Timeouts are not retried, so a stalled service fails within `timeout`.
+    /// Every attempt takes a call from the rate limiter.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, WeatherError> {
+        let request = request.build()?;
+        tracing::debug!(url = %request.url(), "sending request");
+
+        let mut attempt = 0;
+        loop {
+            self.admit().await?;
+            let retry = request.try_clone().expect("GET requests have no streaming body");
+            let delay = match self.client.execute(retry).await {
+                Ok(response) => {
+                    tracing::debug!(status = %response.status(), "received response");
+                    let status = response.status();
+                    let wait = error::retry_after(&response);
+                    let mut delay = wait.unwrap_or_else(|| self.delay(attempt));
+                    if status == StatusCode::TOO_MANY_REQUESTS {
+                        // Other processes share the bucket, so it is emptied for all of them; the
+                        // next attempt then waits in `admit` rather than here
+                        if self.with_limiter(move |limiter| limiter.pause(delay)).await == Some(true) {
+                            delay = Duration::ZERO;
+                        }
+                    }
+                    if attempt >= self.retries || !(status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS) {
+                        return Ok(response);
+                    }
+                    match wait {
+                        // Waiting longer than that is left to the caller, who sees the 429 and its Retry-After
+                        Some(wait) if wait > self.max_backoff => return Ok(response),
+                        _ => delay,
+                    }
+                }
+                Err(e) if e.is_connect() && attempt < self.retries => {
+                    tracing::debug!(error = %e, "could not connect");
+                    self.delay(attempt)
+                }
+                Err(e) => return Err(e.into()),
+            };
+            attempt += 1;
+            tracing::info!(attempt, of = self.retries, ?delay, "retrying request");
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    // Takes one call from the rate limit and quota, waiting for the per-minute bucket when it is empty
+    async fn admit(&self) -> Result<(), WeatherError> {
+        let Some(wait) = self.with_limiter(Limiter::acquire).await.transpose()? else {
+            return Ok(());
+        };
+        if !wait.is_zero() {
+            tracing::info!(?wait, "waiting for the per-minute rate limit");
+            tokio::time::sleep(wait).await;
+        }
+        Ok(())
+    }
+
+    // The limiter locks its state file and reads and writes it synchronously, so it runs on the
+    // blocking pool rather than holding up a worker of the shared runtime
+    async fn with_limiter<T: Send + 'static>(&self, call: impl FnOnce(&Limiter) -> T + Send + 'static) -> Option<T> {
+        let limiter = self.limiter.clone()?;
+        match tokio::task::spawn_blocking(move || call(&limiter)).await {
+            Ok(value) => Some(value),
+            Err(e) => panic::resume_unwind(e.into_panic()),
+        }
+    }
+
+    // "Equal jitter": half of the exponential delay, plus a random share of the other half,
+    // so clients that failed together do not all come back at the same moment
+    fn delay(&self, attempt: u32) -> Duration {
+        let ceiling = self.backoff.saturating_mul(2u32.saturating_pow(attempt)).min(self.max_backoff);
+        let half = ceiling / 2;
+        half + half.mul_f64(rand::thread_rng().gen::<f64>())
+    }
+}
@@ -0,0 +1,51 @@
+//! Weather reports from several providers, behind one client with a cache, a rate limiter and
+//! colored text renderers. The `weather` command line is built on this crate.
+//!
+//! A [`WeatherClient`] looks locations up and fetches current conditions and forecasts through a
+//! [`WeatherProvider`], built from a [`ProviderKind`]. Its methods are async; a [`BlockingClient`]
+//! runs them on a runtime of its own for code that is not. Readings are always metric and are
+//! converted with [`Units`] when they are shown.
+//!
+//! ```no_run
+//! # #[cfg(feature = "open-meteo")] {
+//! use std::collections::BTreeMap;
+//! use weather::http::HttpSettings;
+//! use weather::providers::Endpoints;
+//! use weather::{render, BlockingClient, Location, ProviderKind, Units, WeatherClient};
+//!
+//! let provider = ProviderKind::OpenMeteo.build(None, &Endpoints::default(), &HttpSettings::default(), None)?;
+//! let client = BlockingClient::new(WeatherClient::new(provider, None, false, None, BTreeMap::new()))?;
+//! let location: Location = "Berlin, DE".parse()?;
+//! let place = client.locate(&location)?.value.remove(0);
+//! let report = client.current(&place)?.value;
+//! println!("{}", render::weather_text(&Units::default(), &report, false, None));
+//! # }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! # Features
+//! Each provider is behind a feature of its own: `openweathermap`, `open-meteo`, `met-no` and
+//! `nws`. So is each structured output format: `json`, `csv` and `ndjson`. All of them are on by
+//! default, and at least one provider is needed.
+
+#[cfg(not(any(feature = "openweathermap", feature = "open-meteo", feature = "met-no", feature = "nws")))]
+compile_error!("enable at least one provider feature: openweathermap, open-meteo, met-no or nws");
+
+pub mod aqi;
+pub mod cache;
+pub mod client;
+pub mod error;
+pub mod http;
+pub mod model;
+pub mod output;
+pub mod providers;
+pub mod quota;
+pub mod redact;
+pub mod render;
+pub mod units;
+
+pub use client::{BlockingClient, Fetched, Origin, WeatherClient};
+pub use error::WeatherError;
+pub use model::{AirQuality, AirReading, Condition, Coordinates, DailySummary, Forecast, ForecastEntry, Location, Observation, Place, PlaceQuery, Pollutants};
+pub use providers::{ProviderKind, WeatherProvider};
+pub use units::Units;
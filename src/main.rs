@@ -1,157 +1,170 @@
-use std::io;
-use colored::*;
-use serde::Deserialize;
-use reqwest::blocking::get;
-
-// Struct to store weather information obtained from OpenWeatherMap API
-#[derive(Deserialize, Debug)]
-struct WeatherData {
-    weather: Vec<WeatherDetails>, // Contains description of the weather
-    main: WeatherMain,            // Holds core weather metrics
-    wind: WindInfo,               // Contains wind-related data
-    name: String,                 // Holds the location name
-}
-
-// Struct representing weather description details
-#[derive(Deserialize, Debug)]
-struct WeatherDetails {
-    description: String, // Describes the weather condition
-}
-
-// Struct representing main weather parameters
-#[derive(Deserialize, Debug)]
-struct WeatherMain {
-    temp: f64,     // Temperature in Celsius
-    humidity: f64, // Humidity percentage
-    pressure: f64, // Pressure in hPa
-}
-
-// Struct representing wind information
-#[derive(Deserialize, Debug)]
-struct WindInfo {
-    speed: f64, // Speed of the wind in m/s
-}
-
-// Core struct responsible for retrieving and displaying weather data
-struct WeatherApp {
-    api_token: String, // API token for OpenWeatherMap access
-}
-
-impl WeatherApp {
-    // Constructs a new instance of WeatherApp
-    fn initialize(api_token: &str) -> Self {
-        WeatherApp {
-            api_token: api_token.to_owned(),
+// Builds without every output format leave some views and imports unused and some catch-all match arms unreachable
+#![cfg_attr(not(all(feature = "json", feature = "csv", feature = "ndjson")), allow(dead_code, unused_imports, unreachable_patterns))]
+
+mod app;
+mod batch;
+mod cli;
+mod config;
+mod exporter;
+mod manage;
+mod rules;
+mod server;
+mod tui;
+
+use std::io::{self, IsTerminal};
+use std::process::ExitCode;
+use std::sync::Arc;
+use app::{UserInteraction, WeatherApp};
+use cli::{Cli, Command, InteractiveArgs, NowArgs};
+use config::LoadedConfig;
+use rules::RuleSet;
+use weather::cache::{self, Cache};
+use weather::output::{self, ErrorView, OutputFormat};
+use weather::quota::Limiter;
+use weather::{redact, BlockingClient, Location, Units, WeatherClient};
+
+fn main() -> ExitCode {
+    let cli = Cli::parse_args();
+    redact::init_tracing(cli.verbose);
+    let output_format = cli.output.unwrap_or_default();
+
+    let mut loaded = match LoadedConfig::load(cli.config.as_deref()) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            report_setup_error(output_format, e.code(), &e);
+            return ExitCode::from(2);
         }
+    };
+
+    // Without a subcommand, show the default location if there is one, and prompt otherwise
+    let command = match (cli.command, &loaded.config.default_location) {
+        (Some(command), _) => command,
+        (None, Some(name)) => Command::Now(NowArgs {
+            locations: vec![Location::Saved(name.clone())],
+            city: None,
+            state: None,
+            country: None,
+            from_file: None,
+            sort: None,
+            jobs: 1,
+            detailed: false,
+        }),
+        (None, None) => Command::Interactive(InteractiveArgs::default()),
+    };
+    let provider_kind = cli.provider.or(loaded.config.provider).unwrap_or_default();
+    let config = &loaded.config;
+    let units = Units::new(
+        cli.units.or(config.units).unwrap_or_default(),
+        cli.temperature_unit.or(config.temperature_unit),
+        cli.wind_unit.or(config.wind_unit),
+        cli.pressure_unit.or(config.pressure_unit),
+        cli.precipitation_unit.or(config.precipitation_unit),
+    );
+
+    if let Command::Config = command {
+        return manage::show_config(&loaded, provider_kind, units, cli.api_key.as_deref(), output_format);
     }
-
-    // Retrieves weather data from the API using the specified city and country code
-    fn obtain_weather(&self, city: &str, country: &str) -> Result<WeatherData, reqwest::Error> {
-        let api_endpoint = format!(
-            "http://api.openweathermap.org/data/2.5/weather?q={},{}&units=metric&appid={}",
-            city, country, self.api_token
-        );
-
-        let api_response = get(&api_endpoint)?;
-        let weather_info = api_response.json::<WeatherData>()?;
-        Ok(weather_info)
+    if let Command::Quota = command {
+        return manage::show_quota(provider_kind, config, output_format);
     }
-
-    // Displays the weather details in a formatted way
-    fn render_weather_info(&self, weather_info: &WeatherData) {
-        let weather_desc = &weather_info.weather[0].description;
-        let temp = weather_info.main.temp;
-        let humidity = weather_info.main.humidity;
-        let pressure = weather_info.main.pressure;
-        let wind_velocity = weather_info.wind.speed;
-
-        let formatted_details = format!(
-            "Weather Update for {}: {} {}
-            > Temperature: {:.1}°C
-            > Humidity: {:.1}%
-            > Pressure: {:.1} hPa
-            > Wind Speed: {:.1} m/s",
-            weather_info.name,
-            weather_desc,
-            Self::emoji_for_temperature(temp),
-            temp,
-            humidity,
-            pressure,
-            wind_velocity
-        );
-
-        let colored_output = Self::colorize_weather_output(weather_desc, &formatted_details);
-        println!("{}", colored_output);
-    }
-
-    // Determines an emoji representation based on the temperature
-    fn emoji_for_temperature(temp: f64) -> &'static str {
-        match temp {
-            _ if temp < 0.0 => "❄️",
-            _ if temp < 10.0 => "☁️",
-            _ if temp < 20.0 => "⛅",
-            _ if temp < 30.0 => "🌤️",
-            _ => "🔥",
-        }
+    if let Command::Locations(action) = command {
+        return manage::manage_locations(&mut loaded, action, output_format);
     }
-
-    // Applies color effects to the weather report based on the description
-    fn colorize_weather_output(description: &str, weather_text: &str) -> ColoredString {
-        match description {
-            "clear sky" => weather_text.bright_yellow(),
-            "few clouds" | "scattered clouds" | "broken clouds" => weather_text.bright_blue(),
-            "overcast clouds" | "mist" | "haze" | "smoke" | "dust" | "fog" => weather_text.dimmed(),
-            "rain" | "thunderstorm" | "snow" => weather_text.bright_cyan(),
-            _ => weather_text.normal(),
-        }
+    if output_format.is_structured() && matches!(command, Command::Interactive(_)) {
+        let message = "interactive mode prompts for input and only supports text output; use `now` or `forecast`";
+        report_setup_error(output_format, "unsupported_output", message);
+        return ExitCode::from(2);
     }
-}
-
-// Handles interactions with the user in the terminal
-struct UserInteraction;
-
-impl UserInteraction {
-    // Prompts the user to enter the city and country code
-    fn acquire_user_input() -> (String, String) {
-        println!("{}", "Enter the name of the city:".bright_green());
-        let mut city = String::new();
-        io::stdin().read_line(&mut city).expect("Unable to read city name");
-        let city = city.trim().to_string();
-
-        println!("{}", "Enter the country code (e.g., US for United States):".bright_green());
-        let mut country = String::new();
-        io::stdin().read_line(&mut country).expect("Unable to read country code");
-        let country = country.trim().to_string();
-
-        (city, country)
+    if matches!(command, Command::Tui(_)) && (output_format.is_structured() || !io::stdout().is_terminal()) {
+        let message = "the dashboard needs a terminal and only supports text output; use `watch` or `now`";
+        report_setup_error(output_format, "unsupported_output", message);
+        return ExitCode::from(2);
     }
 
-    // Main execution loop to fetch weather data and handle user prompts
-    fn execute_app(weather_app: &WeatherApp) {
-        println!("{}", "Welcome to Weather App!".bright_yellow());
-
-        loop {
-            let (city, country) = Self::acquire_user_input();
-
-            match weather_app.obtain_weather(&city, &country) {
-                Ok(weather_info) => weather_app.render_weather_info(&weather_info),
-                Err(e) => eprintln!("Error retrieving weather information: {}", e),
-            }
-
-            println!("{}", "Would you like to check the weather for another location? (yes/no):".bright_green());
-            let mut user_choice = String::new();
-            io::stdin().read_line(&mut user_choice).expect("Unable to read user input");
-            if user_choice.trim().to_lowercase() != "yes" {
-                println!("Thank you for using Weather App!");
-                break;
+    let api_key = if provider_kind.requires_api_key() {
+        match loaded.resolve_api_key(cli.api_key.as_deref()) {
+            Ok(resolved) => Some(resolved.key),
+            Err(e) => {
+                report_setup_error(output_format, e.code(), &e);
+                return ExitCode::from(2);
             }
         }
+    } else {
+        None
+    };
+    // Rules are only needed by check and watch; a missing default rules.toml is fine for watch
+    let rules = match &command {
+        Command::Check(args) => RuleSet::find(args.rules.as_deref(), config.rules_file.as_deref(), true),
+        Command::Watch(args) => RuleSet::find(args.rules.as_deref(), config.rules_file.as_deref(), false),
+        _ => Ok(None),
+    };
+    let rules = match rules {
+        Ok(rules) => rules.unwrap_or_default(),
+        Err(e) => {
+            report_setup_error(output_format, e.code(), &e);
+            return ExitCode::from(2);
+        }
+    };
+    // The locations compared by now or polled by the exporter
+    let tracked = match &command {
+        Command::Now(args) => batch::locations(args).map_err(|message| ("locations_file", message)),
+        Command::Exporter(args) => exporter::locations(&args.locations, &config.exporter).map_err(|message| ("exporter_locations", message)),
+        _ => Ok(Vec::new()),
+    };
+    let tracked = match tracked {
+        Ok(tracked) => tracked,
+        Err((code, message)) => {
+            report_setup_error(output_format, code, message);
+            return ExitCode::from(2);
+        }
+    };
+    let limiter = Limiter::open(provider_kind, config.quota.clone()).map(Arc::new);
+    let provider = match provider_kind.build(api_key, &config.endpoints(), &config.http, limiter.clone()) {
+        Ok(provider) => provider,
+        Err(e) => {
+            report_setup_error(output_format, e.code(), format!("Could not set up the {} provider: {}", provider_kind, e));
+            return ExitCode::FAILURE;
+        }
+    };
+    let cache = Cache::open(provider_kind, config.cache_ttl.unwrap_or(cache::DEFAULT_TTL));
+    let saved = config.locations.iter().map(|(name, saved)| (name.clone(), saved.0.clone())).collect();
+    let client = match BlockingClient::new(WeatherClient::new(provider, cache, cli.offline, limiter, saved)) {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Could not start the network runtime: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let weather_app = WeatherApp::initialize(client, units, output_format);
+
+    match command {
+        // A single location keeps the full report; several are compared side by side
+        Command::Now(args) => match tracked.as_slice() {
+            [location] if args.from_file.is_none() => UserInteraction::report_once(&weather_app, location, args.detailed),
+            _ => batch::compare(&weather_app, &tracked, &args),
+        },
+        Command::Forecast(args) => UserInteraction::forecast_once(&weather_app, &args),
+        Command::Air(args) => UserInteraction::air_once(&weather_app, &args),
+        Command::Check(args) => UserInteraction::check(&weather_app, &args, &rules),
+        Command::Watch(args) => UserInteraction::watch(&weather_app, &args, &rules),
+        Command::Serve(args) => server::serve(Arc::new(weather_app), &args),
+        Command::Exporter(args) => {
+            let every = args.every.unwrap_or(config.exporter.interval);
+            exporter::run(Arc::new(weather_app), tracked, every, args.bind.as_deref().unwrap_or(&config.exporter.bind))
+        }
+        Command::Tui(args) => tui::run(weather_app, loaded, &args),
+        Command::Interactive(args) => {
+            UserInteraction::execute_app(&weather_app, args.detailed);
+            ExitCode::SUCCESS
+        }
+        Command::Config | Command::Quota | Command::Locations(_) => unreachable!("handled before the API key is resolved"),
     }
 }
 
-fn main() {
-    let api_token = ""; // <-- API KEY
-    let weather_app = WeatherApp::initialize(api_token);
-
-    UserInteraction::execute_app(&weather_app);
+// Prints an error raised before any lookup, as text or as a JSON object for structured output
+fn report_setup_error(format: OutputFormat, code: &'static str, message: impl std::fmt::Display) {
+    match format {
+        OutputFormat::Text => eprintln!("{}", message),
+        _ => output::print_error(&ErrorView::new(code, message)),
+    }
 }
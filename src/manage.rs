@@ -0,0 +1,309 @@
+use std::process::ExitCode;
+use std::time::Duration;
+use serde::Serialize;
+use weather::cache::{self, Cache};
+use weather::http::HttpSettings;
+use weather::output::{self, OutputFormat, RecordWriter};
+use weather::quota::Limiter;
+use weather::{model, redact, Location, ProviderKind, Units};
+use crate::cli::LocationsCommand;
+use crate::config::{self, LoadedConfig};
+use crate::{report_setup_error, rules};
+
+// A location saved in the config file; `weather locations ls` prints a JSON array of these
+#[derive(Serialize, Debug)]
+pub struct SavedLocationView {
+    pub name: String,
+    pub location: String,
+    pub default: bool,
+}
+
+// What `locations add` or `rm` changed, printed instead of a sentence in the structured formats
+#[derive(Serialize, Debug)]
+pub struct LocationChangeView {
+    pub action: &'static str, // "saved", "updated" or "removed"
+    #[serde(flatten)]
+    pub location: SavedLocationView,
+}
+
+// Prints the outcome of `locations add` or `rm`: the sentence as text, or the change as a record
+fn report_change(format: OutputFormat, message: String, change: LocationChangeView) -> ExitCode {
+    match format {
+        OutputFormat::Text => println!("{}", message),
+        #[cfg(feature = "json")]
+        OutputFormat::Json => output::print_json(&change),
+        format => {
+            if let Err(e) = RecordWriter::new(format).write(&change) {
+                eprintln!("Could not write the change: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+// Prints how much of the rate limit and the daily and monthly budgets the provider has used
+pub fn show_quota(provider: ProviderKind, config: &config::Config, format: OutputFormat) -> ExitCode {
+    let Some(limiter) = Limiter::open(provider, config.quota.clone()) else {
+        report_setup_error(format, "quota_unavailable", "There is no state directory to keep quota usage in.");
+        return ExitCode::FAILURE;
+    };
+    let status = match limiter.status() {
+        Ok(status) => status,
+        Err(e) => {
+            report_setup_error(format, "quota_unavailable", format!("Could not read {}: {}", limiter.path().display(), e));
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match format {
+        OutputFormat::Text => {
+            let used = |calls: u64, limit: Option<u64>| match limit {
+                Some(limit) => format!("{} of {} calls ({:.1}%)", calls, limit, calls as f64 * 100.0 / limit as f64),
+                None => format!("{} calls (no limit)", calls),
+            };
+            println!("provider: {}", status.provider);
+            match (status.per_minute, status.available_now) {
+                (Some(per_minute), Some(available)) => println!("this minute: {} of {} calls available", available, per_minute),
+                _ => println!("this minute: no rate limit"),
+            }
+            println!("today (UTC): {}", used(status.calls_today, status.daily_limit));
+            println!("this month (UTC): {}", used(status.calls_this_month, status.monthly_limit));
+            println!("warn at {:.0}%, refuse at {:.0}%", status.warn_at * 100.0, status.refuse_at * 100.0);
+        }
+        #[cfg(feature = "json")]
+        OutputFormat::Json => output::print_json(&status),
+        format => {
+            if let Err(e) = RecordWriter::new(format).write(&status) {
+                eprintln!("Could not write the quota: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+// Adds, removes or lists the locations saved in the config file
+pub fn manage_locations(loaded: &mut LoadedConfig, action: LocationsCommand, format: OutputFormat) -> ExitCode {
+    let saved = loaded.config.locations.clone();
+    let default = loaded.config.default_location.clone();
+    let default = default.as_deref();
+    match action {
+        LocationsCommand::Ls => {
+            let views: Vec<SavedLocationView> = saved
+                .iter()
+                .map(|(name, location)| SavedLocationView { name: name.clone(), location: location.0.to_string(), default: default == Some(name.as_str()) })
+                .collect();
+            match format {
+                OutputFormat::Text if views.is_empty() => println!("No saved locations. Add one with `weather locations add NAME LOCATION`."),
+                OutputFormat::Text => {
+                    let width = views.iter().map(|view| view.name.len() + 1).max().unwrap_or_default();
+                    for view in &views {
+                        let marker = if view.default { " (default)" } else { "" };
+                        println!("{:width$}  {}{}", format!("@{}", view.name), view.location, marker, width = width);
+                    }
+                }
+                #[cfg(feature = "json")]
+                OutputFormat::Json => output::print_json(&views),
+                format => {
+                    let mut writer = RecordWriter::new(format);
+                    if let Err(e) = views.iter().try_for_each(|view| writer.write(view)) {
+                        eprintln!("Could not write the locations: {}", e);
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+            ExitCode::SUCCESS
+        }
+        LocationsCommand::Add(args) => {
+            let name = args.name.strip_prefix('@').unwrap_or(&args.name);
+            if !model::is_saved_name(name) {
+                report_setup_error(format, "invalid_name", format!("\"{}\" cannot be used as a name; use letters, digits, '-' and '_'", name));
+                return ExitCode::from(2);
+            }
+            let location = args.location.location();
+            // Saving where an alias points keeps the entry valid if the alias is removed later
+            let location = match &location {
+                Location::Saved(other) => match saved.get(other) {
+                    Some(target) => target.0.clone(),
+                    None => {
+                        report_setup_error(format, "not_found", format!("There is no saved location named \"{}\".", other));
+                        return ExitCode::FAILURE;
+                    }
+                },
+                _ => location,
+            };
+            match loaded.save_location(name, &location, args.default) {
+                Ok(replaced) => {
+                    let (verb, action) = if replaced { ("Updated", "updated") } else { ("Saved", "saved") };
+                    let marker = if args.default { ", the default location" } else { "" };
+                    let change = LocationChangeView {
+                        action,
+                        location: SavedLocationView { name: name.to_string(), location: location.to_string(), default: args.default || default == Some(name) },
+                    };
+                    report_change(format, format!("{} @{}: {}{}", verb, name, location, marker), change)
+                }
+                Err(e) => {
+                    report_setup_error(format, e.code(), &e);
+                    ExitCode::from(2)
+                }
+            }
+        }
+        LocationsCommand::Rm { name } => {
+            let name = name.strip_prefix('@').unwrap_or(&name);
+            let removed = saved.get(name).map(|location| location.0.to_string()).unwrap_or_default();
+            match loaded.remove_location(name) {
+                Ok(true) => {
+                    let change = LocationChangeView {
+                        action: "removed",
+                        location: SavedLocationView { name: name.to_string(), location: removed, default: default == Some(name) },
+                    };
+                    report_change(format, format!("Removed @{}.", name), change)
+                }
+                Ok(false) => {
+                    report_setup_error(format, "not_found", format!("There is no saved location named \"{}\".", name));
+                    ExitCode::FAILURE
+                }
+                Err(e) => {
+                    report_setup_error(format, e.code(), &e);
+                    ExitCode::from(2)
+                }
+            }
+        }
+    }
+}
+
+// The settings `weather config` shows; paths are null when there is no such directory
+#[derive(Serialize, Debug)]
+pub struct ConfigView {
+    pub config_file: Option<String>,
+    pub config_file_exists: bool,
+    pub provider: String,
+    pub units: String,
+    pub cache_dir: Option<String>,
+    pub cache_ttl_seconds: u64,
+    pub quota_state: Option<String>,
+    pub rules_file: Option<String>,
+    pub rules_file_exists: bool,
+    pub saved_locations: usize,
+    pub default_location: Option<String>,
+    pub http_timeout_seconds: f64,
+    pub http_connect_timeout_seconds: f64,
+    pub http_retries: u32,
+    pub proxy: Option<String>,          // With its password redacted; null when taken from the environment
+    pub endpoint: String,
+    pub geocoder: Option<String>,       // Only for the providers that need a separate geocoder
+    pub api_key_needed: bool,
+    pub api_key_source: Option<String>, // Null when no key is needed or none was found
+}
+
+// Prints the config file location, the provider and where the API key would be taken from
+pub fn show_config(loaded: &LoadedConfig, provider: ProviderKind, units: Units, api_key_flag: Option<&str>, format: OutputFormat) -> ExitCode {
+    let ttl = loaded.config.cache_ttl.unwrap_or(cache::DEFAULT_TTL);
+    let rules_file = loaded.config.rules_file.as_deref().map(config::expand_home).or_else(rules::default_rules_path);
+    let http = &loaded.config.http;
+    // Proxy URLs may carry a password
+    let proxy = http.proxy.as_ref().map(|proxy| {
+        reqwest::Url::parse(proxy).map_or_else(
+            |_| proxy.clone(),
+            |mut url| {
+                if url.password().is_some() {
+                    let _ = url.set_password(Some(redact::REDACTED));
+                }
+                url.to_string()
+            },
+        )
+    });
+    let endpoints = loaded.config.endpoints();
+    let api_key = provider.requires_api_key().then(|| loaded.resolve_api_key(api_key_flag));
+    let view = ConfigView {
+        config_file: loaded.path.as_ref().map(|path| path.display().to_string()),
+        config_file_exists: loaded.exists,
+        provider: provider.to_string(),
+        units: units.to_string(),
+        cache_dir: Cache::open(provider, ttl).map(|cache| cache.dir.display().to_string()),
+        cache_ttl_seconds: ttl.as_secs(),
+        quota_state: Limiter::open(provider, loaded.config.quota.clone()).map(|limiter| limiter.path().display().to_string()),
+        rules_file_exists: rules_file.as_ref().is_some_and(|path| path.exists()),
+        rules_file: rules_file.map(|path| path.display().to_string()),
+        saved_locations: loaded.config.locations.len(),
+        default_location: loaded.config.default_location.clone(),
+        http_timeout_seconds: http.timeout.as_secs_f64(),
+        http_connect_timeout_seconds: http.connect_timeout.as_secs_f64(),
+        http_retries: http.retries,
+        proxy,
+        endpoint: endpoints.base_url(provider).to_string(),
+        geocoder: (!provider.requires_api_key()).then(|| endpoints.geocoding_url().to_string()),
+        api_key_needed: api_key.is_some(),
+        api_key_source: api_key.as_ref().and_then(|resolved| resolved.as_ref().ok()).map(|resolved| resolved.source.to_string()),
+    };
+
+    match format {
+        OutputFormat::Text => print_config(&view, ttl, http),
+        #[cfg(feature = "json")]
+        OutputFormat::Json => output::print_json(&view),
+        format => {
+            if let Err(e) = RecordWriter::new(format).write(&view) {
+                eprintln!("Could not write the settings: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    match api_key {
+        Some(Err(e)) => {
+            match format {
+                OutputFormat::Text => eprintln!("{}", e),
+                format => report_setup_error(format, e.code(), &e),
+            }
+            ExitCode::from(2)
+        }
+        _ => ExitCode::SUCCESS,
+    }
+}
+
+// The settings as `name: value` lines
+fn print_config(view: &ConfigView, ttl: Duration, http: &HttpSettings) {
+    match &view.config_file {
+        Some(path) if view.config_file_exists => println!("config file: {}", path),
+        Some(path) => println!("config file: {} (not found)", path),
+        None => println!("config file: none"),
+    }
+    println!("provider: {}", view.provider);
+    println!("units: {}", view.units);
+    match &view.cache_dir {
+        Some(dir) => println!("cache: {} (reports kept for {})", dir, humantime::format_duration(ttl)),
+        None => println!("cache: unavailable"),
+    }
+    match &view.quota_state {
+        Some(path) => println!("quota state: {}", path),
+        None => println!("quota state: unavailable"),
+    }
+    match &view.rules_file {
+        Some(path) if view.rules_file_exists => println!("rules file: {}", path),
+        Some(path) => println!("rules file: {} (not found)", path),
+        None => println!("rules file: none"),
+    }
+    match &view.default_location {
+        Some(name) => println!("saved locations: {} (default @{})", view.saved_locations, name),
+        None => println!("saved locations: {} (no default)", view.saved_locations),
+    }
+    println!(
+        "http: timeout {} (connect {}), {} retries",
+        humantime::format_duration(http.timeout),
+        humantime::format_duration(http.connect_timeout),
+        http.retries
+    );
+    match &view.proxy {
+        Some(proxy) => println!("proxy: {}", proxy),
+        None => println!("proxy: from the environment, if set"),
+    }
+    println!("endpoint: {}", view.endpoint);
+    if let Some(geocoder) = &view.geocoder {
+        println!("geocoder: {}", geocoder);
+    }
+    match &view.api_key_source {
+        Some(source) => println!("api key: from {}", source),
+        None if view.api_key_needed => println!("api key: not found"),
+        None => println!("api key: not needed"),
+    }
+}
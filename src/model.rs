@@ -0,0 +1,374 @@
+//! Provider-neutral locations, places and readings. Readings are always metric.
+
+use std::fmt;
+use std::str::FromStr;
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Broad weather condition used to pick colors, independent of the provider's wording
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Condition {
+    Clear,
+    Cloudy,
+    Overcast,
+    Fog,
+    Rain,
+    Thunderstorm,
+    Snow,
+    #[default]
+    Unknown,
+}
+
+impl Condition {
+    /// Classifies free-text descriptions such as "Light Rain" or "Mostly Cloudy"
+    pub fn from_description(description: &str) -> Self {
+        let text = description.to_lowercase();
+        let has = |words: &[&str]| words.iter().any(|word| text.contains(word));
+
+        if has(&["thunder"]) {
+            Condition::Thunderstorm
+        } else if has(&["snow", "sleet", "ice", "hail", "flurr"]) {
+            Condition::Snow
+        } else if has(&["rain", "drizzle", "shower"]) {
+            Condition::Rain
+        } else if has(&["fog", "mist", "haze", "smoke", "dust", "sand", "ash"]) {
+            Condition::Fog
+        } else if has(&["overcast"]) {
+            Condition::Overcast
+        } else if has(&["cloud", "fair"]) {
+            Condition::Cloudy
+        } else if has(&["clear", "sunny"]) {
+            Condition::Clear
+        } else {
+            Condition::Unknown
+        }
+    }
+}
+
+/// A point on the globe in decimal degrees
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Coordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Free-text place name, e.g. "Springfield, IL, US": name, then optional state, then country code
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PlaceQuery {
+    pub name: String,
+    pub state: Option<String>,
+    pub country: Option<String>,
+}
+
+impl FromStr for PlaceQuery {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = text.split(',').map(str::trim).collect();
+        let non_empty = |part: &&str| Some(part.to_string()).filter(|part| !part.is_empty());
+        let query = match parts.as_slice() {
+            [name] => PlaceQuery { name: name.to_string(), ..PlaceQuery::default() },
+            [name, country] => PlaceQuery { name: name.to_string(), state: None, country: non_empty(country) },
+            [name, state, country] => PlaceQuery {
+                name: name.to_string(),
+                state: non_empty(state),
+                country: non_empty(country),
+            },
+            _ => return Err(format!("expected \"city[, state][, country]\", got {:?}", text)),
+        };
+        if query.name.is_empty() {
+            return Err("the place name is empty".to_string());
+        }
+        Ok(query)
+    }
+}
+
+impl fmt::Display for PlaceQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        for part in [&self.state, &self.country].into_iter().flatten() {
+            write!(f, ", {}", part)?;
+        }
+        Ok(())
+    }
+}
+
+/// Any way of naming a location: a place name, coordinates, a postal code, a provider city ID or
+/// a location saved in the config file. Parsed from text such as "Berlin, DE", "52.52,13.40",
+/// "zip:10115,DE", "id:2950159" or "@home".
+#[derive(Debug, Clone, PartialEq)]
+pub enum Location {
+    Named(PlaceQuery),
+    Coordinates(Coordinates),
+    Postal { code: String, country: Option<String> },
+    CityId(u64),
+    Saved(String), // Name of an entry in the [locations] table, resolved before any lookup
+}
+
+impl FromStr for Location {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        let prefixed = |prefix: &str| {
+            text.get(..prefix.len())
+                .filter(|head| head.eq_ignore_ascii_case(prefix))
+                .map(|_| text[prefix.len()..].trim())
+        };
+
+        if let Some(name) = text.strip_prefix('@') {
+            if !is_saved_name(name) {
+                return Err(format!("invalid saved location name {:?}; use letters, digits, '-' and '_'", name));
+            }
+            return Ok(Location::Saved(name.to_string()));
+        }
+        if let Some(rest) = prefixed("zip:").or_else(|| prefixed("postal:")) {
+            let (code, country) = match rest.split_once(',') {
+                Some((code, country)) => (code.trim(), Some(country.trim().to_string()).filter(|c| !c.is_empty())),
+                None => (rest, None),
+            };
+            if code.is_empty() {
+                return Err("the postal code is empty".to_string());
+            }
+            return Ok(Location::Postal { code: code.to_string(), country });
+        }
+        if let Some(rest) = prefixed("id:") {
+            return rest.parse().map(Location::CityId).map_err(|_| format!("invalid city ID {:?}", rest));
+        }
+        if let Some((lat, lon)) = text.split_once(',') {
+            if let (Ok(latitude), Ok(longitude)) = (lat.trim().parse::<f64>(), lon.trim().parse::<f64>()) {
+                if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+                    return Err(format!("coordinates out of range: {}", text));
+                }
+                return Ok(Location::Coordinates(Coordinates { latitude, longitude }));
+            }
+        }
+        text.parse().map(Location::Named)
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::Named(query) => query.fmt(f),
+            Location::Coordinates(at) => write!(f, "{},{}", at.latitude, at.longitude),
+            Location::Postal { code, country: Some(country) } => write!(f, "zip:{},{}", code, country),
+            Location::Postal { code, country: None } => write!(f, "zip:{}", code),
+            Location::CityId(id) => write!(f, "id:{}", id),
+            Location::Saved(name) => write!(f, "@{}", name),
+        }
+    }
+}
+
+/// Whether a name can be used for a saved location, as in "@home"
+pub fn is_saved_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+/// A geocoded place; weather is always fetched for its coordinates
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Place {
+    pub name: String,
+    pub state: Option<String>,   // State, province or region, where the geocoder knows it
+    pub country: Option<String>, // ISO 3166 country code
+    pub coordinates: Coordinates,
+}
+
+impl Place {
+    /// A place known only by its coordinates, named after them
+    pub fn at(coordinates: Coordinates) -> Self {
+        Place {
+            name: format!("{:.4}, {:.4}", coordinates.latitude, coordinates.longitude),
+            state: None,
+            country: None,
+            coordinates,
+        }
+    }
+
+    /// Whether the place is only known by its coordinates, as given rather than looked up
+    pub fn is_unnamed(&self) -> bool {
+        self.state.is_none() && self.country.is_none() && self.name == Place::at(self.coordinates).name
+    }
+}
+
+impl fmt::Display for Place {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        for part in [&self.state, &self.country].into_iter().flatten() {
+            write!(f, ", {}", part)?;
+        }
+        write!(f, " ({:.4}, {:.4})", self.coordinates.latitude, self.coordinates.longitude)
+    }
+}
+
+/// Current conditions at a location, in metric units, as every provider reports them.
+/// Providers leave a reading at None when their API does not offer it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Observation {
+    pub location: String,                   // Display name of the location
+    pub country: Option<String>,            // ISO 3166 country code
+    pub coordinates: Option<Coordinates>,   // Where the reading applies
+    pub observed_at: Option<DateTime<Utc>>, // When the reading was taken
+    #[serde(with = "optional_offset_seconds")]
+    pub utc_offset: Option<FixedOffset>,    // Offset of the location's local time from UTC
+    pub description: String,                // Provider's wording of the conditions, lower case
+    pub condition: Condition,               // Classified condition used for colors
+    pub condition_code: Option<u32>,        // Provider's numeric condition code
+    pub icon: Option<String>,               // Provider's icon name for the condition
+    pub temperature: f64,                   // Temperature in Celsius
+    pub feels_like: Option<f64>,            // Apparent temperature in Celsius
+    pub temp_min: Option<f64>,              // Lowest temperature currently observed in the area, Celsius
+    pub temp_max: Option<f64>,              // Highest temperature currently observed in the area, Celsius
+    pub humidity: Option<f64>,              // Relative humidity in percent
+    pub pressure: Option<f64>,              // Pressure in hPa
+    pub sea_level_pressure: Option<f64>,    // Pressure reduced to sea level in hPa
+    pub ground_level_pressure: Option<f64>, // Pressure at ground level in hPa
+    pub visibility: Option<f64>,            // Visibility in metres
+    pub cloud_cover: Option<f64>,           // Cloudiness in percent
+    pub wind_speed: Option<f64>,            // Wind speed in m/s
+    pub wind_direction: Option<f64>,        // Direction the wind blows from, degrees clockwise from north
+    pub wind_gust: Option<f64>,             // Gust speed in m/s
+    pub rain_1h: Option<f64>,               // Rain over the last hour in mm
+    pub rain_3h: Option<f64>,               // Rain over the last 3 hours in mm
+    pub snow_1h: Option<f64>,               // Snow over the last hour in mm
+    pub snow_3h: Option<f64>,               // Snow over the last 3 hours in mm
+    pub sunrise: Option<DateTime<Utc>>,     // Today's sunrise
+    pub sunset: Option<DateTime<Utc>>,      // Today's sunset
+}
+
+/// One step of a forecast, e.g. a 3-hour slot from OpenWeatherMap or an hour from Open-Meteo
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ForecastEntry {
+    pub time: DateTime<Utc>,        // Start of the step
+    pub description: String,        // Provider's wording of the conditions, lower case
+    pub condition: Condition,       // Classified condition used for colors
+    pub temperature: f64,           // Temperature in Celsius
+    pub humidity: Option<f64>,      // Relative humidity in percent
+    pub wind_speed: Option<f64>,    // Wind speed in m/s
+    pub precipitation: Option<f64>, // Rain and snow over the step in mm
+}
+
+/// A forecast for one location, in the order the provider returned it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Forecast {
+    pub location: String,        // Display name of the location
+    #[serde(with = "offset_seconds")]
+    pub utc_offset: FixedOffset, // Offset of the location's local time from UTC
+    pub entries: Vec<ForecastEntry>,
+}
+
+/// Summary of the forecast entries that fall on one local calendar day
+#[derive(Debug, Clone)]
+pub struct DailySummary {
+    pub date: NaiveDate,            // Local date at the location
+    pub min_temperature: f64,       // Lowest temperature in Celsius
+    pub max_temperature: f64,       // Highest temperature in Celsius
+    pub description: String,        // Most frequent description of the day
+    pub condition: Condition,       // Condition belonging to that description
+    pub precipitation: Option<f64>, // Total rain and snow in mm, if the provider reports it
+}
+
+impl Forecast {
+    /// Local time of an entry at the forecast location
+    pub fn local_time(&self, entry: &ForecastEntry) -> DateTime<FixedOffset> {
+        entry.time.with_timezone(&self.utc_offset)
+    }
+
+    /// Groups the entries into local calendar days, earliest first
+    pub fn daily(&self) -> Vec<DailySummary> {
+        let mut days: Vec<(NaiveDate, Vec<&ForecastEntry>)> = Vec::new();
+        for entry in &self.entries {
+            let date = self.local_time(entry).date_naive();
+            match days.last_mut() {
+                Some((day, entries)) if *day == date => entries.push(entry),
+                _ => days.push((date, vec![entry])),
+            }
+        }
+
+        days.into_iter().map(|(date, entries)| DailySummary::from_entries(date, &entries)).collect()
+    }
+}
+
+impl DailySummary {
+    fn from_entries(date: NaiveDate, entries: &[&ForecastEntry]) -> Self {
+        let temperatures = entries.iter().map(|entry| entry.temperature);
+        let min_temperature = temperatures.clone().fold(f64::INFINITY, f64::min);
+        let max_temperature = temperatures.fold(f64::NEG_INFINITY, f64::max);
+
+        // Most frequent description wins; ties go to the one seen first
+        let mut counts: Vec<(&ForecastEntry, usize)> = Vec::new();
+        for entry in entries {
+            match counts.iter_mut().find(|(seen, _)| seen.description == entry.description) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((entry, 1)),
+            }
+        }
+        let dominant = counts.iter().rev().max_by_key(|(_, count)| *count).map(|(entry, _)| *entry).unwrap_or(entries[0]);
+
+        let reported: Vec<f64> = entries.iter().filter_map(|entry| entry.precipitation).collect();
+        let precipitation = if reported.is_empty() { None } else { Some(reported.iter().sum()) };
+
+        DailySummary {
+            date,
+            min_temperature,
+            max_temperature,
+            description: dominant.description.clone(),
+            condition: dominant.condition,
+            precipitation,
+        }
+    }
+}
+
+/// Concentrations of the pollutants in the air, in µg/m³
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub struct Pollutants {
+    pub pm2_5: f64, // Fine particles, 2.5 µm across or less
+    pub pm10: f64,  // Coarse particles, 10 µm across or less
+    pub o3: f64,    // Ozone
+    pub no2: f64,   // Nitrogen dioxide
+    pub so2: f64,   // Sulphur dioxide
+    pub co: f64,    // Carbon monoxide
+    pub nh3: f64,   // Ammonia
+}
+
+/// Air quality at one point in time
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AirReading {
+    pub time: DateTime<Utc>,    // When the reading applies
+    pub index: Option<u8>,      // Provider's own index, 1 (good) to 5 (very poor) for OpenWeatherMap
+    pub pollutants: Pollutants, // Concentrations at that time
+}
+
+/// Air quality at a place: a single reading for the current air, one per hour for a forecast
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AirQuality {
+    pub location: String,          // Display name of the location
+    pub readings: Vec<AirReading>, // Earliest first
+}
+
+// chrono's FixedOffset has no serde support; cached reports store it as seconds east of UTC
+mod offset_seconds {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(offset: &FixedOffset, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i32(offset.local_minus_utc())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<FixedOffset, D::Error> {
+        let seconds = i32::deserialize(deserializer)?;
+        FixedOffset::east_opt(seconds).ok_or_else(|| serde::de::Error::custom(format!("invalid UTC offset {}", seconds)))
+    }
+}
+
+mod optional_offset_seconds {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(offset: &Option<FixedOffset>, serializer: S) -> Result<S::Ok, S::Error> {
+        offset.map(|offset| offset.local_minus_utc()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<FixedOffset>, D::Error> {
+        Ok(Option::<i32>::deserialize(deserializer)?.and_then(FixedOffset::east_opt))
+    }
+}
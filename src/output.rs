@@ -0,0 +1,529 @@
+//! Serializable views of reports for JSON, CSV and NDJSON output, and the writers that print them.
+
+use std::io::{self, Write};
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::Value;
+use crate::aqi::{self, Category};
+use crate::error::WeatherError;
+use crate::model::{AirQuality, AirReading, Condition, Forecast, Location, Observation, Place};
+use crate::units::Units;
+
+/// How reports are printed; every format but text is behind the cargo feature of the same name
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,   // Colored prose for people
+    #[cfg(feature = "json")]
+    Json,   // One JSON object per report on stdout, errors as JSON on stderr
+    #[cfg(feature = "csv")]
+    Csv,    // A header row, then one row per record; nested fields become "parent_child" columns
+    #[cfg(feature = "ndjson")]
+    Ndjson, // One compact JSON record per line
+}
+
+impl OutputFormat {
+    /// Structured formats never mix prompts, colors or prose into their output
+    pub fn is_structured(self) -> bool {
+        self != OutputFormat::Text
+    }
+}
+
+// Readings are converted to the selected units and rounded to two decimals
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Unit symbols of the readings in a report
+#[derive(Serialize, Debug)]
+pub struct UnitsView {
+    pub temperature: &'static str,
+    pub wind_speed: &'static str,
+    pub pressure: &'static str,
+    pub precipitation: &'static str,
+    pub visibility: &'static str,
+}
+
+impl From<&Units> for UnitsView {
+    fn from(units: &Units) -> Self {
+        UnitsView {
+            temperature: units.temperature.symbol().trim_start(),
+            wind_speed: units.speed.symbol(),
+            pressure: units.pressure.symbol(),
+            precipitation: units.precipitation.symbol(),
+            visibility: units.distance.symbol(),
+        }
+    }
+}
+
+/// Where a report applies
+#[derive(Serialize, Debug)]
+pub struct LocationView {
+    pub name: String,
+    pub country: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+/// Current conditions; every key is always present, with null for readings the provider lacks
+#[derive(Serialize, Debug)]
+pub struct ObservationView {
+    pub location: LocationView,
+    pub observed_at: Option<DateTime<Utc>>,
+    pub utc_offset_seconds: Option<i32>,
+    pub units: UnitsView,
+    pub description: String,
+    pub condition: Condition,
+    pub condition_code: Option<u32>,
+    pub icon: Option<String>,
+    pub temperature: f64,
+    pub feels_like: Option<f64>,
+    pub temp_min: Option<f64>,
+    pub temp_max: Option<f64>,
+    pub humidity: Option<f64>,
+    pub pressure: Option<f64>,
+    pub sea_level_pressure: Option<f64>,
+    pub ground_level_pressure: Option<f64>,
+    pub visibility: Option<f64>,
+    pub cloud_cover: Option<f64>,
+    pub wind_speed: Option<f64>,
+    pub wind_direction: Option<f64>,
+    pub wind_gust: Option<f64>,
+    pub rain_1h: Option<f64>,
+    pub rain_3h: Option<f64>,
+    pub snow_1h: Option<f64>,
+    pub snow_3h: Option<f64>,
+    pub sunrise: Option<DateTime<Utc>>,
+    pub sunset: Option<DateTime<Utc>>,
+}
+
+impl ObservationView {
+    pub fn new(info: &Observation, units: &Units) -> Self {
+        let temperature = |celsius: Option<f64>| celsius.map(|celsius| round(units.temperature.convert(celsius)));
+        let speed = |speed: Option<f64>| speed.map(|speed| round(units.speed.convert(speed)));
+        let pressure = |pressure: Option<f64>| pressure.map(|pressure| round(units.pressure.convert(pressure)));
+        let precipitation = |amount: Option<f64>| amount.map(|amount| round(units.precipitation.convert(amount)));
+
+        ObservationView {
+            location: LocationView {
+                name: info.location.clone(),
+                country: info.country.clone(),
+                latitude: info.coordinates.map(|at| at.latitude),
+                longitude: info.coordinates.map(|at| at.longitude),
+            },
+            observed_at: info.observed_at,
+            utc_offset_seconds: info.utc_offset.map(|offset| offset.local_minus_utc()),
+            units: UnitsView::from(units),
+            description: info.description.clone(),
+            condition: info.condition,
+            condition_code: info.condition_code,
+            icon: info.icon.clone(),
+            temperature: round(units.temperature.convert(info.temperature)),
+            feels_like: temperature(info.feels_like),
+            temp_min: temperature(info.temp_min),
+            temp_max: temperature(info.temp_max),
+            humidity: info.humidity.map(round),
+            pressure: pressure(info.pressure),
+            sea_level_pressure: pressure(info.sea_level_pressure),
+            ground_level_pressure: pressure(info.ground_level_pressure),
+            visibility: info.visibility.map(|metres| round(units.distance.convert(metres))),
+            cloud_cover: info.cloud_cover.map(round),
+            wind_speed: speed(info.wind_speed),
+            wind_direction: info.wind_direction.map(round),
+            wind_gust: speed(info.wind_gust),
+            rain_1h: precipitation(info.rain_1h),
+            rain_3h: precipitation(info.rain_3h),
+            snow_1h: precipitation(info.snow_1h),
+            snow_3h: precipitation(info.snow_3h),
+            sunrise: info.sunrise,
+            sunset: info.sunset,
+        }
+    }
+}
+
+/// One local day of a forecast
+#[derive(Serialize, Debug)]
+pub struct DayView {
+    pub date: NaiveDate,
+    pub min_temperature: f64,
+    pub max_temperature: f64,
+    pub description: String,
+    pub condition: Condition,
+    pub precipitation: Option<f64>,
+}
+
+/// A forecast day or step on its own, for formats with one record per line
+#[derive(Serialize, Debug)]
+pub struct ForecastRow<'a, T> {
+    pub location: &'a str,
+    #[serde(flatten)]
+    pub item: &'a T,
+    pub units: &'a UnitsView,
+}
+
+/// One forecast step; the time carries the location's UTC offset
+#[derive(Serialize, Debug)]
+pub struct EntryView {
+    pub time: DateTime<FixedOffset>,
+    pub description: String,
+    pub condition: Condition,
+    pub temperature: f64,
+    pub humidity: Option<f64>,
+    pub wind_speed: Option<f64>,
+    pub precipitation: Option<f64>,
+}
+
+/// A forecast with its daily summaries and every step
+#[derive(Serialize, Debug)]
+pub struct ForecastView {
+    pub location: String,
+    pub utc_offset_seconds: i32,
+    pub units: UnitsView,
+    pub days: Vec<DayView>,
+    pub entries: Vec<EntryView>,
+}
+
+impl ForecastView {
+    pub fn new(forecast: &Forecast, units: &Units) -> Self {
+        let temperature = |celsius: f64| round(units.temperature.convert(celsius));
+        let precipitation = |amount: Option<f64>| amount.map(|amount| round(units.precipitation.convert(amount)));
+
+        ForecastView {
+            location: forecast.location.clone(),
+            utc_offset_seconds: forecast.utc_offset.local_minus_utc(),
+            units: UnitsView::from(units),
+            days: forecast
+                .daily()
+                .into_iter()
+                .map(|day| DayView {
+                    date: day.date,
+                    min_temperature: temperature(day.min_temperature),
+                    max_temperature: temperature(day.max_temperature),
+                    description: day.description,
+                    condition: day.condition,
+                    precipitation: precipitation(day.precipitation),
+                })
+                .collect(),
+            entries: forecast
+                .entries
+                .iter()
+                .map(|entry| EntryView {
+                    time: forecast.local_time(entry),
+                    description: entry.description.clone(),
+                    condition: entry.condition,
+                    temperature: temperature(entry.temperature),
+                    humidity: entry.humidity.map(round),
+                    wind_speed: entry.wind_speed.map(|speed| round(units.speed.convert(speed))),
+                    precipitation: precipitation(entry.precipitation),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ForecastView {
+    /// Writes the days, or every step when `hourly`, as separate records
+    pub fn write_rows(&self, writer: &mut RecordWriter, hourly: bool) -> io::Result<()> {
+        let location = &self.location;
+        if hourly {
+            for entry in &self.entries {
+                writer.write(&ForecastRow { location, item: entry, units: &self.units })?;
+            }
+        } else {
+            for day in &self.days {
+                writer.write(&ForecastRow { location, item: day, units: &self.units })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One air quality reading with its US AQI; concentrations are in µg/m³
+#[derive(Serialize, Debug)]
+pub struct AirReadingView {
+    pub time: DateTime<Utc>,
+    pub index: Option<u8>,                // Provider's own index, 1 to 5 for OpenWeatherMap
+    pub index_name: Option<&'static str>, // Its name, e.g. "Fair"
+    pub us_aqi: u16,
+    pub category: Category,
+    pub dominant_pollutant: &'static str,
+    pub pm2_5: f64,
+    pub pm10: f64,
+    pub o3: f64,
+    pub no2: f64,
+    pub so2: f64,
+    pub co: f64,
+    pub nh3: f64,
+}
+
+impl From<&AirReading> for AirReadingView {
+    fn from(reading: &AirReading) -> Self {
+        let us_aqi = aqi::us_aqi(&reading.pollutants);
+        let pollutants = &reading.pollutants;
+        AirReadingView {
+            time: reading.time,
+            index: reading.index,
+            index_name: reading.index.map(aqi::index_name),
+            us_aqi: us_aqi.value,
+            category: us_aqi.category,
+            dominant_pollutant: us_aqi.pollutant,
+            pm2_5: round(pollutants.pm2_5),
+            pm10: round(pollutants.pm10),
+            o3: round(pollutants.o3),
+            no2: round(pollutants.no2),
+            so2: round(pollutants.so2),
+            co: round(pollutants.co),
+            nh3: round(pollutants.nh3),
+        }
+    }
+}
+
+/// The worst air of one UTC day of an air quality forecast
+#[derive(Serialize, Debug)]
+pub struct AirDayView {
+    pub date: NaiveDate,
+    pub us_aqi: u16,
+    pub category: Category,
+    pub dominant_pollutant: &'static str,
+    pub index: Option<u8>,
+    pub index_name: Option<&'static str>,
+}
+
+/// An air quality reading on its own, for formats with one record per line
+#[derive(Serialize, Debug)]
+pub struct AirRow<'a> {
+    pub location: &'a str,
+    pub period: &'static str, // "current" or "forecast"
+    #[serde(flatten)]
+    pub reading: &'a AirReadingView,
+}
+
+/// The current air quality with the forecast's days and every forecast hour
+#[derive(Serialize, Debug)]
+pub struct AirQualityView {
+    pub location: String,
+    pub concentration_unit: &'static str,
+    pub current: AirReadingView,
+    pub days: Vec<AirDayView>,
+    pub forecast: Vec<AirReadingView>,
+}
+
+impl AirQualityView {
+    /// Combines the reading taken as the current air at `location` with the forecast
+    pub fn new(location: &str, current: &AirReading, forecast: &AirQuality) -> Self {
+        AirQualityView {
+            location: location.to_string(),
+            concentration_unit: "µg/m³",
+            current: AirReadingView::from(current),
+            days: aqi::daily(&forecast.readings)
+                .into_iter()
+                .map(|day| AirDayView {
+                    date: day.date,
+                    us_aqi: day.us_aqi.value,
+                    category: day.us_aqi.category,
+                    dominant_pollutant: day.us_aqi.pollutant,
+                    index: day.index,
+                    index_name: day.index.map(aqi::index_name),
+                })
+                .collect(),
+            forecast: forecast.readings.iter().map(AirReadingView::from).collect(),
+        }
+    }
+
+    /// Writes the current reading, then every forecast hour, as separate records
+    pub fn write_rows(&self, writer: &mut RecordWriter) -> io::Result<()> {
+        let location = &self.location;
+        writer.write(&AirRow { location, period: "current", reading: &self.current })?;
+        for reading in &self.forecast {
+            writer.write(&AirRow { location, period: "forecast", reading })?;
+        }
+        Ok(())
+    }
+}
+
+/// A location of a comparison of several: its report, or the error in its place. Rows of failed
+/// locations are written with the readings of a report blanked out, so every row has the same keys.
+#[derive(Serialize, Debug)]
+pub struct ComparisonView {
+    pub query: String,                    // The location as it was given
+    pub error_code: Option<&'static str>, // Why there is no report, see ErrorDetails
+    pub error_message: Option<String>,    // The error as text
+    #[serde(flatten)]
+    pub report: Option<ObservationView>,
+}
+
+impl ComparisonView {
+    pub fn new(query: &Location, report: Result<ObservationView, &WeatherError>) -> Self {
+        match report {
+            Ok(report) => ComparisonView { query: query.to_string(), error_code: None, error_message: None, report: Some(report) },
+            Err(error) => ComparisonView {
+                query: query.to_string(),
+                error_code: Some(error.code()),
+                error_message: Some(error.to_string()),
+                report: None,
+            },
+        }
+    }
+}
+
+/// The same record with every value null, standing in for readings a row does not have
+pub fn blank(record: &Value) -> Value {
+    match record {
+        Value::Object(fields) => Value::Object(fields.iter().map(|(key, value)| (key.clone(), blank(value))).collect()),
+        _ => Value::Null,
+    }
+}
+
+/// Error printed to stderr in structured output modes: {"error": {"code": ..., "message": ...}}
+#[derive(Serialize, Debug)]
+pub struct ErrorView {
+    pub error: ErrorDetails,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ErrorDetails {
+    pub code: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub candidates: Vec<Place>, // Matching places when the code is "ambiguous"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_seconds: Option<u64>, // Wait requested by the provider when the code is "rate_limited"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,         // Location that failed, when several were looked up
+}
+
+impl ErrorView {
+    pub fn new(code: &'static str, message: impl ToString) -> Self {
+        ErrorView {
+            error: ErrorDetails { code, message: message.to_string(), candidates: Vec::new(), retry_after_seconds: None, location: None },
+        }
+    }
+}
+
+impl From<&WeatherError> for ErrorView {
+    fn from(error: &WeatherError) -> Self {
+        let mut view = ErrorView::new(error.code(), error);
+        match error {
+            WeatherError::Ambiguous { candidates, .. } => view.error.candidates = candidates.clone(),
+            WeatherError::RateLimited { retry_after } => view.error.retry_after_seconds = retry_after.map(|wait| wait.as_secs()),
+            _ => {}
+        }
+        view
+    }
+}
+
+/// Warning printed to stderr in structured output modes: {"warning": {"code": ..., "message": ...}}
+#[derive(Serialize, Debug)]
+pub struct WarningView {
+    pub warning: WarningDetails,
+}
+
+#[derive(Serialize, Debug)]
+pub struct WarningDetails {
+    pub code: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub age_seconds: Option<u64>, // Age of the cached data shown instead of a fresh report
+}
+
+/// Prints a report as pretty JSON on stdout
+pub fn print_json<T: Serialize>(value: &T) {
+    println!("{}", serde_json::to_string_pretty(value).expect("reports always serialize"));
+}
+
+/// Prints an error as a single-line JSON object on stderr
+pub fn print_error(error: &ErrorView) {
+    eprintln!("{}", serde_json::to_string(error).expect("errors always serialize"));
+}
+
+/// Prints a warning as a single-line JSON object on stderr
+pub fn print_warning(warning: &WarningView) {
+    eprintln!("{}", serde_json::to_string(warning).expect("warnings always serialize"));
+}
+
+/// Writes records to stdout as CSV rows or NDJSON lines, flushing after each so batches stream.
+/// The CSV header comes from the first record; later records fill the same columns.
+pub struct RecordWriter {
+    #[cfg_attr(not(feature = "csv"), allow(dead_code))]
+    format: OutputFormat,
+    #[cfg(feature = "csv")]
+    csv: csv::Writer<io::Stdout>,
+    #[cfg(feature = "csv")]
+    columns: Option<Vec<String>>,
+}
+
+impl RecordWriter {
+    pub fn new(format: OutputFormat) -> Self {
+        RecordWriter {
+            format,
+            #[cfg(feature = "csv")]
+            csv: csv::Writer::from_writer(io::stdout()),
+            #[cfg(feature = "csv")]
+            columns: None,
+        }
+    }
+
+    /// Writes one record: a row for CSV output, a line of JSON otherwise
+    pub fn write<T: Serialize>(&mut self, record: &T) -> io::Result<()> {
+        let value = serde_json::to_value(record).map_err(io::Error::other)?;
+        #[cfg(feature = "csv")]
+        if self.format == OutputFormat::Csv {
+            return self.write_row(value);
+        }
+        let mut stdout = io::stdout().lock();
+        serde_json::to_writer(&mut stdout, &value)?;
+        writeln!(stdout)?;
+        stdout.flush()
+    }
+
+    #[cfg(feature = "csv")]
+    fn write_row(&mut self, value: Value) -> io::Result<()> {
+        let mut cells = Vec::new();
+        flatten("", value, &mut cells);
+        let columns = match &self.columns {
+            Some(columns) => columns,
+            None => {
+                let columns: Vec<String> = cells.iter().map(|(name, _)| name.clone()).collect();
+                self.csv.write_record(&columns)?;
+                self.columns.insert(columns)
+            }
+        };
+        let row = columns.iter().map(|column| {
+            cells.iter().find(|(name, _)| name == column).map(|(_, value)| csv_cell(value)).unwrap_or_default()
+        });
+        self.csv.write_record(row)?;
+        self.csv.flush()
+    }
+}
+
+/// A record as flat name/text pairs, e.g. ("units_temperature", "°C"); missing readings are empty
+pub fn flat_fields<T: Serialize>(record: &T) -> Vec<(String, String)> {
+    let mut cells = Vec::new();
+    if let Ok(value) = serde_json::to_value(record) {
+        flatten("", value, &mut cells);
+    }
+    cells.into_iter().map(|(name, value)| (name, csv_cell(&value))).collect()
+}
+
+// Flattens nested objects into "parent_child" columns, keeping the view's field order
+fn flatten(prefix: &str, value: Value, cells: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                let name = if prefix.is_empty() { key } else { format!("{}_{}", prefix, key) };
+                flatten(&name, value, cells);
+            }
+        }
+        other => cells.push((prefix.to_string(), other)),
+    }
+}
+
+// Text of a CSV cell; missing readings are left empty
+fn csv_cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,103 @@
+//! The free Open-Meteo geocoder, which finds places for the providers without one of their own.
+
+use serde::Deserialize;
+use super::dedupe;
+use crate::error::{self, WeatherError};
+use crate::http::HttpClient;
+use crate::model::{Coordinates, Location, Place};
+
+#[derive(Deserialize, Debug)]
+struct GeocodingResponse {
+    #[serde(default)]
+    results: Vec<GeocodingResult>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GeocodingResult {
+    name: String,
+    latitude: f64,
+    longitude: f64,
+    #[serde(default)]
+    country_code: String,
+    admin1: Option<String>, // State or region
+    #[serde(default)]
+    postcodes: Vec<String>,
+}
+
+// Postal abbreviations of US states and territories and Canadian provinces, which the geocoder
+// spells out
+const STATES: &[(&str, &str, &str)] = &[
+    ("US", "AL", "Alabama"), ("US", "AK", "Alaska"), ("US", "AZ", "Arizona"), ("US", "AR", "Arkansas"),
+    ("US", "CA", "California"), ("US", "CO", "Colorado"), ("US", "CT", "Connecticut"), ("US", "DE", "Delaware"),
+    ("US", "DC", "District of Columbia"), ("US", "FL", "Florida"), ("US", "GA", "Georgia"), ("US", "HI", "Hawaii"),
+    ("US", "ID", "Idaho"), ("US", "IL", "Illinois"), ("US", "IN", "Indiana"), ("US", "IA", "Iowa"),
+    ("US", "KS", "Kansas"), ("US", "KY", "Kentucky"), ("US", "LA", "Louisiana"), ("US", "ME", "Maine"),
+    ("US", "MD", "Maryland"), ("US", "MA", "Massachusetts"), ("US", "MI", "Michigan"), ("US", "MN", "Minnesota"),
+    ("US", "MS", "Mississippi"), ("US", "MO", "Missouri"), ("US", "MT", "Montana"), ("US", "NE", "Nebraska"),
+    ("US", "NV", "Nevada"), ("US", "NH", "New Hampshire"), ("US", "NJ", "New Jersey"), ("US", "NM", "New Mexico"),
+    ("US", "NY", "New York"), ("US", "NC", "North Carolina"), ("US", "ND", "North Dakota"), ("US", "OH", "Ohio"),
+    ("US", "OK", "Oklahoma"), ("US", "OR", "Oregon"), ("US", "PA", "Pennsylvania"), ("US", "RI", "Rhode Island"),
+    ("US", "SC", "South Carolina"), ("US", "SD", "South Dakota"), ("US", "TN", "Tennessee"), ("US", "TX", "Texas"),
+    ("US", "UT", "Utah"), ("US", "VT", "Vermont"), ("US", "VA", "Virginia"), ("US", "WA", "Washington"),
+    ("US", "WV", "West Virginia"), ("US", "WI", "Wisconsin"), ("US", "WY", "Wyoming"), ("US", "PR", "Puerto Rico"),
+    ("US", "GU", "Guam"), ("US", "VI", "U.S. Virgin Islands"), ("US", "AS", "American Samoa"), ("US", "MP", "Northern Mariana Islands"),
+    ("CA", "AB", "Alberta"), ("CA", "BC", "British Columbia"), ("CA", "MB", "Manitoba"), ("CA", "NB", "New Brunswick"),
+    ("CA", "NL", "Newfoundland and Labrador"), ("CA", "NS", "Nova Scotia"), ("CA", "NT", "Northwest Territories"),
+    ("CA", "NU", "Nunavut"), ("CA", "ON", "Ontario"), ("CA", "PE", "Prince Edward Island"), ("CA", "QC", "Quebec"),
+    ("CA", "SK", "Saskatchewan"), ("CA", "YT", "Yukon"),
+];
+
+/// Resolves a location with the free Open-Meteo geocoder at `base_url`, which searches names and
+/// postal codes. The state, when given, is spelled out the way the geocoder does, e.g. "Illinois",
+/// or for the US and Canada abbreviated, e.g. "IL".
+pub async fn locate(client: &HttpClient, base_url: &str, location: &Location) -> Result<Vec<Place>, WeatherError> {
+    let matches = |wanted: &Option<String>, actual: &str| wanted.as_ref().is_none_or(|wanted| wanted.eq_ignore_ascii_case(actual));
+    match location {
+        Location::Named(query) => search(client, base_url, &query.name, |result| {
+            result.name.eq_ignore_ascii_case(&query.name)
+                && matches(&query.country, &result.country_code)
+                && query.state.as_ref().is_none_or(|state| same_state(state, result))
+        })
+        .await,
+        Location::Postal { code, country } => search(client, base_url, code, |result| {
+            result.postcodes.iter().any(|postcode| postcode.eq_ignore_ascii_case(code)) && matches(country, &result.country_code)
+        })
+        .await,
+        Location::Coordinates(at) => Ok(vec![Place::at(*at)]),
+        Location::CityId(_) => Err(WeatherError::Unsupported { feature: "city ID lookup" }),
+        Location::Saved(name) => Err(WeatherError::NotFound {
+            message: format!("@{} is a saved location, which the provider cannot look up; resolve it through WeatherClient", name),
+        }),
+    }
+}
+
+// Whether a result lies in the state the user named, spelled out or abbreviated
+fn same_state(wanted: &str, result: &GeocodingResult) -> bool {
+    let Some(admin1) = &result.admin1 else { return false };
+    wanted.eq_ignore_ascii_case(admin1)
+        || STATES.iter().any(|(country, code, name)| {
+            *country == result.country_code && code.eq_ignore_ascii_case(wanted) && name.eq_ignore_ascii_case(admin1)
+        })
+}
+
+// Searches the geocoder and keeps the results accepted by `keep`
+async fn search(client: &HttpClient, base_url: &str, text: &str, keep: impl Fn(&GeocodingResult) -> bool) -> Result<Vec<Place>, WeatherError> {
+    let request = client
+        .get(format!("{}/v1/search", base_url))
+        .query(&[("name", text), ("count", "20"), ("language", "en"), ("format", "json")]);
+    let response = client.send(request).await?;
+    let found: GeocodingResponse = error::read_json(response).await?;
+
+    let places = found
+        .results
+        .into_iter()
+        .filter(keep)
+        .map(|result| Place {
+            name: result.name,
+            state: result.admin1,
+            country: Some(result.country_code).filter(|code| !code.is_empty()),
+            coordinates: Coordinates { latitude: result.latitude, longitude: result.longitude },
+        })
+        .collect();
+    Ok(dedupe(places))
+}
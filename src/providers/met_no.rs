@@ -0,0 +1,202 @@
+//! MET Norway, free and anonymous.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, FixedOffset, Offset, Utc};
+use serde::Deserialize;
+use super::geocoding::locate;
+use super::WeatherProvider;
+use crate::error::{self, WeatherError};
+use crate::http::HttpClient;
+use crate::model::{Condition, Forecast, ForecastEntry, Location, Observation, Place};
+
+#[derive(Deserialize, Debug)]
+struct LocationForecast {
+    properties: ForecastProperties,
+}
+
+#[derive(Deserialize, Debug)]
+struct ForecastProperties {
+    timeseries: Vec<TimeStep>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TimeStep {
+    time: DateTime<Utc>,
+    data: TimeStepData,
+}
+
+#[derive(Deserialize, Debug)]
+struct TimeStepData {
+    instant: Instant,
+    next_1_hours: Option<Period>,
+    next_6_hours: Option<Period>, // Beyond the first days, steps are six hours apart and only have this
+}
+
+#[derive(Deserialize, Debug)]
+struct Instant {
+    details: InstantDetails,
+}
+
+#[derive(Deserialize, Debug)]
+struct InstantDetails {
+    air_temperature: f64,
+    relative_humidity: Option<f64>,
+    air_pressure_at_sea_level: Option<f64>,
+    cloud_area_fraction: Option<f64>,
+    wind_speed: Option<f64>,
+    wind_from_direction: Option<f64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Period {
+    summary: Summary,
+    details: Option<PeriodDetails>,
+}
+
+#[derive(Deserialize, Debug)]
+struct PeriodDetails {
+    precipitation_amount: Option<f64>, // mm over the period
+}
+
+#[derive(Deserialize, Debug)]
+struct Summary {
+    symbol_code: String,
+}
+
+// Words that make up MET Norway symbol codes such as "lightrainshowers_day"
+const SYMBOL_WORDS: &[&str] = &[
+    "clear", "sky", "fair", "partly", "cloudy", "fog", "light", "heavy", "rain", "sleet", "snow", "showers", "and",
+    "thunder", "s",
+];
+
+// Splits a symbol code into words, e.g. "heavyrainandthunder_night" -> "heavy rain and thunder"
+fn describe_symbol(symbol_code: &str) -> String {
+    let mut rest = symbol_code.split('_').next().unwrap_or_default();
+    let mut words = Vec::new();
+    while !rest.is_empty() {
+        let Some(word) = SYMBOL_WORDS.iter().filter(|word| rest.starts_with(*word)).max_by_key(|word| word.len()) else {
+            return symbol_code.to_string();
+        };
+        // met.no spells some codes "lightssleet"; the stray "s" is not a word
+        if *word != "s" {
+            words.push(*word);
+        }
+        rest = &rest[word.len()..];
+    }
+    words.join(" ")
+}
+
+// Forecast days shown, as from the other providers
+const FORECAST_DAYS: i64 = 5;
+
+// The API reports UTC only and the geocoder gives no time zone, so forecast days follow the
+// nautical time zone of the longitude: close to local time, but without daylight saving
+fn nautical_offset(longitude: f64) -> FixedOffset {
+    FixedOffset::east_opt((longitude / 15.0).round() as i32 * 3600).unwrap_or_else(|| Utc.fix())
+}
+
+/// Weather from api.met.no (MET Norway); free, no key. Current conditions and the forecast both
+/// come from the Locationforecast time series.
+pub struct MetNo {
+    client: HttpClient,
+    locationforecast_url: String,
+    geocoding_url: String,
+}
+
+impl MetNo {
+    pub fn new(client: HttpClient, base_url: &str, geocoding_url: &str) -> Self {
+        MetNo {
+            client,
+            locationforecast_url: format!("{}/weatherapi/locationforecast/2.0/compact", base_url),
+            geocoding_url: geocoding_url.to_owned(),
+        }
+    }
+
+    // Fetches the Locationforecast time series at a place, earliest step first
+    async fn timeseries(&self, place: &Place) -> Result<Vec<TimeStep>, WeatherError> {
+        // met.no asks clients to send at most four decimals
+        let lat = format!("{:.4}", place.coordinates.latitude);
+        let lon = format!("{:.4}", place.coordinates.longitude);
+        let response = self.client.send(self.client.get(&self.locationforecast_url).query(&[("lat", lat), ("lon", lon)])).await?;
+        let forecast: LocationForecast = error::read_json(response).await?;
+        Ok(forecast.properties.timeseries)
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for MetNo {
+    fn name(&self) -> &'static str {
+        "MET Norway"
+    }
+
+    async fn locate(&self, location: &Location) -> Result<Vec<Place>, WeatherError> {
+        locate(&self.client, &self.geocoding_url, location).await
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn current(&self, place: &Place) -> Result<Observation, WeatherError> {
+        let Some(now) = self.timeseries(place).await?.into_iter().next() else {
+            return Err(WeatherError::Provider { code: "no_data".to_string(), message: "empty forecast".to_string() });
+        };
+        let description = now
+            .data
+            .next_1_hours
+            .as_ref()
+            .map(|period| describe_symbol(&period.summary.symbol_code))
+            .unwrap_or_default();
+        let details = &now.data.instant.details;
+
+        Ok(Observation {
+            location: place.name.clone(),
+            country: place.country.clone(),
+            coordinates: Some(place.coordinates),
+            observed_at: Some(now.time),
+            condition: Condition::from_description(&description),
+            icon: now.data.next_1_hours.map(|period| period.summary.symbol_code),
+            description,
+            temperature: details.air_temperature,
+            humidity: details.relative_humidity,
+            pressure: details.air_pressure_at_sea_level,
+            sea_level_pressure: details.air_pressure_at_sea_level,
+            cloud_cover: details.cloud_area_fraction,
+            wind_speed: details.wind_speed,
+            wind_direction: details.wind_from_direction,
+            ..Observation::default()
+        })
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn forecast(&self, place: &Place) -> Result<Forecast, WeatherError> {
+        let timeseries = self.timeseries(place).await?;
+        let Some(first) = timeseries.first() else {
+            return Err(WeatherError::Provider { code: "no_data".to_string(), message: "empty forecast".to_string() });
+        };
+        let until = first.time + Duration::days(FORECAST_DAYS);
+
+        let entries = timeseries
+            .into_iter()
+            .take_while(|step| step.time < until)
+            .filter_map(|step| {
+                // The last steps have no period after them to describe
+                let period = step.data.next_1_hours.or(step.data.next_6_hours)?;
+                let description = describe_symbol(&period.summary.symbol_code);
+                let details = step.data.instant.details;
+                Some(ForecastEntry {
+                    time: step.time,
+                    condition: Condition::from_description(&description),
+                    description,
+                    temperature: details.air_temperature,
+                    humidity: details.relative_humidity,
+                    wind_speed: details.wind_speed,
+                    precipitation: period.details.and_then(|details| details.precipitation_amount),
+                })
+            })
+            .collect();
+
+        Ok(Forecast {
+            location: place.name.clone(),
+            utc_offset: nautical_offset(place.coordinates.longitude),
+            entries,
+        })
+    }
+}
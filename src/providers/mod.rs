@@ -0,0 +1,171 @@
+//! The weather services a [`WeatherClient`](crate::WeatherClient) can fetch from, each behind the
+//! cargo feature of the same name.
+
+#[cfg(any(feature = "open-meteo", feature = "met-no", feature = "nws"))]
+mod geocoding;
+#[cfg(feature = "met-no")]
+mod met_no;
+#[cfg(feature = "nws")]
+mod nws;
+#[cfg(feature = "open-meteo")]
+mod open_meteo;
+#[cfg(feature = "openweathermap")]
+mod openweathermap;
+
+use std::fmt;
+use std::sync::Arc;
+use async_trait::async_trait;
+use clap::ValueEnum;
+use serde::Deserialize;
+use crate::error::WeatherError;
+use crate::http::{HttpClient, HttpSettings};
+use crate::model::{AirQuality, Forecast, Location, Observation, Place};
+use crate::quota::Limiter;
+use crate::redact::ApiKey;
+
+/// User agent sent with every request; MET Norway and the NWS reject anonymous clients
+pub const USER_AGENT: &str = concat!("weather-cli/", env!("CARGO_PKG_VERSION"), " (https://github.com/RayeMilk/weatherApplicationCLI_FOR_PRACTICE)");
+
+/// A weather service that can resolve locations to places and report the weather at a place.
+/// Providers are shared between the threads of `weather serve` and the tasks of a batch lookup.
+#[async_trait]
+pub trait WeatherProvider: Send + Sync {
+    /// Human-readable name used in messages
+    fn name(&self) -> &'static str;
+
+    /// Resolves a location to matching places; more than one means the location is ambiguous
+    async fn locate(&self, location: &Location) -> Result<Vec<Place>, WeatherError>;
+
+    /// Fetches current conditions at a geocoded place
+    async fn current(&self, place: &Place) -> Result<Observation, WeatherError>;
+
+    /// Fetches the multi-day forecast for a geocoded place; the default reports it as unsupported
+    async fn forecast(&self, _place: &Place) -> Result<Forecast, WeatherError> {
+        Err(WeatherError::Unsupported { feature: "forecast" })
+    }
+
+    /// Fetches the current air pollution at a geocoded place; the default reports it as unsupported
+    async fn air_quality(&self, _place: &Place) -> Result<AirQuality, WeatherError> {
+        Err(WeatherError::Unsupported { feature: "pollution report" })
+    }
+
+    /// Fetches the hourly air pollution forecast for a geocoded place; the default reports it as unsupported
+    async fn air_forecast(&self, _place: &Place) -> Result<AirQuality, WeatherError> {
+        Err(WeatherError::Unsupported { feature: "pollution report" })
+    }
+}
+
+// Drops candidates that only differ in their coordinates; geocoders list some towns twice
+fn dedupe(places: Vec<Place>) -> Vec<Place> {
+    let mut unique: Vec<Place> = Vec::new();
+    for place in places {
+        if !unique.iter().any(|seen| seen.name == place.name && seen.state == place.state && seen.country == place.country) {
+            unique.push(place);
+        }
+    }
+    unique
+}
+
+/// Where each service is reached, the `[endpoints]` table of the config file. Unset entries use
+/// the public services; a mirror or a test server can take their place.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Endpoints {
+    pub openweathermap: Option<String>, // Defaults to https://api.openweathermap.org
+    pub open_meteo: Option<String>,     // Defaults to https://api.open-meteo.com
+    pub met_no: Option<String>,         // Defaults to https://api.met.no
+    pub nws: Option<String>,            // Defaults to https://api.weather.gov
+    pub geocoding: Option<String>,      // Open-Meteo geocoder used by all but OpenWeatherMap
+}
+
+impl Endpoints {
+    /// Endpoint of a provider, without a trailing slash
+    pub fn base_url(&self, kind: ProviderKind) -> &str {
+        let (configured, default) = match kind {
+            #[cfg(feature = "openweathermap")]
+            ProviderKind::OpenWeatherMap => (&self.openweathermap, "https://api.openweathermap.org"),
+            #[cfg(feature = "open-meteo")]
+            ProviderKind::OpenMeteo => (&self.open_meteo, "https://api.open-meteo.com"),
+            #[cfg(feature = "met-no")]
+            ProviderKind::MetNo => (&self.met_no, "https://api.met.no"),
+            #[cfg(feature = "nws")]
+            ProviderKind::Nws => (&self.nws, "https://api.weather.gov"),
+        };
+        configured.as_deref().unwrap_or(default).trim_end_matches('/')
+    }
+
+    /// Endpoint of the Open-Meteo geocoder, without a trailing slash
+    pub fn geocoding_url(&self) -> &str {
+        self.geocoding.as_deref().unwrap_or("https://geocoding-api.open-meteo.com").trim_end_matches('/')
+    }
+}
+
+/// The weather services this build can talk to
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProviderKind {
+    #[cfg(feature = "openweathermap")]
+    #[value(name = "openweathermap")]
+    #[serde(rename = "openweathermap")]
+    OpenWeatherMap,
+    #[cfg(feature = "open-meteo")]
+    OpenMeteo,
+    #[cfg(feature = "met-no")]
+    MetNo,
+    #[cfg(feature = "nws")]
+    Nws,
+}
+
+/// OpenWeatherMap when it is compiled in, otherwise the first provider that is
+impl Default for ProviderKind {
+    fn default() -> Self {
+        ProviderKind::value_variants()[0]
+    }
+}
+
+impl ProviderKind {
+    /// Only OpenWeatherMap needs a key; the others are free and anonymous
+    pub fn requires_api_key(self) -> bool {
+        match self {
+            #[cfg(feature = "openweathermap")]
+            ProviderKind::OpenWeatherMap => true,
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    /// Constructs the provider, reaching it and the geocoder through `endpoints`; `api_key` is only
+    /// used by OpenWeatherMap, which fails with [`WeatherError::Auth`] without one. Every request,
+    /// retries included, takes a call from `limiter`.
+    #[cfg_attr(not(feature = "openweathermap"), allow(unused_variables))]
+    pub fn build(
+        self,
+        api_key: Option<ApiKey>,
+        endpoints: &Endpoints,
+        http: &HttpSettings,
+        limiter: Option<Arc<Limiter>>,
+    ) -> Result<Box<dyn WeatherProvider>, WeatherError> {
+        let client = HttpClient::new(http, limiter)?;
+        let base_url = endpoints.base_url(self);
+        Ok(match self {
+            #[cfg(feature = "openweathermap")]
+            ProviderKind::OpenWeatherMap => {
+                let api_key = api_key.ok_or_else(|| WeatherError::Auth { message: "OpenWeatherMap needs an API key and none was given".to_string() })?;
+                Box::new(openweathermap::OpenWeatherMap::new(client, api_key, base_url))
+            }
+            #[cfg(feature = "open-meteo")]
+            ProviderKind::OpenMeteo => Box::new(open_meteo::OpenMeteo::new(client, base_url, endpoints.geocoding_url())),
+            #[cfg(feature = "met-no")]
+            ProviderKind::MetNo => Box::new(met_no::MetNo::new(client, base_url, endpoints.geocoding_url())),
+            #[cfg(feature = "nws")]
+            ProviderKind::Nws => Box::new(nws::Nws::new(client, base_url, endpoints.geocoding_url())),
+        })
+    }
+}
+
+impl fmt::Display for ProviderKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.to_possible_value().expect("no skipped variants");
+        f.write_str(name.get_name())
+    }
+}
@@ -0,0 +1,203 @@
+//! The US National Weather Service, free and anonymous, covering the United States only.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Offset, Utc};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use super::geocoding::locate;
+use super::WeatherProvider;
+use crate::error::{self, WeatherError};
+use crate::http::HttpClient;
+use crate::model::{Condition, Forecast, ForecastEntry, Location, Observation, Place};
+
+#[derive(Deserialize, Debug)]
+struct Point {
+    properties: PointProperties,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PointProperties {
+    observation_stations: String,
+    forecast_hourly: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct StationCollection {
+    features: Vec<Station>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Station {
+    id: String, // URL of the station, e.g. https://api.weather.gov/stations/KNYC
+}
+
+#[derive(Deserialize, Debug)]
+struct LatestObservation {
+    properties: ObservationProperties,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ObservationProperties {
+    timestamp: DateTime<Utc>,
+    #[serde(default)]
+    text_description: String,
+    icon: Option<String>,
+    temperature: Measurement,         // degC
+    relative_humidity: Measurement,   // percent
+    barometric_pressure: Measurement, // Pa
+    sea_level_pressure: Measurement,  // Pa
+    wind_speed: Measurement,          // km/h
+    wind_direction: Measurement,      // degrees
+    wind_gust: Measurement,           // km/h
+    visibility: Measurement,          // m
+    heat_index: Measurement,          // degC
+    wind_chill: Measurement,          // degC
+}
+
+#[derive(Deserialize, Debug)]
+struct HourlyForecast {
+    properties: HourlyProperties,
+}
+
+#[derive(Deserialize, Debug)]
+struct HourlyProperties {
+    periods: Vec<HourlyPeriod>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct HourlyPeriod {
+    start_time: String,     // RFC 3339 in the location's local offset
+    temperature: f64,       // degC, as units=si is requested
+    wind_speed: String,     // e.g. "10 km/h" or "5 to 10 km/h"
+    short_forecast: String, // e.g. "Chance Light Rain"
+    relative_humidity: Option<Measurement>,
+}
+
+// A quality-controlled value; stations leave it null when a sensor reports nothing
+#[derive(Deserialize, Debug)]
+struct Measurement {
+    value: Option<f64>,
+}
+
+/// Latest station observations from api.weather.gov (US National Weather Service); US only, no key
+pub struct Nws {
+    client: HttpClient,
+    base_url: String,
+    geocoding_url: String,
+}
+
+impl Nws {
+    pub fn new(client: HttpClient, base_url: &str, geocoding_url: &str) -> Self {
+        Nws {
+            client,
+            base_url: base_url.to_owned(),
+            geocoding_url: geocoding_url.to_owned(),
+        }
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T, WeatherError> {
+        let response = self.client.send(self.client.get(url).header(reqwest::header::ACCEPT, "application/geo+json")).await?;
+        error::read_json(response).await
+    }
+
+    // Looks up the NWS grid point of a place
+    async fn point(&self, place: &Place) -> Result<Point, WeatherError> {
+        let at = place.coordinates;
+        self.get_json(&format!("{}/points/{:.4},{:.4}", self.base_url, at.latitude, at.longitude))
+            .await
+            .map_err(|e| match e {
+                WeatherError::NotFound { .. } => WeatherError::NotFound {
+                    message: format!("{} is outside the area covered by the US National Weather Service", place.name),
+                },
+                other => other,
+            })
+    }
+}
+
+// Takes the upper bound of a wind speed such as "5 to 10 km/h", converted to m/s
+fn parse_wind_speed(text: &str) -> Option<f64> {
+    let kmh: f64 = text.split_whitespace().rev().find_map(|word| word.parse().ok())?;
+    Some(kmh / 3.6)
+}
+
+#[async_trait]
+impl WeatherProvider for Nws {
+    fn name(&self) -> &'static str {
+        "US National Weather Service"
+    }
+
+    async fn locate(&self, location: &Location) -> Result<Vec<Place>, WeatherError> {
+        locate(&self.client, &self.geocoding_url, location).await
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn current(&self, place: &Place) -> Result<Observation, WeatherError> {
+        let point = self.point(place).await?;
+        let stations: StationCollection = self.get_json(&point.properties.observation_stations).await?;
+        let Some(station) = stations.features.first() else {
+            return Err(WeatherError::NotFound { message: format!("no observation station near {}", place.name) });
+        };
+        let latest: LatestObservation = self.get_json(&format!("{}/observations/latest", station.id)).await?;
+        let observed = latest.properties;
+
+        let Some(temperature) = observed.temperature.value else {
+            return Err(WeatherError::Provider {
+                code: "no_data".to_string(),
+                message: format!("station {} reported no temperature", station.id),
+            });
+        };
+        let pressure = observed.sea_level_pressure.value.or(observed.barometric_pressure.value);
+
+        Ok(Observation {
+            location: place.name.clone(),
+            country: place.country.clone(),
+            coordinates: Some(place.coordinates),
+            observed_at: Some(observed.timestamp),
+            description: observed.text_description.to_lowercase(),
+            condition: Condition::from_description(&observed.text_description),
+            icon: observed.icon,
+            temperature,
+            feels_like: observed.heat_index.value.or(observed.wind_chill.value),
+            humidity: observed.relative_humidity.value,
+            pressure: pressure.map(|pascals| pascals / 100.0),
+            sea_level_pressure: observed.sea_level_pressure.value.map(|pascals| pascals / 100.0),
+            ground_level_pressure: observed.barometric_pressure.value.map(|pascals| pascals / 100.0),
+            visibility: observed.visibility.value,
+            wind_speed: observed.wind_speed.value.map(|kmh| kmh / 3.6),
+            wind_direction: observed.wind_direction.value,
+            wind_gust: observed.wind_gust.value.map(|kmh| kmh / 3.6),
+            ..Observation::default()
+        })
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn forecast(&self, place: &Place) -> Result<Forecast, WeatherError> {
+        let point = self.point(place).await?;
+        let hourly: HourlyForecast = self.get_json(&format!("{}?units=si", point.properties.forecast_hourly)).await?;
+
+        let mut utc_offset = Utc.fix();
+        let entries = hourly
+            .properties
+            .periods
+            .into_iter()
+            .filter_map(|period| {
+                let start = DateTime::parse_from_rfc3339(&period.start_time).ok()?;
+                utc_offset = *start.offset();
+                Some(ForecastEntry {
+                    time: start.with_timezone(&Utc),
+                    description: period.short_forecast.to_lowercase(),
+                    condition: Condition::from_description(&period.short_forecast),
+                    temperature: period.temperature,
+                    humidity: period.relative_humidity.and_then(|humidity| humidity.value),
+                    wind_speed: parse_wind_speed(&period.wind_speed),
+                    precipitation: None,
+                })
+            })
+            .collect();
+
+        Ok(Forecast { location: place.name.clone(), utc_offset, entries })
+    }
+}
@@ -0,0 +1,195 @@
+//! Open-Meteo, free and anonymous.
+
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset, Offset, Utc};
+use serde::Deserialize;
+use super::geocoding::locate;
+use super::WeatherProvider;
+use crate::error::{self, WeatherError};
+use crate::http::HttpClient;
+use crate::model::{Condition, Forecast, ForecastEntry, Location, Observation, Place};
+
+#[derive(Deserialize, Debug)]
+struct CurrentResponse {
+    utc_offset_seconds: i32,
+    current: CurrentValues,
+}
+
+// Values named in CURRENT_VARIABLES; `time` is Unix seconds thanks to timeformat=unixtime
+#[derive(Deserialize, Debug)]
+struct CurrentValues {
+    time: i64,
+    temperature_2m: f64,
+    apparent_temperature: Option<f64>,
+    relative_humidity_2m: Option<f64>,
+    pressure_msl: Option<f64>,
+    surface_pressure: Option<f64>,
+    cloud_cover: Option<f64>,
+    wind_speed_10m: Option<f64>,
+    wind_direction_10m: Option<f64>,
+    wind_gusts_10m: Option<f64>,
+    rain: Option<f64>, // mm over the preceding hour
+    weather_code: Option<u8>,
+}
+
+const CURRENT_VARIABLES: &str = "temperature_2m,apparent_temperature,relative_humidity_2m,pressure_msl,surface_pressure,\
+cloud_cover,wind_speed_10m,wind_direction_10m,wind_gusts_10m,rain,weather_code";
+
+#[derive(Deserialize, Debug)]
+struct HourlyResponse {
+    utc_offset_seconds: i32,
+    hourly: HourlyValues,
+}
+
+// Parallel arrays, one value per hour; times are Unix seconds thanks to timeformat=unixtime
+#[derive(Deserialize, Debug)]
+struct HourlyValues {
+    time: Vec<i64>,
+    temperature_2m: Vec<Option<f64>>,
+    relative_humidity_2m: Vec<Option<f64>>,
+    wind_speed_10m: Vec<Option<f64>>,
+    precipitation: Vec<Option<f64>>,
+    weather_code: Vec<Option<u8>>,
+}
+
+// Describes a WMO weather interpretation code as used by Open-Meteo
+fn describe_wmo_code(code: u8) -> (&'static str, Condition) {
+    match code {
+        0 => ("clear sky", Condition::Clear),
+        1 => ("mainly clear", Condition::Cloudy),
+        2 => ("partly cloudy", Condition::Cloudy),
+        3 => ("overcast", Condition::Overcast),
+        45 => ("fog", Condition::Fog),
+        48 => ("depositing rime fog", Condition::Fog),
+        51 | 53 | 55 => ("drizzle", Condition::Rain),
+        56 | 57 => ("freezing drizzle", Condition::Rain),
+        61 => ("light rain", Condition::Rain),
+        63 => ("moderate rain", Condition::Rain),
+        65 => ("heavy rain", Condition::Rain),
+        66 | 67 => ("freezing rain", Condition::Rain),
+        71 => ("light snow", Condition::Snow),
+        73 => ("moderate snow", Condition::Snow),
+        75 => ("heavy snow", Condition::Snow),
+        77 => ("snow grains", Condition::Snow),
+        80..=82 => ("rain showers", Condition::Rain),
+        85 | 86 => ("snow showers", Condition::Snow),
+        95 => ("thunderstorm", Condition::Thunderstorm),
+        96 | 99 => ("thunderstorm with hail", Condition::Thunderstorm),
+        _ => ("unknown", Condition::Unknown),
+    }
+}
+
+/// Weather from open-meteo.com; free, no key
+pub struct OpenMeteo {
+    client: HttpClient,
+    forecast_url: String,
+    geocoding_url: String,
+}
+
+impl OpenMeteo {
+    pub fn new(client: HttpClient, base_url: &str, geocoding_url: &str) -> Self {
+        OpenMeteo {
+            client,
+            forecast_url: format!("{}/v1/forecast", base_url),
+            geocoding_url: geocoding_url.to_owned(),
+        }
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for OpenMeteo {
+    fn name(&self) -> &'static str {
+        "Open-Meteo"
+    }
+
+    async fn locate(&self, location: &Location) -> Result<Vec<Place>, WeatherError> {
+        locate(&self.client, &self.geocoding_url, location).await
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn current(&self, place: &Place) -> Result<Observation, WeatherError> {
+        let at = place.coordinates;
+        let request = self
+            .client
+            .get(&self.forecast_url)
+            .query(&[("latitude", at.latitude), ("longitude", at.longitude)])
+            .query(&[
+                ("current", CURRENT_VARIABLES),
+                ("wind_speed_unit", "ms"),
+                ("timezone", "auto"),
+                ("timeformat", "unixtime"),
+            ]);
+        let response = self.client.send(request).await?;
+        let forecast: CurrentResponse = error::read_json(response).await?;
+        let current = forecast.current;
+
+        let (description, condition) = current.weather_code.map_or(("unknown", Condition::Unknown), describe_wmo_code);
+        Ok(Observation {
+            location: place.name.clone(),
+            country: place.country.clone(),
+            coordinates: Some(at),
+            observed_at: DateTime::from_timestamp(current.time, 0),
+            utc_offset: FixedOffset::east_opt(forecast.utc_offset_seconds),
+            description: description.to_string(),
+            condition,
+            condition_code: current.weather_code.map(u32::from),
+            temperature: current.temperature_2m,
+            feels_like: current.apparent_temperature,
+            humidity: current.relative_humidity_2m,
+            pressure: current.pressure_msl,
+            sea_level_pressure: current.pressure_msl,
+            ground_level_pressure: current.surface_pressure,
+            cloud_cover: current.cloud_cover,
+            wind_speed: current.wind_speed_10m,
+            wind_direction: current.wind_direction_10m,
+            wind_gust: current.wind_gusts_10m,
+            rain_1h: current.rain,
+            ..Observation::default()
+        })
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn forecast(&self, place: &Place) -> Result<Forecast, WeatherError> {
+        let at = place.coordinates;
+        let request = self
+            .client
+            .get(&self.forecast_url)
+            .query(&[("latitude", at.latitude), ("longitude", at.longitude)])
+            .query(&[
+                ("hourly", "temperature_2m,relative_humidity_2m,wind_speed_10m,precipitation,weather_code"),
+                ("wind_speed_unit", "ms"),
+                ("timezone", "auto"),
+                ("timeformat", "unixtime"),
+                ("forecast_days", "5"),
+            ]);
+        let response = self.client.send(request).await?;
+        let forecast: HourlyResponse = error::read_json(response).await?;
+        let hourly = forecast.hourly;
+
+        let entries = hourly
+            .time
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &time)| {
+                let temperature = (*hourly.temperature_2m.get(i)?)?;
+                let code = hourly.weather_code.get(i).copied().flatten();
+                let (description, condition) = code.map_or(("unknown", Condition::Unknown), describe_wmo_code);
+                Some(ForecastEntry {
+                    time: DateTime::from_timestamp(time, 0)?,
+                    description: description.to_string(),
+                    condition,
+                    temperature,
+                    humidity: hourly.relative_humidity_2m.get(i).copied().flatten(),
+                    wind_speed: hourly.wind_speed_10m.get(i).copied().flatten(),
+                    precipitation: hourly.precipitation.get(i).copied().flatten(),
+                })
+            })
+            .collect();
+
+        Ok(Forecast {
+            location: place.name.clone(),
+            utc_offset: FixedOffset::east_opt(forecast.utc_offset_seconds).unwrap_or_else(|| Utc.fix()),
+            entries,
+        })
+    }
+}
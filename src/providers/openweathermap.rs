@@ -0,0 +1,422 @@
+//! OpenWeatherMap, which needs an API key.
+
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset, Offset, Utc};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use super::{dedupe, WeatherProvider};
+use crate::error::{self, WeatherError};
+use crate::http::HttpClient;
+use crate::model::{AirQuality, AirReading, Condition, Coordinates, Forecast, ForecastEntry, Location, Observation, Place, PlaceQuery, Pollutants};
+use crate::redact::ApiKey;
+
+// Struct to store weather information obtained from OpenWeatherMap API
+#[derive(Deserialize, Debug)]
+struct WeatherData {
+    coord: Option<Coord>,         // Coordinates of the location
+    weather: Vec<WeatherDetails>, // Contains description of the weather
+    main: WeatherMain,            // Holds core weather metrics
+    visibility: Option<f64>,      // Visibility in metres, at most 10 km
+    wind: WindInfo,               // Contains wind-related data
+    clouds: Option<Clouds>,       // Contains cloud cover
+    rain: Option<Precipitation>,  // Rain volumes, absent when dry
+    snow: Option<Precipitation>,  // Snow volumes, absent when dry
+    dt: i64,                      // Time of the observation, Unix time in UTC
+    sys: SystemInfo,              // Holds country, sunrise and sunset
+    timezone: Option<i32>,        // Shift from UTC in seconds
+    name: String,                 // Holds the location name
+}
+
+// Struct representing coordinates
+#[derive(Deserialize, Debug)]
+struct Coord {
+    lat: f64, // Latitude in degrees
+    lon: f64, // Longitude in degrees
+}
+
+// Struct representing weather description details
+#[derive(Deserialize, Debug)]
+struct WeatherDetails {
+    id: u32,              // Condition code, e.g. 500 for light rain
+    description: String,  // Describes the weather condition
+    icon: Option<String>, // Icon name, e.g. "10d"
+}
+
+// Struct representing main weather parameters
+#[derive(Deserialize, Debug)]
+struct WeatherMain {
+    temp: f64,               // Temperature in Celsius
+    feels_like: Option<f64>, // Apparent temperature in Celsius
+    temp_min: Option<f64>,   // Lowest temperature currently observed in the area
+    temp_max: Option<f64>,   // Highest temperature currently observed in the area
+    humidity: f64,           // Humidity percentage
+    pressure: f64,           // Pressure in hPa
+    sea_level: Option<f64>,  // Pressure at sea level in hPa
+    grnd_level: Option<f64>, // Pressure at ground level in hPa
+}
+
+// Struct representing wind information
+#[derive(Deserialize, Debug)]
+struct WindInfo {
+    speed: f64,        // Speed of the wind in m/s
+    deg: Option<f64>,  // Direction the wind blows from, in degrees
+    gust: Option<f64>, // Gust speed in m/s
+}
+
+// Struct representing cloud cover
+#[derive(Deserialize, Debug)]
+struct Clouds {
+    all: f64, // Cloudiness in percent
+}
+
+// Struct representing rain or snow volumes of the current weather
+#[derive(Deserialize, Debug)]
+struct Precipitation {
+    #[serde(rename = "1h")]
+    one_hour: Option<f64>, // Volume over the last hour in mm
+    #[serde(rename = "3h")]
+    three_hours: Option<f64>, // Volume over the last 3 hours in mm
+}
+
+// Struct representing country and daylight information
+#[derive(Deserialize, Debug)]
+struct SystemInfo {
+    country: Option<String>, // Country code, e.g. DE
+    sunrise: Option<i64>,    // Sunrise, Unix time in UTC
+    sunset: Option<i64>,     // Sunset, Unix time in UTC
+}
+
+// Response of the 5-day/3-hour forecast endpoint
+#[derive(Deserialize, Debug)]
+struct ForecastData {
+    list: Vec<ForecastItem>, // One item per 3-hour step
+    city: ForecastCity,      // Location the forecast is for
+}
+
+// Struct representing one 3-hour forecast step
+#[derive(Deserialize, Debug)]
+struct ForecastItem {
+    dt: i64,                      // Start of the step, Unix time in UTC
+    weather: Vec<WeatherDetails>, // Contains description of the weather
+    main: WeatherMain,            // Holds core weather metrics
+    wind: WindInfo,               // Contains wind-related data
+    rain: Option<Volume>,         // Rain over the step, absent when dry
+    snow: Option<Volume>,         // Snow over the step, absent when dry
+}
+
+// Struct representing a precipitation volume
+#[derive(Deserialize, Debug)]
+struct Volume {
+    #[serde(rename = "3h", default)]
+    three_hours: f64, // Volume over the last 3 hours in mm
+}
+
+// Struct representing the location of a forecast
+#[derive(Deserialize, Debug)]
+struct ForecastCity {
+    name: String,  // Holds the location name
+    timezone: i32, // Shift from UTC in seconds
+}
+
+// Response of the air pollution endpoints, current and forecast
+#[derive(Deserialize, Debug)]
+struct AirPollutionData {
+    list: Vec<AirPollutionItem>, // One item now, or one per forecast hour
+}
+
+// Struct representing the air at one time
+#[derive(Deserialize, Debug)]
+struct AirPollutionItem {
+    dt: i64,                // Time of the reading, Unix time in UTC
+    main: AirIndex,         // Holds the air quality index
+    components: Pollutants, // Concentrations in µg/m³
+}
+
+// Struct representing OpenWeatherMap's air quality index
+#[derive(Deserialize, Debug)]
+struct AirIndex {
+    aqi: u8, // 1 (good) to 5 (very poor)
+}
+
+// Description and condition of the first weather entry
+fn describe(weather: &[WeatherDetails]) -> (String, Condition) {
+    match weather.first() {
+        Some(details) => (details.description.clone(), condition_for_code(details.id)),
+        None => (String::new(), Condition::Unknown),
+    }
+}
+
+impl From<ForecastData> for Forecast {
+    fn from(data: ForecastData) -> Self {
+        let entries = data
+            .list
+            .into_iter()
+            .filter_map(|item| {
+                let (description, condition) = describe(&item.weather);
+                let precipitation = [&item.rain, &item.snow].into_iter().flatten().fold(0.0, |total, volume| total + volume.three_hours);
+                Some(ForecastEntry {
+                    time: DateTime::from_timestamp(item.dt, 0)?,
+                    description,
+                    condition,
+                    temperature: item.main.temp,
+                    humidity: Some(item.main.humidity),
+                    wind_speed: Some(item.wind.speed),
+                    precipitation: Some(precipitation),
+                })
+            })
+            .collect();
+
+        Forecast {
+            location: data.city.name,
+            utc_offset: FixedOffset::east_opt(data.city.timezone).unwrap_or_else(|| Utc.fix()),
+            entries,
+        }
+    }
+}
+
+impl From<AirPollutionData> for AirQuality {
+    fn from(data: AirPollutionData) -> Self {
+        let readings = data
+            .list
+            .into_iter()
+            .filter_map(|item| {
+                Some(AirReading {
+                    time: DateTime::from_timestamp(item.dt, 0)?,
+                    index: Some(item.main.aqi),
+                    pollutants: item.components,
+                })
+            })
+            .collect();
+        // The reply names no place; callers fill the location in
+        AirQuality { location: String::new(), readings }
+    }
+}
+
+impl From<WeatherData> for Observation {
+    fn from(data: WeatherData) -> Self {
+        let (description, condition) = describe(&data.weather);
+        let details = data.weather.first();
+        let timestamp = |seconds: i64| DateTime::from_timestamp(seconds, 0);
+        Observation {
+            location: data.name,
+            country: data.sys.country,
+            coordinates: data.coord.map(|coord| Coordinates { latitude: coord.lat, longitude: coord.lon }),
+            observed_at: timestamp(data.dt),
+            utc_offset: data.timezone.and_then(FixedOffset::east_opt),
+            description,
+            condition,
+            condition_code: details.map(|details| details.id),
+            icon: details.and_then(|details| details.icon.clone()),
+            temperature: data.main.temp,
+            feels_like: data.main.feels_like,
+            temp_min: data.main.temp_min,
+            temp_max: data.main.temp_max,
+            humidity: Some(data.main.humidity),
+            pressure: Some(data.main.pressure),
+            sea_level_pressure: data.main.sea_level,
+            ground_level_pressure: data.main.grnd_level,
+            visibility: data.visibility,
+            cloud_cover: data.clouds.map(|clouds| clouds.all),
+            wind_speed: Some(data.wind.speed),
+            wind_direction: data.wind.deg,
+            wind_gust: data.wind.gust,
+            rain_1h: data.rain.as_ref().and_then(|rain| rain.one_hour),
+            rain_3h: data.rain.as_ref().and_then(|rain| rain.three_hours),
+            snow_1h: data.snow.as_ref().and_then(|snow| snow.one_hour),
+            snow_3h: data.snow.as_ref().and_then(|snow| snow.three_hours),
+            sunrise: data.sys.sunrise.and_then(timestamp),
+            sunset: data.sys.sunset.and_then(timestamp),
+        }
+    }
+}
+
+// Maps OpenWeatherMap condition codes (https://openweathermap.org/weather-conditions)
+fn condition_for_code(code: u32) -> Condition {
+    match code {
+        200..=299 => Condition::Thunderstorm,
+        300..=599 => Condition::Rain,
+        600..=699 => Condition::Snow,
+        700..=799 => Condition::Fog,
+        800 => Condition::Clear,
+        801..=803 => Condition::Cloudy,
+        804 => Condition::Overcast,
+        _ => Condition::Unknown,
+    }
+}
+
+// Struct representing one match of the direct geocoding endpoint
+#[derive(Deserialize, Debug)]
+struct GeocodingMatch {
+    name: String,          // Holds the location name
+    lat: f64,              // Latitude in degrees
+    lon: f64,              // Longitude in degrees
+    country: String,       // Country code, e.g. US
+    state: Option<String>, // State or region, e.g. Illinois
+}
+
+// Reply of the zip geocoding endpoint; a single match
+#[derive(Deserialize, Debug)]
+struct ZipMatch {
+    name: String,    // Holds the location name
+    lat: f64,        // Latitude in degrees
+    lon: f64,        // Longitude in degrees
+    country: String, // Country code, e.g. DE
+}
+
+/// Weather and geocoding from api.openweathermap.org; needs an API key
+pub struct OpenWeatherMap {
+    client: HttpClient,
+    api_key: ApiKey,
+    base_url: String,
+}
+
+impl OpenWeatherMap {
+    pub fn new(client: HttpClient, api_key: ApiKey, base_url: &str) -> Self {
+        // The key travels in the query string, so plain HTTP is only fine for a local test server
+        let local = reqwest::Url::parse(base_url)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| host == "localhost" || host.parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_loopback())))
+            .unwrap_or(false);
+        if base_url.starts_with("http://") && !local {
+            tracing::warn!(base_url, "base_url uses plain HTTP, so the API key is sent unencrypted");
+        }
+        OpenWeatherMap {
+            client,
+            api_key,
+            base_url: base_url.to_owned(),
+        }
+    }
+
+    // Sends a query to an endpoint such as "data/2.5/weather" and decodes the reply
+    async fn query<T: DeserializeOwned>(&self, endpoint: &str, params: &[(&str, String)]) -> Result<T, WeatherError> {
+        let request = self
+            .client
+            .get(format!("{}/{}", self.base_url, endpoint))
+            .query(params)
+            .query(&[("appid", self.api_key.expose())]);
+        error::read_json(self.client.send(request).await?).await
+    }
+
+    // Looks a place name up with the direct geocoding endpoint
+    async fn search(&self, query: &PlaceQuery) -> Result<Vec<Place>, WeatherError> {
+        // The API takes "name,state,country" with the state only honoured for the US
+        let q = [Some(&query.name), query.state.as_ref(), query.country.as_ref()]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(",");
+        let matches: Vec<GeocodingMatch> = self.query("geo/1.0/direct", &[("q", q), ("limit", "5".to_string())]).await?;
+
+        let places = matches
+            .into_iter()
+            .map(|found| Place {
+                name: found.name,
+                state: found.state,
+                country: Some(found.country),
+                coordinates: Coordinates { latitude: found.lat, longitude: found.lon },
+            })
+            .collect();
+        Ok(dedupe(places))
+    }
+
+    // Fetches air pollution readings at a place from one of the air pollution endpoints
+    async fn air_pollution(&self, endpoint: &str, place: &Place) -> Result<AirQuality, WeatherError> {
+        let at = [("lat", place.coordinates.latitude.to_string()), ("lon", place.coordinates.longitude.to_string())];
+        let mut air = AirQuality::from(self.query::<AirPollutionData>(endpoint, &at).await?);
+        if air.readings.is_empty() {
+            return Err(WeatherError::NotFound { message: format!("no air pollution readings for {}", place) });
+        }
+        air.location = place.name.clone();
+        Ok(air)
+    }
+
+    // Reverse lookups often name a district, so a place the user picked keeps its name. Bare
+    // coordinates take the name the reply gives, unless it has none, as out at sea.
+    fn name(place: &Place, replied: String) -> String {
+        match place.is_unnamed() && !replied.is_empty() {
+            true => replied,
+            false => place.name.clone(),
+        }
+    }
+
+    // Query parameters asking for metric readings at a place
+    fn at(place: &Place) -> [(&'static str, String); 3] {
+        [
+            ("lat", place.coordinates.latitude.to_string()),
+            ("lon", place.coordinates.longitude.to_string()),
+            ("units", "metric".to_string()),
+        ]
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for OpenWeatherMap {
+    fn name(&self) -> &'static str {
+        "OpenWeatherMap"
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn locate(&self, location: &Location) -> Result<Vec<Place>, WeatherError> {
+        match location {
+            Location::Named(query) => self.search(query).await,
+            Location::Coordinates(at) => Ok(vec![Place::at(*at)]),
+            Location::Postal { code, country } => {
+                // The API defaults to the US when no country is given
+                let zip = match country {
+                    Some(country) => format!("{code},{country}"),
+                    None => code.clone(),
+                };
+                let found: ZipMatch = self.query("geo/1.0/zip", &[("zip", zip)]).await?;
+                Ok(vec![Place {
+                    name: found.name,
+                    state: None,
+                    country: Some(found.country),
+                    coordinates: Coordinates { latitude: found.lat, longitude: found.lon },
+                }])
+            }
+            // Saved names live in the caller's config; WeatherClient resolves them before asking
+            Location::Saved(name) => Err(WeatherError::NotFound {
+                message: format!("@{} is a saved location, which the provider cannot look up; resolve it through WeatherClient", name),
+            }),
+            Location::CityId(id) => {
+                // There is no lookup endpoint for IDs; the current weather names and places the city
+                let data: WeatherData =
+                    self.query("data/2.5/weather", &[("id", id.to_string()), ("units", "metric".to_string())]).await?;
+                let Some(coord) = data.coord else {
+                    return Err(WeatherError::NotFound { message: format!("city ID {id} has no coordinates") });
+                };
+                Ok(vec![Place {
+                    name: data.name,
+                    state: None,
+                    country: data.sys.country,
+                    coordinates: Coordinates { latitude: coord.lat, longitude: coord.lon },
+                }])
+            }
+        }
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn current(&self, place: &Place) -> Result<Observation, WeatherError> {
+        let mut observation = Observation::from(self.query::<WeatherData>("data/2.5/weather", &Self::at(place)).await?);
+        observation.location = Self::name(place, observation.location);
+        Ok(observation)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn forecast(&self, place: &Place) -> Result<Forecast, WeatherError> {
+        let mut forecast = Forecast::from(self.query::<ForecastData>("data/2.5/forecast", &Self::at(place)).await?);
+        forecast.location = Self::name(place, forecast.location);
+        Ok(forecast)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn air_quality(&self, place: &Place) -> Result<AirQuality, WeatherError> {
+        self.air_pollution("data/2.5/air_pollution", place).await
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn air_forecast(&self, place: &Place) -> Result<AirQuality, WeatherError> {
+        self.air_pollution("data/2.5/air_pollution/forecast", place).await
+    }
+}
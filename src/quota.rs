@@ -0,0 +1,266 @@
+//! A per-minute rate limit and daily and monthly call budgets, shared between processes.
+
+use std::fs::{self, File};
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use crate::error::WeatherError;
+use crate::providers::ProviderKind;
+
+/// Limits from the `[quota]` table of the config file. Limits left out are the provider's own, see
+/// [`QuotaSettings::for_provider`].
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuotaSettings {
+    pub per_minute: Option<u32>,    // Calls allowed per minute; 0 turns the limiter off
+    pub daily_limit: Option<u64>,   // Calls allowed per UTC day
+    pub monthly_limit: Option<u64>, // Calls allowed per UTC calendar month
+    pub warn_at: f64,               // Share of a daily or monthly limit that triggers a warning
+    pub refuse_at: f64,             // Share of a daily or monthly limit at which calls are refused
+    #[serde(with = "humantime_serde")]
+    pub max_wait: Duration,         // Longest wait for the per-minute bucket before giving up
+}
+
+impl Default for QuotaSettings {
+    fn default() -> Self {
+        QuotaSettings {
+            per_minute: None,
+            daily_limit: None,
+            monthly_limit: None,
+            warn_at: 0.8,
+            refuse_at: 1.0,
+            max_wait: Duration::from_secs(30),
+        }
+    }
+}
+
+impl QuotaSettings {
+    /// Fills in the limits the config file leaves out with the provider's: OpenWeatherMap's free plan
+    /// allows 60 calls per minute and 1,000,000 per month, while the keyless services publish no
+    /// fixed numbers, so their calls are only counted
+    pub fn for_provider(self, provider: ProviderKind) -> Self {
+        let (per_minute, monthly_limit) = match provider {
+            #[cfg(feature = "openweathermap")]
+            ProviderKind::OpenWeatherMap => (Some(60), Some(1_000_000)),
+            #[allow(unreachable_patterns)]
+            _ => (None, None),
+        };
+        QuotaSettings { per_minute: self.per_minute.or(per_minute), monthly_limit: self.monthly_limit.or(monthly_limit), ..self }
+    }
+
+    // Calls allowed per minute, 0 when there is no limit
+    fn rate(&self) -> u32 {
+        self.per_minute.unwrap_or(0)
+    }
+}
+
+// Usage shared by every process through quota-<provider>.json
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Usage {
+    tokens: Option<f64>,                // Calls left in the per-minute bucket
+    refilled_at: Option<DateTime<Utc>>, // When `tokens` was last brought up to date
+    day: Option<NaiveDate>,             // UTC day the daily count is for
+    day_calls: u64,                     // Calls made that day
+    month: Option<String>,              // UTC month the monthly count is for, e.g. "2026-10"
+    month_calls: u64,                   // Calls made that month
+}
+
+impl Usage {
+    // Starts new counters when the day or month has changed
+    fn roll_over(&mut self, now: DateTime<Utc>) {
+        let today = now.date_naive();
+        if self.day != Some(today) {
+            self.day = Some(today);
+            self.day_calls = 0;
+        }
+        let month = format!("{:04}-{:02}", now.year(), now.month());
+        if self.month.as_deref() != Some(month.as_str()) {
+            self.month = Some(month);
+            self.month_calls = 0;
+        }
+    }
+
+    // Tops the per-minute bucket up for the time passed since the last refill
+    fn refill(&mut self, now: DateTime<Utc>, per_minute: u32) -> f64 {
+        let capacity = f64::from(per_minute);
+        let elapsed = self.refilled_at.map_or(0.0, |then| (now - then).num_milliseconds().max(0) as f64 / 1000.0);
+        let tokens = self.tokens.map_or(capacity, |tokens| (tokens + elapsed * capacity / 60.0).min(capacity));
+        self.tokens = Some(tokens);
+        self.refilled_at = Some(now);
+        tokens
+    }
+}
+
+/// Snapshot of the usage, for `weather quota`
+#[derive(Serialize, Debug)]
+pub struct QuotaStatus {
+    pub provider: String,
+    pub per_minute: Option<u32>,    // None when calls are not rate limited
+    pub available_now: Option<u32>,
+    pub calls_today: u64,
+    pub daily_limit: Option<u64>,
+    pub calls_this_month: u64,
+    pub monthly_limit: Option<u64>,
+    pub warn_at: f64,
+    pub refuse_at: f64,
+}
+
+/// Token-bucket limiter and call counter whose state lives under $XDG_STATE_HOME/weather/,
+/// guarded by a lock file so that concurrent processes share one budget
+#[derive(Debug)]
+pub struct Limiter {
+    dir: PathBuf,
+    provider: ProviderKind,
+    pub settings: QuotaSettings,
+    warning: Mutex<Option<String>>, // Raised by the latest call, until someone takes it
+}
+
+impl Limiter {
+    /// The limiter for one provider, with its limits filled in; None when the platform has no state
+    /// or data directory
+    pub fn open(provider: ProviderKind, settings: QuotaSettings) -> Option<Self> {
+        let dir = dirs::state_dir().or_else(dirs::data_local_dir)?.join("weather");
+        Some(Limiter { dir, provider, settings: settings.for_provider(provider), warning: Mutex::new(None) })
+    }
+
+    /// Takes the budget for one call and returns how long to wait for the per-minute bucket before
+    /// making it. A daily or monthly limit getting close leaves a warning for [`Limiter::take_warning`].
+    pub fn acquire(&self) -> Result<Duration, WeatherError> {
+        let settings = &self.settings;
+        let _lock = match self.lock() {
+            Ok(lock) => lock,
+            Err(e) => {
+                // A broken state directory should not stop anyone from checking the weather
+                tracing::warn!(error = %e, "could not lock the quota state; not rate limiting");
+                return Ok(Duration::ZERO);
+            }
+        };
+        let mut usage = self.load();
+        let now = Utc::now();
+        usage.roll_over(now);
+
+        for (period, used, limit) in self.periods(&usage) {
+            if used as f64 >= limit as f64 * settings.refuse_at {
+                return Err(WeatherError::QuotaExceeded { period, used, limit });
+            }
+        }
+
+        let mut wait = Duration::ZERO;
+        if settings.rate() > 0 {
+            let tokens = usage.refill(now, settings.rate()) - 1.0;
+            if tokens < 0.0 {
+                wait = Duration::from_secs_f64(-tokens * 60.0 / f64::from(settings.rate()));
+                if wait > settings.max_wait {
+                    return Err(WeatherError::RateLimited { retry_after: Some(wait) });
+                }
+            }
+            // Going below zero reserves a token that has not been refilled yet
+            usage.tokens = Some(tokens);
+        }
+        usage.day_calls += 1;
+        usage.month_calls += 1;
+
+        let warning = self.periods(&usage).into_iter().find_map(|(period, used, limit)| {
+            (used as f64 >= limit as f64 * settings.warn_at)
+                .then(|| format!("{} of the {} limit of {} calls have been used.", used, period, limit))
+        });
+        if let Err(e) = self.save(&usage) {
+            tracing::warn!(error = %e, "could not save the quota state");
+        }
+        if warning.is_some() {
+            *self.warning.lock().unwrap_or_else(|e| e.into_inner()) = warning;
+        }
+        Ok(wait)
+    }
+
+    /// The warning left by the calls made since the last time it was taken
+    pub fn take_warning(&self) -> Option<String> {
+        self.warning.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+
+    /// Empties the per-minute bucket for `wait` after the service answered 429, so that every process
+    /// sharing it holds off, not only the one that was told. The bucket is back to one call when
+    /// `wait` is over. Returns false when there is no per-minute limit to pause.
+    pub fn pause(&self, wait: Duration) -> bool {
+        let per_minute = self.settings.rate();
+        if per_minute == 0 {
+            return false;
+        }
+        let _lock = match self.lock() {
+            Ok(lock) => lock,
+            Err(e) => {
+                tracing::warn!(error = %e, "could not lock the quota state");
+                return false;
+            }
+        };
+        let mut usage = self.load();
+        let tokens = usage.refill(Utc::now(), per_minute);
+        usage.tokens = Some(tokens.min(1.0) - wait.as_secs_f64() * f64::from(per_minute) / 60.0);
+        match self.save(&usage) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!(error = %e, "could not save the quota state");
+                false
+            }
+        }
+    }
+
+    /// Current usage without taking anything
+    pub fn status(&self) -> io::Result<QuotaStatus> {
+        let _lock = self.lock()?;
+        let mut usage = self.load();
+        let now = Utc::now();
+        usage.roll_over(now);
+        let tokens = usage.refill(now, self.settings.rate());
+
+        Ok(QuotaStatus {
+            provider: self.provider.to_string(),
+            per_minute: self.settings.per_minute.filter(|per_minute| *per_minute > 0),
+            available_now: (self.settings.rate() > 0).then(|| tokens.max(0.0).floor() as u32),
+            calls_today: usage.day_calls,
+            daily_limit: self.settings.daily_limit,
+            calls_this_month: usage.month_calls,
+            monthly_limit: self.settings.monthly_limit,
+            warn_at: self.settings.warn_at,
+            refuse_at: self.settings.refuse_at,
+        })
+    }
+
+    /// Where the state file lives, for `weather config`
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(format!("quota-{}.json", self.provider))
+    }
+
+    // The configured periods with their usage
+    fn periods(&self, usage: &Usage) -> Vec<(&'static str, u64, u64)> {
+        [("daily", usage.day_calls, self.settings.daily_limit), ("monthly", usage.month_calls, self.settings.monthly_limit)]
+            .into_iter()
+            .filter_map(|(period, used, limit)| Some((period, used, limit?)))
+            .collect()
+    }
+
+    // Holds an exclusive lock on quota-<provider>.lock until the returned file is dropped
+    fn lock(&self) -> io::Result<File> {
+        fs::create_dir_all(&self.dir)?;
+        let file = File::create(self.dir.join(format!("quota-{}.lock", self.provider)))?;
+        file.lock()?;
+        Ok(file)
+    }
+
+    fn load(&self) -> Usage {
+        match fs::read_to_string(self.path()) {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+                tracing::warn!(error = %e, "ignoring unreadable quota state");
+                Usage::default()
+            }),
+            Err(_) => Usage::default(),
+        }
+    }
+
+    fn save(&self, usage: &Usage) -> io::Result<()> {
+        fs::write(self.path(), serde_json::to_vec_pretty(usage).map_err(io::Error::other)?)
+    }
+}
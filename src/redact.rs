@@ -0,0 +1,114 @@
+//! Keeps API keys and other secrets out of errors and log lines.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::{OnceLock, RwLock};
+use serde::{Deserialize, Deserializer};
+
+/// Placeholder written wherever a secret would have appeared
+pub const REDACTED: &str = "[REDACTED]";
+
+// Every secret seen by this process; `redact` hides all of them
+static SECRETS: OnceLock<RwLock<Vec<String>>> = OnceLock::new();
+
+fn secrets() -> &'static RwLock<Vec<String>> {
+    SECRETS.get_or_init(|| RwLock::new(Vec::new()))
+}
+
+/// Registers a secret so that every later call to `redact` hides it
+pub fn register(secret: &str) {
+    if secret.is_empty() {
+        return;
+    }
+    let mut known = secrets().write().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if !known.iter().any(|existing| existing == secret) {
+        known.push(secret.to_string());
+        // Longest first, so a secret containing another is replaced whole
+        known.sort_by_key(|existing| std::cmp::Reverse(existing.len()));
+    }
+}
+
+/// Replaces every registered secret in `text` with REDACTED
+pub fn redact(text: &str) -> String {
+    let known = secrets().read().unwrap_or_else(|poisoned| poisoned.into_inner());
+    known.iter().fold(text.to_string(), |text, secret| text.replace(secret.as_str(), REDACTED))
+}
+
+/// Strips registered secrets from the URL carried by a reqwest error
+pub fn redact_error(mut error: reqwest::Error) -> reqwest::Error {
+    if let Some(url) = error.url_mut() {
+        if let Ok(clean) = reqwest::Url::parse(&redact(url.as_str())) {
+            *url = clean;
+        }
+    }
+    error
+}
+
+/// API key whose Debug and Display output never reveal the value
+#[derive(Clone, PartialEq)]
+pub struct ApiKey(String);
+
+impl ApiKey {
+    /// Wraps a key and registers it for redaction
+    pub fn new(key: impl Into<String>) -> Self {
+        let key = key.into();
+        register(&key);
+        ApiKey(key)
+    }
+
+    /// The raw key, for building request URLs only
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ApiKey({})", REDACTED)
+    }
+}
+
+impl fmt::Display for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<'de> Deserialize<'de> for ApiKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(ApiKey::new)
+    }
+}
+
+/// Writer that redacts each chunk before passing it on; tracing writes one event per chunk
+pub struct RedactingWriter<W: Write>(W);
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        self.0.write_all(redact(&text).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Installs the tracing subscriber, writing redacted log lines to stderr.
+/// WEATHER_LOG takes an env-filter directive; otherwise `verbosity` picks the level.
+pub fn init_tracing(verbosity: u8) {
+    let default_level = match verbosity {
+        0 => "warn",
+        1 => "debug",
+        _ => "trace",
+    };
+    let filter = tracing_subscriber::EnvFilter::try_from_env("WEATHER_LOG")
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(format!("weather={}", default_level)));
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(|| RedactingWriter(io::stderr()))
+        .with_ansi(false)
+        .init();
+}
@@ -0,0 +1,268 @@
+//! Text renderers: the colored reports and forecasts the command line prints, and the pieces
+//! they are made of.
+
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use colored::*;
+use crate::aqi::{self, Category};
+use crate::model::{AirQuality, AirReading, Condition, Forecast, Observation};
+use crate::units::Units;
+
+/// Formats the current conditions as a colored report, with trend arrows against the previous
+/// reading when there is one. `detailed` adds every reading of [`detailed_readings`].
+pub fn weather_text(units: &Units, weather_info: &Observation, detailed: bool, previous: Option<&Observation>) -> ColoredString {
+    let temp = weather_info.temperature;
+    let trend = |reading: fn(&Observation) -> Option<f64>| {
+        previous.map_or("", |previous| trend_arrow(reading(weather_info), reading(previous)))
+    };
+
+    let mut formatted_details = format!(
+        "Weather Update for {}: {} {}
+            > Temperature: {}{}
+            > Humidity: {}{}
+            > Pressure: {}{}
+            > Wind Speed: {}{}",
+        weather_info.location,
+        weather_info.description,
+        emoji_for_temperature(temp),
+        units.temperature(temp),
+        trend(|info| Some(info.temperature)),
+        format_reading(weather_info.humidity.map(|humidity| format!("{:.1}%", humidity))),
+        trend(|info| info.humidity),
+        format_reading(weather_info.pressure.map(|pressure| units.pressure(pressure))),
+        trend(|info| info.pressure),
+        format_reading(weather_info.wind_speed.map(|speed| units.speed(speed))),
+        trend(|info| info.wind_speed)
+    );
+    if detailed {
+        for (label, value) in detailed_readings(units, weather_info) {
+            formatted_details.push_str(&format!("\n            > {}: {}", label, value));
+        }
+    }
+
+    colorize_weather_output(weather_info.condition, &formatted_details)
+}
+
+/// Arrow showing which way a reading moved; changes too small to show with one decimal count as steady
+pub fn trend_arrow(now: Option<f64>, before: Option<f64>) -> &'static str {
+    match (now, before) {
+        (Some(now), Some(before)) if now - before >= 0.05 => " ↑",
+        (Some(now), Some(before)) if before - now >= 0.05 => " ↓",
+        (Some(_), Some(_)) => " →",
+        _ => "",
+    }
+}
+
+/// Lists the extra readings shown by --detailed in the given units, skipping those the provider did not report
+pub fn detailed_readings(units: &Units, info: &Observation) -> Vec<(&'static str, String)> {
+    let mut readings = Vec::new();
+    let mut add = |label, value: Option<String>| {
+        if let Some(value) = value {
+            readings.push((label, value));
+        }
+    };
+    let local_time = |time: Option<DateTime<Utc>>, pattern: &str| {
+        time.map(|time| match info.utc_offset {
+            Some(offset) => time.with_timezone(&offset).format(pattern).to_string(),
+            None => format!("{} UTC", time.format(pattern)),
+        })
+    };
+
+    add("Location", Some(match (&info.country, info.coordinates) {
+        (Some(country), Some(at)) => format!("{}, {} ({:.4}, {:.4})", info.location, country, at.latitude, at.longitude),
+        (Some(country), None) => format!("{}, {}", info.location, country),
+        (None, Some(at)) => format!("{} ({:.4}, {:.4})", info.location, at.latitude, at.longitude),
+        (None, None) => info.location.clone(),
+    }));
+    add("Observed", local_time(info.observed_at, "%Y-%m-%d %H:%M"));
+    add("Feels Like", info.feels_like.map(|celsius| units.temperature(celsius)));
+    add("Min/Max", match (info.temp_min, info.temp_max) {
+        (Some(min), Some(max)) => Some(format!("{} / {}", units.temperature(min), units.temperature(max))),
+        _ => None,
+    });
+    add("Sea-Level Pressure", info.sea_level_pressure.map(|pressure| units.pressure(pressure)));
+    add("Ground-Level Pressure", info.ground_level_pressure.map(|pressure| units.pressure(pressure)));
+    add("Visibility", info.visibility.map(|metres| units.distance(metres)));
+    add("Cloud Cover", info.cloud_cover.map(|cover| format!("{:.1}%", cover)));
+    add("Wind Direction", info.wind_direction.map(|degrees| format!("{:.0}° ({})", degrees, compass_point(degrees))));
+    add("Wind Gusts", info.wind_gust.map(|speed| units.speed(speed)));
+    add("Rain (1h)", info.rain_1h.map(|amount| units.precipitation(amount)));
+    add("Rain (3h)", info.rain_3h.map(|amount| units.precipitation(amount)));
+    add("Snow (1h)", info.snow_1h.map(|amount| units.precipitation(amount)));
+    add("Snow (3h)", info.snow_3h.map(|amount| units.precipitation(amount)));
+    add("Sunrise", local_time(info.sunrise, "%H:%M"));
+    add("Sunset", local_time(info.sunset, "%H:%M"));
+    add("Condition Code", match (info.condition_code, &info.icon) {
+        (Some(code), Some(icon)) => Some(format!("{} (icon {})", code, icon)),
+        (Some(code), None) => Some(code.to_string()),
+        (None, Some(icon)) => Some(format!("icon {}", icon)),
+        (None, None) => None,
+    });
+    readings
+}
+
+/// Names the 16-point compass direction for a bearing in degrees
+pub fn compass_point(degrees: f64) -> &'static str {
+    const POINTS: [&str; 16] = [
+        "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW", "NW", "NNW",
+    ];
+    POINTS[((degrees.rem_euclid(360.0) / 22.5).round() as usize) % 16]
+}
+
+/// Formats a forecast as a bold heading and one line per local day, optionally followed by each
+/// forecast step of that day
+pub fn forecast_lines(units: &Units, forecast: &Forecast, hourly: bool) -> Vec<ColoredString> {
+    let mut lines = vec![format!("Forecast for {}:", forecast.location).bold()];
+
+    for day in forecast.daily() {
+        let summary = format!(
+            "{} {} {} to {}, {}, precipitation {}",
+            day.date.format("%a %Y-%m-%d"),
+            emoji_for_temperature(day.max_temperature),
+            units.temperature(day.min_temperature),
+            units.temperature(day.max_temperature),
+            day.description,
+            format_reading(day.precipitation.map(|amount| units.precipitation(amount)))
+        );
+        lines.push(colorize_weather_output(day.condition, &summary));
+
+        if !hourly {
+            continue;
+        }
+        for entry in forecast.entries.iter().filter(|entry| forecast.local_time(entry).date_naive() == day.date) {
+            let step = format!(
+                "    > {} {} {} {}, humidity {}, wind {}, precipitation {}",
+                forecast.local_time(entry).format("%H:%M"),
+                units.temperature(entry.temperature),
+                emoji_for_temperature(entry.temperature),
+                entry.description,
+                format_reading(entry.humidity.map(|humidity| format!("{:.1}%", humidity))),
+                format_reading(entry.wind_speed.map(|speed| units.speed(speed))),
+                format_reading(entry.precipitation.map(|amount| units.precipitation(amount)))
+            );
+            lines.push(colorize_weather_output(entry.condition, &step));
+        }
+    }
+    lines
+}
+
+/// Shows a formatted reading, or "n/a" when the provider has none
+pub fn format_reading(value: Option<String>) -> String {
+    value.unwrap_or_else(|| "n/a".to_string())
+}
+
+/// Determines an emoji representation based on the temperature in °C, whatever unit is shown
+pub fn emoji_for_temperature(temp: f64) -> &'static str {
+    match temp {
+        _ if temp < 0.0 => "❄️",
+        _ if temp < 10.0 => "☁️",
+        _ if temp < 20.0 => "⛅",
+        _ if temp < 30.0 => "🌤️",
+        _ => "🔥",
+    }
+}
+
+/// Applies color effects to the weather report based on the condition
+pub fn colorize_weather_output(condition: Condition, weather_text: &str) -> ColoredString {
+    match condition {
+        Condition::Clear => weather_text.bright_yellow(),
+        Condition::Cloudy => weather_text.bright_blue(),
+        Condition::Overcast | Condition::Fog => weather_text.dimmed(),
+        Condition::Rain | Condition::Thunderstorm | Condition::Snow => weather_text.bright_cyan(),
+        Condition::Unknown => weather_text.normal(),
+    }
+}
+
+/// Formats an air quality reading as a report colored by its US AQI category: the US AQI, the
+/// provider's own index and every pollutant
+pub fn air_quality_text(location: &str, reading: &AirReading) -> ColoredString {
+    let us_aqi = aqi::us_aqi(&reading.pollutants);
+    let mut text = format!(
+        "Air Quality for {}: {}
+            > US AQI: {}, mostly {}
+            > Index: {}",
+        location,
+        us_aqi.category,
+        us_aqi.value,
+        us_aqi.pollutant,
+        index_text(reading.index)
+    );
+    for (name, concentration) in pollutant_readings(reading) {
+        text.push_str(&format!("\n            > {}: {:.1} µg/m³", name, concentration));
+    }
+    colorize_air_quality(us_aqi.category, &text)
+}
+
+/// Formats an air quality forecast as a bold heading and one line per UTC day with its worst air,
+/// optionally followed by every hourly reading of that day
+pub fn air_forecast_lines(forecast: &AirQuality, hourly: bool) -> Vec<ColoredString> {
+    let mut lines = vec![format!("Air quality forecast for {} (UTC):", forecast.location).bold()];
+
+    for day in aqi::daily(&forecast.readings) {
+        let summary = format!(
+            "{} US AQI up to {}, {} (mostly {}), index up to {}",
+            day.date.format("%a %Y-%m-%d"),
+            day.us_aqi.value,
+            day.us_aqi.category,
+            day.us_aqi.pollutant,
+            index_text(day.index)
+        );
+        lines.push(colorize_air_quality(day.us_aqi.category, &summary));
+
+        if !hourly {
+            continue;
+        }
+        for reading in forecast.readings.iter().filter(|reading| reading.time.date_naive() == day.date) {
+            let us_aqi = aqi::us_aqi(&reading.pollutants);
+            let step = format!(
+                "    > {} US AQI {}, {} (mostly {}), index {}, PM2.5 {:.1} µg/m³, O3 {:.1} µg/m³",
+                reading.time.format("%H:%M"),
+                us_aqi.value,
+                us_aqi.category,
+                us_aqi.pollutant,
+                index_text(reading.index),
+                reading.pollutants.pm2_5,
+                reading.pollutants.o3
+            );
+            lines.push(colorize_air_quality(us_aqi.category, &step));
+        }
+    }
+    lines
+}
+
+/// Lists the pollutants of a reading with their names, in the order reports show them
+pub fn pollutant_readings(reading: &AirReading) -> [(&'static str, f64); 7] {
+    let pollutants = &reading.pollutants;
+    [
+        ("PM2.5", pollutants.pm2_5),
+        ("PM10", pollutants.pm10),
+        ("O3", pollutants.o3),
+        ("NO2", pollutants.no2),
+        ("SO2", pollutants.so2),
+        ("CO", pollutants.co),
+        ("NH3", pollutants.nh3),
+    ]
+}
+
+// Shows the provider's index with its name, e.g. "2 of 5 (Fair)", or "n/a" when there is none
+fn index_text(index: Option<u8>) -> String {
+    format_reading(index.map(|index| format!("{} of 5 ({})", index, aqi::index_name(index))))
+}
+
+/// Applies the EPA's color for an air quality category, as `colorize_weather_output` does for the
+/// weather. Orange and maroon have no ANSI color, so they are given as RGB.
+pub fn colorize_air_quality(category: Category, text: &str) -> ColoredString {
+    match category {
+        Category::Good => text.bright_green(),
+        Category::Moderate => text.bright_yellow(),
+        Category::UnhealthyForSensitiveGroups => text.truecolor(255, 126, 0),
+        Category::Unhealthy => text.bright_red(),
+        Category::VeryUnhealthy => text.magenta(),
+        Category::Hazardous => text.truecolor(126, 0, 35),
+    }
+}
+
+/// Formats how long ago something happened, to the second, e.g. "3m 12s"
+pub fn format_age(age: Duration) -> String {
+    humantime::format_duration(Duration::from_secs(age.as_secs())).to_string()
+}
@@ -0,0 +1,449 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use weather::model::Observation;
+use weather::output::ObservationView;
+use crate::config::{expand_home, shell_command};
+
+// Hooks of a rule that stays true run again after this long
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30 * 60);
+
+// Rules read from rules.toml, e.g.
+//   [[rule]]
+//   name = "frost"
+//   when = "temp < 0"
+//   command = "notify-send 'Frost at the depot'"
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RuleSet {
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub name: String,             // Shown when the rule fires and passed to its command
+    pub when: Expression,         // Fires while this is true of the current reading
+    pub command: Option<String>,  // Shell command run by `weather watch` when the rule fires
+    #[serde(default = "default_cooldown", with = "humantime_serde")]
+    pub cooldown: Duration,       // Shortest time between two runs of the command
+}
+
+fn default_cooldown() -> Duration {
+    DEFAULT_COOLDOWN
+}
+
+impl Rule {
+    // Starts the rule's command with `env` added to its environment, without waiting for it. Its
+    // stdout is discarded so it cannot garble the report on screen; stderr is passed through.
+    pub fn run_command(&self, env: Vec<(String, String)>) {
+        let Some(command) = &self.command else { return };
+        let spawned = shell_command(command)
+            .envs(env)
+            .env("WEATHER_RULE", &self.name)
+            .env("WEATHER_RULE_WHEN", self.when.to_string())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .spawn();
+
+        let name = self.name.clone();
+        match spawned {
+            Ok(mut child) => {
+                thread::spawn(move || match child.wait() {
+                    Ok(status) if !status.success() => tracing::warn!(rule = %name, %status, "rule command failed"),
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!(rule = %name, error = %e, "could not wait for the rule command"),
+                });
+            }
+            Err(e) => tracing::warn!(rule = %name, error = %e, "could not run the rule command"),
+        }
+    }
+}
+
+// Errors raised while loading the rules file
+#[derive(Error, Debug)]
+pub enum RulesError {
+    #[error("could not read rules file {}: {source}", path.display())]
+    Read { path: PathBuf, source: std::io::Error },
+    #[error("invalid rules file {}: {source}", path.display())]
+    Parse { path: PathBuf, source: toml::de::Error },
+    #[error("no rules file; pass --rules or set rules_file in the config file")]
+    Missing,
+}
+
+impl RulesError {
+    // Stable identifier for scripts, used in structured error output
+    pub fn code(&self) -> &'static str {
+        match self {
+            RulesError::Read { .. } => "rules_read",
+            RulesError::Parse { .. } => "rules_parse",
+            RulesError::Missing => "rules_read",
+        }
+    }
+}
+
+impl RuleSet {
+    // Loads the rules named by --rules, else by `rules_file` in the config file, else rules.toml next
+    // to the config file. A missing rules.toml means there are no rules, unless they are `required`.
+    pub fn find(explicit: Option<&Path>, configured: Option<&Path>, required: bool) -> Result<Option<Self>, RulesError> {
+        let path = match (explicit.or(configured), default_rules_path()) {
+            (Some(path), _) => expand_home(path),
+            (None, Some(path)) if required || path.exists() => path,
+            (None, _) if required => return Err(RulesError::Missing),
+            (None, _) => return Ok(None),
+        };
+        Self::load(&path).map(Some)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, RulesError> {
+        let contents = fs::read_to_string(path).map_err(|source| RulesError::Read { path: path.to_path_buf(), source })?;
+        toml::from_str(&contents).map_err(|source| RulesError::Parse { path: path.to_path_buf(), source })
+    }
+}
+
+// How one alert rule came out against a reading; csv and ndjson write one record per rule
+#[derive(Serialize, Debug)]
+pub struct RuleView {
+    pub location: String,
+    pub rule: String,
+    pub when: String,
+    pub fired: bool,
+}
+
+// The result of `weather check`: the reading the rules were tested against and every rule
+#[derive(Serialize, Debug)]
+pub struct CheckView {
+    pub observation: ObservationView,
+    pub rules: Vec<RuleView>,
+}
+
+// Default rules file location, next to the config file, e.g. ~/.config/weather/rules.toml
+pub fn default_rules_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("weather").join("rules.toml"))
+}
+
+// A reading a rule can test; readings are in metric units (°C, m/s, hPa, mm, m, %)
+struct Field {
+    names: &'static [&'static str],
+    read: fn(&Observation) -> Reading,
+}
+
+impl Field {
+    // Whether the field holds a number rather than text, found by reading an empty observation
+    fn is_numeric(&self) -> bool {
+        matches!((self.read)(&Observation::default()), Reading::Number(_))
+    }
+}
+
+enum Reading {
+    Number(Option<f64>),
+    Text(Option<String>),
+}
+
+// Field names follow OpenWeatherMap's response (`temp`, `wind.speed`, `rain.1h`) and the JSON output (`wind_speed`)
+const FIELDS: &[Field] = &[
+    Field { names: &["temp", "temperature"], read: |info| Reading::Number(Some(info.temperature)) },
+    Field { names: &["feels_like"], read: |info| Reading::Number(info.feels_like) },
+    Field { names: &["temp_min"], read: |info| Reading::Number(info.temp_min) },
+    Field { names: &["temp_max"], read: |info| Reading::Number(info.temp_max) },
+    Field { names: &["humidity"], read: |info| Reading::Number(info.humidity) },
+    Field { names: &["pressure"], read: |info| Reading::Number(info.pressure) },
+    Field { names: &["sea_level", "sea_level_pressure"], read: |info| Reading::Number(info.sea_level_pressure) },
+    Field { names: &["grnd_level", "ground_level_pressure"], read: |info| Reading::Number(info.ground_level_pressure) },
+    Field { names: &["visibility"], read: |info| Reading::Number(info.visibility) },
+    Field { names: &["clouds", "clouds.all", "cloud_cover"], read: |info| Reading::Number(info.cloud_cover) },
+    Field { names: &["wind.speed", "wind_speed"], read: |info| Reading::Number(info.wind_speed) },
+    Field { names: &["wind.deg", "wind_direction"], read: |info| Reading::Number(info.wind_direction) },
+    Field { names: &["wind.gust", "wind_gust"], read: |info| Reading::Number(info.wind_gust) },
+    Field { names: &["rain.1h", "rain_1h"], read: |info| Reading::Number(info.rain_1h) },
+    Field { names: &["rain.3h", "rain_3h"], read: |info| Reading::Number(info.rain_3h) },
+    Field { names: &["snow.1h", "snow_1h"], read: |info| Reading::Number(info.snow_1h) },
+    Field { names: &["snow.3h", "snow_3h"], read: |info| Reading::Number(info.snow_3h) },
+    Field { names: &["description"], read: |info| Reading::Text(Some(info.description.clone())) },
+    Field { names: &["condition"], read: |info| Reading::Text(serde_json::to_value(info.condition).ok().and_then(|name| name.as_str().map(str::to_string))) },
+    Field { names: &["location", "name"], read: |info| Reading::Text(Some(info.location.clone())) },
+    Field { names: &["country"], read: |info| Reading::Text(info.country.clone()) },
+];
+
+// Finds a field by any of its names
+fn field_index(name: &str) -> Result<usize, String> {
+    FIELDS.iter().position(|field| field.names.contains(&name)).ok_or_else(|| {
+        let known: Vec<&str> = FIELDS.iter().map(|field| field.names[0]).collect();
+        format!("unknown field `{}`; known fields are {}", name, known.join(", "))
+    })
+}
+
+// Order of a comparison of several locations: a field named as in rules, descending after a "-"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortKey {
+    field: usize, // Indexes FIELDS
+    descending: bool,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (name, descending) = match text.strip_prefix('-') {
+            Some(name) => (name, true),
+            None => (text, false),
+        };
+        Ok(SortKey { field: field_index(name.trim())?, descending })
+    }
+}
+
+impl SortKey {
+    // Orders two readings; text ignores case, and readings the provider did not report come last
+    // either way
+    pub fn compare(self, a: &Observation, b: &Observation) -> Ordering {
+        let ordered = |ordering: Ordering| if self.descending { ordering.reverse() } else { ordering };
+        let read = FIELDS[self.field].read;
+        match (read(a), read(b)) {
+            (Reading::Number(Some(a)), Reading::Number(Some(b))) => ordered(a.total_cmp(&b)),
+            (Reading::Text(Some(a)), Reading::Text(Some(b))) => ordered(a.to_lowercase().cmp(&b.to_lowercase())),
+            (Reading::Number(Some(_)) | Reading::Text(Some(_)), _) => Ordering::Less,
+            (_, Reading::Number(Some(_)) | Reading::Text(Some(_))) => Ordering::Greater,
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+    Contains,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(f64),
+    Text(String),
+}
+
+#[derive(Debug)]
+enum Node {
+    Compare { field: usize, operator: Operator, value: Value }, // `field` indexes FIELDS
+    Not(Box<Node>),
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+}
+
+// A parsed condition such as `temp < 0 and wind.speed > 15` or `description contains "rain"`.
+// Comparisons with a reading the provider did not report are false.
+#[derive(Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Expression {
+    text: String,
+    root: Node,
+}
+
+impl Expression {
+    pub fn evaluate(&self, info: &Observation) -> bool {
+        Self::evaluate_node(&self.root, info)
+    }
+
+    fn evaluate_node(node: &Node, info: &Observation) -> bool {
+        match node {
+            Node::Not(inner) => !Self::evaluate_node(inner, info),
+            Node::And(left, right) => Self::evaluate_node(left, info) && Self::evaluate_node(right, info),
+            Node::Or(left, right) => Self::evaluate_node(left, info) || Self::evaluate_node(right, info),
+            Node::Compare { field, operator, value } => match ((FIELDS[*field].read)(info), value) {
+                (Reading::Number(Some(reading)), Value::Number(value)) => match operator {
+                    Operator::Less => reading < *value,
+                    Operator::LessOrEqual => reading <= *value,
+                    Operator::Greater => reading > *value,
+                    Operator::GreaterOrEqual => reading >= *value,
+                    Operator::Equal => (reading - value).abs() < 1e-9,
+                    Operator::NotEqual => (reading - value).abs() >= 1e-9,
+                    Operator::Contains => false,
+                },
+                // Text matches ignore case, so "Rain" and "light rain" both contain "rain"
+                (Reading::Text(Some(reading)), Value::Text(value)) => {
+                    let (reading, value) = (reading.to_lowercase(), value.to_lowercase());
+                    match operator {
+                        Operator::Equal => reading == value,
+                        Operator::NotEqual => reading != value,
+                        Operator::Contains => reading.contains(&value),
+                        _ => false,
+                    }
+                }
+                _ => false,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl TryFrom<String> for Expression {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        let tokens = tokenize(&text)?;
+        let mut parser = Parser { tokens, position: 0 };
+        let root = parser.or()?;
+        if let Some(token) = parser.tokens.get(parser.position) {
+            return Err(format!("unexpected {} in `{}`", token, text));
+        }
+        Ok(Expression { text, root })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String), // A field name or one of the keywords and, or, not, contains
+    Number(f64),
+    Text(String),
+    Symbol(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "`{}`", word),
+            Token::Number(number) => write!(f, "`{}`", number),
+            Token::Text(text) => write!(f, "\"{}\"", text),
+            Token::Symbol(symbol) => write!(f, "`{}`", symbol),
+        }
+    }
+}
+
+// Splits an expression into words, numbers, quoted text and operators
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    const SYMBOLS: [&str; 12] = ["<=", ">=", "==", "!=", "&&", "||", "<", ">", "=", "!", "(", ")"];
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(**symbol)) {
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        } else if c == '"' || c == '\'' {
+            let end = rest[1..].find(c).ok_or_else(|| format!("unterminated text in `{}`", text))?;
+            tokens.push(Token::Text(rest[1..=end].to_string()));
+            rest = &rest[end + 2..];
+        } else if c.is_ascii_digit() || c == '-' || c == '.' {
+            let end = rest.find(|c: char| !(c.is_ascii_digit() || c == '-' || c == '.')).unwrap_or(rest.len());
+            let number = rest[..end].parse().map_err(|_| format!("`{}` is not a number", &rest[..end]))?;
+            tokens.push(Token::Number(number));
+            rest = &rest[end..];
+        } else if c.is_alphabetic() || c == '_' {
+            let end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.')).unwrap_or(rest.len());
+            tokens.push(Token::Word(rest[..end].to_lowercase()));
+            rest = &rest[end..];
+        } else {
+            return Err(format!("unexpected `{}` in `{}`", c, text));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+// Recursive-descent parser; `not` binds tighter than `and`, which binds tighter than `or`
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    // Consumes the next token if it is one of `choices`
+    fn accept(&mut self, choices: &[Token]) -> bool {
+        let found = self.tokens.get(self.position).is_some_and(|token| choices.contains(token));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Node, String> {
+        let mut node = self.and()?;
+        while self.accept(&[Token::Word("or".to_string()), Token::Symbol("||")]) {
+            node = Node::Or(Box::new(node), Box::new(self.and()?));
+        }
+        Ok(node)
+    }
+
+    fn and(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+        while self.accept(&[Token::Word("and".to_string()), Token::Symbol("&&")]) {
+            node = Node::And(Box::new(node), Box::new(self.unary()?));
+        }
+        Ok(node)
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        if self.accept(&[Token::Word("not".to_string()), Token::Symbol("!")]) {
+            return Ok(Node::Not(Box::new(self.unary()?)));
+        }
+        if self.accept(&[Token::Symbol("(")]) {
+            let node = self.or()?;
+            if !self.accept(&[Token::Symbol(")")]) {
+                return Err("missing `)`".to_string());
+            }
+            return Ok(node);
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Node, String> {
+        let name = match self.next() {
+            Some(Token::Word(name)) => name,
+            Some(other) => return Err(format!("expected a field name, found {}", other)),
+            None => return Err("expected a field name, found the end".to_string()),
+        };
+        let field = field_index(&name)?;
+
+        let operator = match self.next() {
+            Some(Token::Symbol("<")) => Operator::Less,
+            Some(Token::Symbol("<=")) => Operator::LessOrEqual,
+            Some(Token::Symbol(">")) => Operator::Greater,
+            Some(Token::Symbol(">=")) => Operator::GreaterOrEqual,
+            Some(Token::Symbol("==" | "=")) => Operator::Equal,
+            Some(Token::Symbol("!=")) => Operator::NotEqual,
+            Some(Token::Word(word)) if word == "contains" => Operator::Contains,
+            Some(other) => return Err(format!("expected a comparison after `{}`, found {}", name, other)),
+            None => return Err(format!("expected a comparison after `{}`", name)),
+        };
+
+        let value = match self.next() {
+            Some(Token::Number(number)) => Value::Number(number),
+            Some(Token::Text(text)) => Value::Text(text),
+            Some(other) => return Err(format!("expected a number or quoted text, found {}", other)),
+            None => return Err("expected a number or quoted text, found the end".to_string()),
+        };
+
+        let numeric = FIELDS[field].is_numeric();
+        match (&value, operator) {
+            (Value::Number(_), Operator::Contains) => return Err(format!("`contains` needs quoted text, as in {} contains \"rain\"", name)),
+            (Value::Number(_), _) if !numeric => return Err(format!("`{}` is text; compare it with quoted text", name)),
+            (Value::Text(_), _) if numeric => return Err(format!("`{}` is a number; compare it with a number", name)),
+            (Value::Text(_), Operator::Equal | Operator::NotEqual | Operator::Contains) => {}
+            (Value::Text(_), _) => return Err(format!("text can only be compared with ==, != or contains, not in `{}`", name)),
+            (Value::Number(_), _) => {}
+        }
+        Ok(Node::Compare { field, operator, value })
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::process::ExitCode;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Instant;
+use serde::Serialize;
+use tiny_http::{Header, Method, Request, Response, Server};
+use weather::error::WeatherError;
+use weather::model::{Location, PlaceQuery};
+use weather::output::{ErrorView, ForecastView, ObservationView};
+use weather::units::{UnitSystem, Units};
+use crate::cli::ServeArgs;
+use crate::app::{UserInteraction, WeatherApp};
+
+// A finished response, shared by every request that waited for the same fetch
+#[derive(Clone, Debug)]
+struct Reply {
+    status: u16,
+    body: String,
+    retry_after: Option<u64>, // Seconds, sent as Retry-After with 429 and 503 replies
+}
+
+impl Reply {
+    fn json<T: Serialize>(status: u16, value: &T) -> Self {
+        let body = serde_json::to_string(value).expect("views always serialize");
+        Reply { status, body, retry_after: None }
+    }
+
+    fn error(status: u16, code: &'static str, message: impl ToString) -> Self {
+        Reply::json(status, &ErrorView::new(code, message))
+    }
+
+    // Maps a failed lookup onto the HTTP status a gateway would use
+    fn from_error(error: &WeatherError) -> Self {
+        let status = match error {
+            WeatherError::NotFound { .. } => 404,
+            WeatherError::Ambiguous { .. } => 409,
+            WeatherError::RateLimited { .. } | WeatherError::QuotaExceeded { .. } => 429,
+            WeatherError::Unsupported { .. } => 501,
+            WeatherError::Timeout(_) => 504,
+            WeatherError::NotCached { .. } => 503,
+            WeatherError::Auth { .. } | WeatherError::Network(_) | WeatherError::Decode(_) | WeatherError::Provider { .. } => 502,
+        };
+        let view = ErrorView::from(error);
+        let retry_after = view.error.retry_after_seconds;
+        Reply { retry_after, ..Reply::json(status, &view) }
+    }
+}
+
+// One fetch in progress; requests for the same data wait on `done` instead of fetching it again
+#[derive(Default)]
+struct Flight {
+    reply: Mutex<Option<Reply>>,
+    done: Condvar,
+}
+
+// Merges identical requests that arrive while the first of them is still being answered
+#[derive(Default)]
+struct Flights {
+    running: Mutex<HashMap<String, Arc<Flight>>>,
+}
+
+impl Flights {
+    // Runs `work` for the first request with this key and hands its reply to every request that
+    // joined while it ran. Later requests start a new flight, and are usually answered by the cache.
+    fn join(&self, key: &str, work: impl FnOnce() -> Reply) -> Reply {
+        let (flight, leader) = {
+            let mut running = self.running.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            match running.get(key) {
+                Some(flight) => (Arc::clone(flight), false),
+                None => {
+                    let flight = Arc::new(Flight::default());
+                    running.insert(key.to_string(), Arc::clone(&flight));
+                    (flight, true)
+                }
+            }
+        };
+
+        if !leader {
+            tracing::debug!(key, "joining a request in flight");
+            let mut reply = flight.reply.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            while reply.is_none() {
+                reply = flight.done.wait(reply).unwrap_or_else(|poisoned| poisoned.into_inner());
+            }
+            return reply.clone().expect("checked above");
+        }
+
+        // A panic must still release the requests waiting on this flight
+        let reply = panic::catch_unwind(AssertUnwindSafe(work))
+            .unwrap_or_else(|_| Reply::error(500, "internal", "the request could not be answered"));
+        *flight.reply.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(reply.clone());
+        self.running.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).remove(key);
+        flight.done.notify_all();
+        reply
+    }
+}
+
+// Status of the gateway for /v1/health
+#[derive(Serialize, Debug)]
+struct HealthView {
+    status: &'static str,
+    provider: &'static str,
+    version: &'static str,
+    uptime_seconds: u64,
+}
+
+// The reports a client can ask for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Report {
+    Current,
+    Forecast,
+}
+
+// Shared state of the worker threads
+struct Gateway {
+    app: Arc<WeatherApp>,
+    flights: Flights,
+    started: Instant,
+}
+
+// Serves the weather as JSON until the process is stopped; every client shares the app's
+// provider, API key, cache and rate limiter
+pub fn serve(app: Arc<WeatherApp>, args: &ServeArgs) -> ExitCode {
+    let server = match Server::http(&args.bind) {
+        Ok(server) => Arc::new(server),
+        Err(e) => {
+            eprintln!("Could not listen on {}: {}", args.bind, e);
+            return ExitCode::from(2);
+        }
+    };
+    match server.server_addr().to_ip() {
+        Some(address) => println!("Listening on http://{}", address),
+        None => println!("Listening on {}", args.bind),
+    }
+
+    let gateway = Arc::new(Gateway { app, flights: Flights::default(), started: Instant::now() });
+    let workers: Vec<_> = (0..args.workers.max(1))
+        .map(|_| {
+            let server = Arc::clone(&server);
+            let gateway = Arc::clone(&gateway);
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    gateway.handle(request);
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        let _ = worker.join();
+    }
+    ExitCode::SUCCESS
+}
+
+impl Gateway {
+    fn handle(&self, request: Request) {
+        let started = Instant::now();
+        let reply = self.route(request.method(), request.url());
+        tracing::info!(method = %request.method(), url = request.url(), status = reply.status, elapsed = ?started.elapsed(), "served request");
+
+        let mut response = Response::from_string(reply.body)
+            .with_status_code(reply.status)
+            .with_header(Header::from_bytes("Content-Type", "application/json").expect("valid header"));
+        if let Some(seconds) = reply.retry_after {
+            response.add_header(Header::from_bytes("Retry-After", seconds.to_string()).expect("valid header"));
+        }
+        if let Err(e) = request.respond(response) {
+            tracing::warn!(error = %e, "could not send the response");
+        }
+    }
+
+    fn route(&self, method: &Method, url: &str) -> Reply {
+        let url = match reqwest::Url::parse("http://gateway").and_then(|base| base.join(url)) {
+            Ok(url) => url,
+            Err(e) => return Reply::error(400, "bad_request", format!("invalid URL: {}", e)),
+        };
+        let report = match url.path() {
+            "/v1/health" => None,
+            "/v1/current" => Some(Report::Current),
+            "/v1/forecast" => Some(Report::Forecast),
+            path => return Reply::error(404, "unknown_endpoint", format!("no endpoint at {}; try /v1/current, /v1/forecast or /v1/health", path)),
+        };
+        if *method != Method::Get {
+            return Reply::error(405, "method_not_allowed", "only GET is supported");
+        }
+        let Some(report) = report else {
+            return Reply::json(200, &HealthView {
+                status: "ok",
+                provider: self.app.provider_name(),
+                version: env!("CARGO_PKG_VERSION"),
+                uptime_seconds: self.started.elapsed().as_secs(),
+            });
+        };
+
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        let (location, units) = match Self::parse_query(&query, self.app.units) {
+            Ok(parsed) => parsed,
+            Err(message) => return Reply::error(400, "bad_request", message),
+        };
+        // Units only change how the reply is written, but the reply is what flights share
+        let key = format!("{:?} {} {:?}", report, location.to_string().to_lowercase(), units);
+        self.flights.join(&key, || self.fetch(report, &location, &units))
+    }
+
+    // Reads the location from `location`, `lat` and `lon`, or `city` with optional `state` and
+    // `country`, and the units from `units`
+    fn parse_query(query: &HashMap<String, String>, default_units: Units) -> Result<(Location, Units), String> {
+        let location = match (query.get("location"), query.get("lat"), query.get("lon"), query.get("city")) {
+            (Some(location), ..) => location.parse::<Location>().map_err(|e| format!("invalid location: {}", e))?,
+            (None, Some(lat), Some(lon), _) => format!("{},{}", lat, lon).parse::<Location>().map_err(|e| format!("invalid coordinates: {}", e))?,
+            (None, _, _, Some(city)) => Location::Named(PlaceQuery {
+                name: city.clone(),
+                state: query.get("state").cloned(),
+                country: query.get("country").cloned(),
+            }),
+            _ => return Err("give a location as city=..&country=.., lat=..&lon=.. or location=..".to_string()),
+        };
+        let units = match query.get("units") {
+            Some(name) => {
+                let system = <UnitSystem as clap::ValueEnum>::from_str(name, true).map_err(|_| format!("unknown units \"{}\"; use metric, imperial or standard", name))?;
+                Units::from(system)
+            }
+            None => default_units,
+        };
+        Ok((location, units))
+    }
+
+    fn fetch(&self, report: Report, location: &Location, units: &Units) -> Reply {
+        let place = match UserInteraction::pick_place(&self.app, location, false) {
+            Ok(place) => place.expect("non-interactive lookups never cancel"),
+            Err(e) => return Reply::from_error(&e),
+        };
+        let reply = match report {
+            Report::Current => self.app.obtain_weather(&place).map(|info| Reply::json(200, &ObservationView::new(&info, units))),
+            Report::Forecast => self.app.obtain_forecast(&place).map(|forecast| Reply::json(200, &ForecastView::new(&forecast, units))),
+        };
+        reply.unwrap_or_else(|e| Reply::from_error(&e))
+    }
+}
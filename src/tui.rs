@@ -0,0 +1,527 @@
+use std::io;
+use std::process::ExitCode;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Local, Utc};
+use clap::ValueEnum;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::symbols::Marker;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Axis, Block, Chart, Dataset, GraphType, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use weather::error::WeatherError;
+use weather::model::{self, Condition, Forecast, Location, Observation};
+use weather::render;
+use weather::units::{UnitSystem, Units};
+use crate::cli::TuiArgs;
+use crate::app::{UserInteraction, WeatherApp};
+use crate::config::LoadedConfig;
+
+// How far ahead the forecast chart reaches
+const CHART_HOURS: i64 = 48;
+
+// What one refresh of a location brought back; a missing forecast does not hide the current conditions
+struct Report {
+    current: Observation,
+    forecast: Result<Forecast, String>,
+}
+
+// A location in the sidebar with its latest readings
+struct Entry {
+    name: Option<String>, // Name in the config file's [locations] table; none when only given on the command line
+    location: Location,   // What is looked up
+    current: Option<Observation>,
+    forecast: Option<Forecast>,
+    error: Option<String>,          // Why the latest refresh failed; the previous readings stay on screen
+    forecast_error: Option<String>, // Why there is no forecast, e.g. the provider offers none
+    updated: Option<DateTime<Local>>,
+    loading: bool,
+}
+
+impl Entry {
+    fn new(name: Option<String>, location: Location) -> Self {
+        Entry { name, location, current: None, forecast: None, error: None, forecast_error: None, updated: None, loading: false }
+    }
+
+    // How the sidebar shows it: "@name" when saved, else the location as given
+    fn label(&self) -> String {
+        match &self.name {
+            Some(name) => format!("@{}", name),
+            None => self.location.to_string(),
+        }
+    }
+}
+
+// State of the dashboard between two frames
+struct Dashboard {
+    entries: Vec<Entry>,    // The saved locations in the order of the config file's table, then the command line's
+    config: LoadedConfig,   // Where locations added or removed here are saved
+    list: ListState,
+    units: Units,
+    input: Option<String>,  // Location being typed after `a`
+    status: Option<String>, // Latest notice for the footer, cleared by the next key
+    jobs: Sender<(Option<String>, Location)>, // Locations for the background thread to fetch
+    provider: &'static str,
+}
+
+// Shows the locations saved in the config file and those given on the command line until the user
+// quits; fetching happens on a background thread so the screen stays responsive while a slow
+// provider answers
+pub fn run(mut app: WeatherApp, config: LoadedConfig, args: &TuiArgs) -> ExitCode {
+    // Warnings such as stale reports would scribble over the screen, so they go to the footer
+    let (notices, notices_received) = mpsc::channel();
+    app.notices = Some(notices);
+    let units = app.units;
+    let provider = app.provider_name();
+    let app = Arc::new(app);
+
+    let (jobs, pending) = mpsc::channel::<(Option<String>, Location)>();
+    let (finished, results) = mpsc::channel();
+    let fetcher = Arc::clone(&app);
+    thread::spawn(move || {
+        for (name, location) in pending {
+            let report = fetch(&fetcher, &location).map_err(|e| UserInteraction::describe_error(fetcher.provider_name(), &e));
+            if finished.send((name, location, report)).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut dashboard = Dashboard { entries: Vec::new(), config, list: ListState::default(), units, input: None, status: None, jobs, provider };
+    for (name, saved) in &dashboard.config.config.locations {
+        dashboard.entries.push(Entry::new(Some(name.clone()), saved.0.clone()));
+    }
+    dashboard.list.select((!dashboard.entries.is_empty()).then_some(0));
+    for location in &args.locations {
+        dashboard.show_location(location.clone());
+    }
+    dashboard.refresh_all();
+
+    let mut terminal = ratatui::init();
+    let result = dashboard.run(&mut terminal, &results, &notices_received, args.every);
+    ratatui::restore();
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("The dashboard stopped: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Looks up a location and fetches its conditions and forecast, through the cache and the rate limiter
+fn fetch(app: &WeatherApp, location: &Location) -> Result<Report, WeatherError> {
+    let place = UserInteraction::pick_place(app, location, false)?.expect("non-interactive lookups never cancel");
+    let current = app.obtain_weather(&place)?;
+    let forecast = app.obtain_forecast(&place).map_err(|e| UserInteraction::describe_error(app.provider_name(), &e));
+    Ok(Report { current, forecast })
+}
+
+// A name for a location saved without one, e.g. "amsterdam-nl" for "Amsterdam, NL"
+fn name_for(location: &Location) -> String {
+    let text = location.to_string().to_lowercase();
+    let words: Vec<&str> = text.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()).collect();
+    match words.join("-") {
+        name if name.is_empty() => "location".to_string(),
+        name => name,
+    }
+}
+
+impl Dashboard {
+    fn run(&mut self, terminal: &mut DefaultTerminal, results: &Receiver<(Option<String>, Location, Result<Report, String>)>, notices: &Receiver<String>, every: Duration) -> io::Result<()> {
+        let mut next_refresh = Instant::now() + every;
+        loop {
+            for (name, location, report) in results.try_iter() {
+                self.apply(name.as_deref(), &location, report);
+            }
+            if let Some(notice) = notices.try_iter().last() {
+                self.status = Some(notice);
+            }
+            if Instant::now() >= next_refresh {
+                self.refresh_all();
+                next_refresh = Instant::now() + every;
+            }
+
+            terminal.draw(|frame| self.draw(frame))?;
+            if !event::poll(Duration::from_millis(250))? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !self.handle_key(key.code, key.modifiers) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    // Acts on a key press; returns false to quit
+    fn handle_key(&mut self, code: KeyCode, modifiers: KeyModifiers) -> bool {
+        if let Some(input) = &mut self.input {
+            match code {
+                KeyCode::Enter => {
+                    let text = std::mem::take(input);
+                    self.input = None;
+                    self.add(&text);
+                }
+                KeyCode::Esc => self.input = None,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            }
+            return true;
+        }
+
+        self.status = None;
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return false,
+            KeyCode::Up | KeyCode::Char('k') => self.select_by(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.select_by(1),
+            KeyCode::Char('r') => {
+                if let Some(index) = self.list.selected() {
+                    self.refresh(index);
+                }
+            }
+            KeyCode::Char('R') => self.refresh_all(),
+            KeyCode::Char('a') => self.input = Some(String::new()),
+            KeyCode::Char('d') | KeyCode::Delete => self.remove_selected(),
+            KeyCode::Char('u') => {
+                let next = match self.units.system {
+                    UnitSystem::Metric => UnitSystem::Imperial,
+                    UnitSystem::Imperial => UnitSystem::Standard,
+                    UnitSystem::Standard => UnitSystem::Metric,
+                };
+                self.units = Units::from(next);
+                self.status = Some(format!("Units: {}", self.units));
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn select_by(&mut self, step: isize) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let current = self.list.selected().unwrap_or(0) as isize;
+        self.list.select(Some((current + step).clamp(0, self.entries.len() as isize - 1) as usize));
+    }
+
+    fn selected(&self) -> Option<&Entry> {
+        self.list.selected().and_then(|index| self.entries.get(index))
+    }
+
+    // Queues a location for the background thread, unless it is already waiting
+    fn refresh(&mut self, index: usize) {
+        let entry = &mut self.entries[index];
+        if entry.loading {
+            return;
+        }
+        entry.loading = true;
+        let _ = self.jobs.send((entry.name.clone(), entry.location.clone()));
+    }
+
+    fn refresh_all(&mut self) {
+        for index in 0..self.entries.len() {
+            self.refresh(index);
+        }
+    }
+
+    // Stores a finished refresh with its location, if that is still in the sidebar as it was fetched
+    fn apply(&mut self, name: Option<&str>, location: &Location, report: Result<Report, String>) {
+        let Some(entry) = self.entries.iter_mut().find(|entry| entry.name.as_deref() == name && entry.location == *location) else {
+            return;
+        };
+        entry.loading = false;
+        match report {
+            Ok(report) => {
+                entry.current = Some(report.current);
+                match report.forecast {
+                    Ok(forecast) => {
+                        entry.forecast = Some(forecast);
+                        entry.forecast_error = None;
+                    }
+                    Err(e) => entry.forecast_error = Some(e),
+                }
+                entry.error = None;
+                entry.updated = Some(Local::now());
+            }
+            Err(e) => entry.error = Some(e),
+        }
+    }
+
+    // Adds what was typed after `a`: a location, optionally preceded by the name to save it as,
+    // e.g. "home=Amsterdam, NL"
+    fn add(&mut self, text: &str) {
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        let (name, text) = match text.split_once('=') {
+            Some((name, rest)) => (Some(name.trim().trim_start_matches('@')), rest),
+            None => (None, text),
+        };
+        match text.parse::<Location>() {
+            Ok(location) => self.add_location(name, location),
+            Err(e) => self.status = Some(format!("That is not a location: {}", e)),
+        }
+    }
+
+    // Selects the saved location that @name or a location given on the command line refers to, if any
+    fn select_known(&mut self, location: &Location) -> bool {
+        let index = match location {
+            Location::Saved(saved) => self.entries.iter().position(|entry| entry.name.as_deref() == Some(saved)),
+            location => self.entries.iter().position(|entry| entry.location == *location),
+        };
+        if let Some(index) = index {
+            self.list.select(Some(index));
+        }
+        index.is_some()
+    }
+
+    // Shows a location given on the command line for this session, without saving it
+    fn show_location(&mut self, location: Location) {
+        if self.select_known(&location) {
+            return;
+        }
+        if let Location::Saved(saved) = &location {
+            self.status = Some(format!("There is no saved location named \"{}\".", saved));
+            return;
+        }
+        self.entries.push(Entry::new(None, location));
+        self.list.select(Some(self.entries.len() - 1));
+    }
+
+    // Saves a location in the config file and selects it in the sidebar. A location saved before
+    // is only selected, unless it is given a name of its own; @name selects that saved location.
+    fn add_location(&mut self, name: Option<&str>, location: Location) {
+        if let Location::Saved(saved) = &location {
+            if !self.select_known(&location) {
+                self.status = Some(format!("There is no saved location named \"{}\".", saved));
+            }
+            return;
+        }
+        if name.is_none() && self.entries.iter().any(|entry| entry.name.is_some() && entry.location == location) {
+            self.select_known(&location);
+            return;
+        }
+        let name = match name {
+            Some(name) if !model::is_saved_name(name) => {
+                self.status = Some(format!("\"{}\" cannot be used as a name; use letters, digits, '-' and '_'", name));
+                return;
+            }
+            Some(name) => name.to_string(),
+            None => self.unused_name(&location),
+        };
+
+        // A failure only costs the location on the next start
+        if let Err(e) = self.config.save_location(&name, &location, false) {
+            self.status = Some(format!("Could not save @{}: {}", name, e));
+        }
+        // Saving a location that was only shown replaces its entry
+        let index = self.entries.iter().position(|entry| match &entry.name {
+            Some(saved) => *saved == name,
+            None => entry.location == location,
+        });
+        let entry = Entry::new(Some(name), location);
+        let index = match index {
+            Some(index) => {
+                self.entries[index] = entry;
+                index
+            }
+            None => {
+                self.entries.push(entry);
+                self.entries.len() - 1
+            }
+        };
+        self.list.select(Some(index));
+        self.refresh(index);
+    }
+
+    // A name made up from the location that no saved location has yet
+    fn unused_name(&self, location: &Location) -> String {
+        let base = name_for(location);
+        let taken = |name: &String| self.config.config.locations.contains_key(name) || self.entries.iter().any(|entry| entry.name.as_ref() == Some(name));
+        (1..).map(|n| if n == 1 { base.clone() } else { format!("{}-{}", base, n) }).find(|name| !taken(name)).expect("names run out")
+    }
+
+    fn remove_selected(&mut self) {
+        let Some(index) = self.list.selected().filter(|index| *index < self.entries.len()) else {
+            return;
+        };
+        let removed = self.entries.remove(index);
+        self.status = Some(match &removed.name {
+            None => format!("Removed {}", removed.location),
+            Some(name) => match self.config.remove_location(name) {
+                Ok(_) => format!("Removed @{}", name),
+                Err(e) => format!("Could not remove @{} from the config file: {}", name, e),
+            },
+        });
+        self.list.select(match self.entries.len() {
+            0 => None,
+            len => Some(index.min(len - 1)),
+        });
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [body, footer] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [sidebar, main] = Layout::horizontal([Constraint::Length(32), Constraint::Min(0)]).areas(body);
+        let [current, chart] = Layout::vertical([Constraint::Percentage(55), Constraint::Percentage(45)]).areas(main);
+
+        self.draw_sidebar(frame, sidebar);
+        self.draw_current(frame, current);
+        self.draw_chart(frame, chart);
+        self.draw_footer(frame, footer);
+    }
+
+    fn draw_sidebar(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let mut spans = vec![Span::raw(entry.label())];
+                if let Some(current) = &entry.current {
+                    spans.push(Span::raw(format!(" {} {}", self.units.temperature(current.temperature), render::emoji_for_temperature(current.temperature))));
+                }
+                if entry.loading {
+                    spans.push(Span::raw(" …"));
+                } else if entry.error.is_some() {
+                    spans.push(Span::styled(" !", Style::default().fg(Color::LightRed)));
+                }
+                let style = entry.current.as_ref().map_or(Style::default(), |current| condition_style(current.condition));
+                ListItem::new(Line::from(spans)).style(style)
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::bordered().title(" Locations "))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, area, &mut self.list);
+    }
+
+    fn draw_current(&self, frame: &mut Frame, area: Rect) {
+        let Some(entry) = self.selected() else {
+            let hint = Paragraph::new("No saved locations yet. Press a to add one, e.g. \"Berlin, DE\", 52.52,13.40 or home=zip:10115,DE to choose its name.")
+                .wrap(Wrap { trim: true })
+                .block(Block::bordered().title(" Current conditions "));
+            frame.render_widget(hint, area);
+            return;
+        };
+
+        let mut lines = Vec::new();
+        if let Some(info) = &entry.current {
+            let style = condition_style(info.condition);
+            lines.push(Line::styled(
+                format!("Weather Update for {}: {} {}", info.location, info.description, render::emoji_for_temperature(info.temperature)),
+                style.add_modifier(Modifier::BOLD),
+            ));
+            let mut readings = vec![
+                ("Temperature", self.units.temperature(info.temperature)),
+                ("Humidity", render::format_reading(info.humidity.map(|humidity| format!("{:.1}%", humidity)))),
+                ("Pressure", render::format_reading(info.pressure.map(|pressure| self.units.pressure(pressure)))),
+                ("Wind Speed", render::format_reading(info.wind_speed.map(|speed| self.units.speed(speed)))),
+            ];
+            readings.extend(render::detailed_readings(&self.units, info));
+            for (label, value) in readings {
+                lines.push(Line::styled(format!("> {}: {}", label, value), style));
+            }
+        } else if entry.loading {
+            lines.push(Line::raw("Loading…"));
+        }
+        if let Some(error) = &entry.error {
+            lines.push(Line::raw(""));
+            lines.push(Line::styled(error.clone(), Style::default().fg(Color::LightRed)));
+        }
+
+        let mut block = Block::bordered().title(match &entry.name {
+            Some(name) => format!(" @{}: {} ", name, entry.location),
+            None => format!(" {} ", entry.location),
+        });
+        if let Some(updated) = entry.updated {
+            let refreshing = if entry.loading { ", refreshing" } else { "" };
+            block = block.title(Line::from(format!(" updated {}{} ", updated.format("%H:%M:%S"), refreshing)).right_aligned());
+        }
+        frame.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }).block(block), area);
+    }
+
+    // Temperature over the next two days, one point per forecast step
+    fn draw_chart(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered().title(format!(" Next {} hours ", CHART_HOURS));
+        let entry = self.selected();
+        let Some(forecast) = entry.and_then(|entry| entry.forecast.as_ref()) else {
+            let message = entry.and_then(|entry| entry.forecast_error.clone()).unwrap_or_default();
+            frame.render_widget(Paragraph::new(message).wrap(Wrap { trim: true }).block(block), area);
+            return;
+        };
+
+        let now = Utc::now();
+        let steps: Vec<_> = forecast
+            .entries
+            .iter()
+            .filter(|step| step.time >= now - chrono::Duration::hours(3) && step.time <= now + chrono::Duration::hours(CHART_HOURS))
+            .collect();
+        let (Some(first), Some(last)) = (steps.first(), steps.last()) else {
+            frame.render_widget(Paragraph::new("The forecast has no steps ahead.").block(block), area);
+            return;
+        };
+
+        let hours = |time: DateTime<Utc>| (time - first.time).num_minutes() as f64 / 60.0;
+        let points: Vec<(f64, f64)> = steps.iter().map(|step| (hours(step.time), self.units.temperature.convert(step.temperature))).collect();
+        let low = points.iter().map(|(_, temperature)| *temperature).fold(f64::INFINITY, f64::min).floor() - 1.0;
+        let high = points.iter().map(|(_, temperature)| *temperature).fold(f64::NEG_INFINITY, f64::max).ceil() + 1.0;
+        let symbol = self.units.temperature.symbol();
+        let middle = steps[steps.len() / 2];
+        let time_label = |time| forecast.local_time(time).format("%a %H:%M").to_string();
+
+        let dataset = Dataset::default()
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(condition_style(first.condition))
+            .data(&points);
+        let chart = Chart::new(vec![dataset])
+            .block(block)
+            .x_axis(Axis::default().bounds([0.0, hours(last.time).max(1.0)]).labels([time_label(first), time_label(middle), time_label(last)]))
+            .y_axis(Axis::default().bounds([low, high]).labels([
+                format!("{:.0}{}", low, symbol),
+                format!("{:.0}{}", (low + high) / 2.0, symbol),
+                format!("{:.0}{}", high, symbol),
+            ]));
+        frame.render_widget(chart, area);
+    }
+
+    fn draw_footer(&self, frame: &mut Frame, area: Rect) {
+        let line = match (&self.input, &self.status) {
+            (Some(input), _) => Line::from(vec![Span::styled("Add location ([name=]location): ", Style::default().fg(Color::LightGreen)), Span::raw(format!("{}█", input))]),
+            (None, Some(status)) => Line::styled(status.clone(), Style::default().fg(Color::Yellow)),
+            (None, None) => {
+                let system = self.units.system.to_possible_value().expect("no skipped variants");
+                Line::raw(format!(
+                    "↑↓ select  r refresh  R refresh all  a add  d remove  u units ({})  q quit    {}",
+                    system.get_name(),
+                    self.provider
+                ))
+            }
+        };
+        frame.render_widget(Paragraph::new(line), area);
+    }
+}
+
+// The dashboard's version of the text mode's colors for each condition, honouring NO_COLOR
+fn condition_style(condition: Condition) -> Style {
+    if !colored::control::SHOULD_COLORIZE.should_colorize() {
+        return Style::default();
+    }
+    match condition {
+        Condition::Clear => Style::default().fg(Color::LightYellow),
+        Condition::Cloudy => Style::default().fg(Color::LightBlue),
+        Condition::Overcast | Condition::Fog => Style::default().add_modifier(Modifier::DIM),
+        Condition::Rain | Condition::Thunderstorm | Condition::Snow => Style::default().fg(Color::LightCyan),
+        Condition::Unknown => Style::default(),
+    }
+}
@@ -0,0 +1,282 @@
+//! Unit systems and the conversions applied when readings are shown.
+
+use std::fmt;
+use clap::ValueEnum;
+use serde::Deserialize;
+
+// Providers always report metric readings (°C, m/s, hPa, mm, m); these types convert them for display
+// only, so the same observation can be shown in any unit without fetching it again.
+
+/// Named sets of units, as OpenWeatherMap defines them
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnitSystem {
+    #[default]
+    Metric,   // °C, m/s, hPa, mm
+    Imperial, // °F, mph, inHg, in
+    Standard, // K, m/s, hPa, mm
+}
+
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+}
+
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SpeedUnit {
+    #[value(name = "m/s", alias = "ms")]
+    #[serde(rename = "m/s", alias = "ms")]
+    MetresPerSecond,
+    #[value(name = "km/h", alias = "kmh")]
+    #[serde(rename = "km/h", alias = "kmh")]
+    KilometresPerHour,
+    Mph,
+    Knots,
+}
+
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PressureUnit {
+    #[value(name = "hpa")]
+    #[serde(rename = "hpa")]
+    Hectopascal,
+    #[value(name = "kpa")]
+    #[serde(rename = "kpa")]
+    Kilopascal,
+    #[value(name = "inhg")]
+    #[serde(rename = "inhg")]
+    InchesOfMercury,
+    #[value(name = "mmhg")]
+    #[serde(rename = "mmhg")]
+    MillimetresOfMercury,
+}
+
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrecipitationUnit {
+    #[value(name = "mm")]
+    #[serde(rename = "mm")]
+    Millimetres,
+    #[value(name = "in")]
+    #[serde(rename = "in")]
+    Inches,
+}
+
+/// Visibility follows the unit system; there is no override for it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DistanceUnit {
+    Kilometres,
+    Miles,
+}
+
+/// The unit chosen for each kind of reading
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Units {
+    pub system: UnitSystem,
+    pub temperature: TemperatureUnit,
+    pub speed: SpeedUnit,
+    pub pressure: PressureUnit,
+    pub precipitation: PrecipitationUnit,
+    pub distance: DistanceUnit,
+}
+
+impl Default for Units {
+    fn default() -> Self {
+        Units::from(UnitSystem::default())
+    }
+}
+
+impl From<UnitSystem> for Units {
+    fn from(system: UnitSystem) -> Self {
+        let (temperature, speed, pressure, precipitation, distance) = match system {
+            UnitSystem::Metric => (
+                TemperatureUnit::Celsius,
+                SpeedUnit::MetresPerSecond,
+                PressureUnit::Hectopascal,
+                PrecipitationUnit::Millimetres,
+                DistanceUnit::Kilometres,
+            ),
+            UnitSystem::Imperial => (
+                TemperatureUnit::Fahrenheit,
+                SpeedUnit::Mph,
+                PressureUnit::InchesOfMercury,
+                PrecipitationUnit::Inches,
+                DistanceUnit::Miles,
+            ),
+            UnitSystem::Standard => (
+                TemperatureUnit::Kelvin,
+                SpeedUnit::MetresPerSecond,
+                PressureUnit::Hectopascal,
+                PrecipitationUnit::Millimetres,
+                DistanceUnit::Kilometres,
+            ),
+        };
+        Units { system, temperature, speed, pressure, precipitation, distance }
+    }
+}
+
+impl Units {
+    /// Starts from a unit system and replaces the quantities that were overridden
+    pub fn new(
+        system: UnitSystem,
+        temperature: Option<TemperatureUnit>,
+        speed: Option<SpeedUnit>,
+        pressure: Option<PressureUnit>,
+        precipitation: Option<PrecipitationUnit>,
+    ) -> Self {
+        let base = Units::from(system);
+        Units {
+            system,
+            temperature: temperature.unwrap_or(base.temperature),
+            speed: speed.unwrap_or(base.speed),
+            pressure: pressure.unwrap_or(base.pressure),
+            precipitation: precipitation.unwrap_or(base.precipitation),
+            distance: base.distance,
+        }
+    }
+
+    /// Formats a temperature given in °C, e.g. "52.2°F"
+    pub fn temperature(&self, celsius: f64) -> String {
+        format!("{:.1}{}", self.temperature.convert(celsius), self.temperature.symbol())
+    }
+
+    /// Formats a speed given in m/s, e.g. "11.4 mph"
+    pub fn speed(&self, metres_per_second: f64) -> String {
+        format!("{:.1} {}", self.speed.convert(metres_per_second), self.speed.symbol())
+    }
+
+    /// Formats a pressure given in hPa; inches of mercury need two decimals to be useful
+    pub fn pressure(&self, hectopascals: f64) -> String {
+        let value = self.pressure.convert(hectopascals);
+        match self.pressure {
+            PressureUnit::InchesOfMercury => format!("{:.2} {}", value, self.pressure.symbol()),
+            _ => format!("{:.1} {}", value, self.pressure.symbol()),
+        }
+    }
+
+    /// Formats a precipitation amount given in mm; inches need two decimals to be useful
+    pub fn precipitation(&self, millimetres: f64) -> String {
+        let value = self.precipitation.convert(millimetres);
+        match self.precipitation {
+            PrecipitationUnit::Inches => format!("{:.2} {}", value, self.precipitation.symbol()),
+            PrecipitationUnit::Millimetres => format!("{:.1} {}", value, self.precipitation.symbol()),
+        }
+    }
+
+    /// Formats a distance given in metres
+    pub fn distance(&self, metres: f64) -> String {
+        format!("{:.1} {}", self.distance.convert(metres), self.distance.symbol())
+    }
+}
+
+impl fmt::Display for Units {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let system = self.system.to_possible_value().expect("no skipped variants");
+        write!(
+            f,
+            "{} ({}, {}, {}, {})",
+            system.get_name(),
+            self.temperature.symbol(),
+            self.speed.symbol(),
+            self.pressure.symbol(),
+            self.precipitation.symbol()
+        )
+    }
+}
+
+impl TemperatureUnit {
+    /// Converts from °C
+    pub fn convert(self, celsius: f64) -> f64 {
+        match self {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+            TemperatureUnit::Kelvin => celsius + 273.15,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+            TemperatureUnit::Kelvin => " K",
+        }
+    }
+}
+
+impl SpeedUnit {
+    /// Converts from m/s
+    pub fn convert(self, speed: f64) -> f64 {
+        match self {
+            SpeedUnit::MetresPerSecond => speed,
+            SpeedUnit::KilometresPerHour => speed * 3.6,
+            SpeedUnit::Mph => speed / 0.44704,
+            SpeedUnit::Knots => speed * 3600.0 / 1852.0,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            SpeedUnit::MetresPerSecond => "m/s",
+            SpeedUnit::KilometresPerHour => "km/h",
+            SpeedUnit::Mph => "mph",
+            SpeedUnit::Knots => "kn",
+        }
+    }
+}
+
+impl PressureUnit {
+    /// Converts from hPa
+    pub fn convert(self, pressure: f64) -> f64 {
+        match self {
+            PressureUnit::Hectopascal => pressure,
+            PressureUnit::Kilopascal => pressure / 10.0,
+            PressureUnit::InchesOfMercury => pressure / 33.8639,
+            PressureUnit::MillimetresOfMercury => pressure / 1.333224,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            PressureUnit::Hectopascal => "hPa",
+            PressureUnit::Kilopascal => "kPa",
+            PressureUnit::InchesOfMercury => "inHg",
+            PressureUnit::MillimetresOfMercury => "mmHg",
+        }
+    }
+}
+
+impl PrecipitationUnit {
+    /// Converts from mm
+    pub fn convert(self, amount: f64) -> f64 {
+        match self {
+            PrecipitationUnit::Millimetres => amount,
+            PrecipitationUnit::Inches => amount / 25.4,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            PrecipitationUnit::Millimetres => "mm",
+            PrecipitationUnit::Inches => "in",
+        }
+    }
+}
+
+impl DistanceUnit {
+    /// Converts from metres
+    pub fn convert(self, distance: f64) -> f64 {
+        match self {
+            DistanceUnit::Kilometres => distance / 1000.0,
+            DistanceUnit::Miles => distance / 1609.344,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            DistanceUnit::Kilometres => "km",
+            DistanceUnit::Miles => "mi",
+        }
+    }
+}
@@ -0,0 +1,78 @@
+// Builds without some providers or formats leave the helpers of their tests unused
+#![cfg_attr(not(all(feature = "openweathermap", feature = "open-meteo", feature = "json")), allow(dead_code, unused_imports))]
+
+mod common;
+
+use common::{geocoded, http_response, run_weather, temp_home, MockServer};
+use serde_json::Value;
+
+// Mostly fine particles: a US AQI of 75, Moderate, and OpenWeatherMap's index 2
+const CURRENT: &str = r#"{"coord":{"lon":13.4,"lat":52.52},"list":[{"main":{"aqi":2},"components":{"co":230.31,"no":0.5,"no2":15.0,"o3":68.66,"so2":2.1,"pm2_5":22.1,"pm10":30.0,"nh3":1.2},"dt":1792044000}]}"#;
+
+// Two hours on 2026-10-15 UTC, the second Unhealthy for Sensitive Groups, and a clean hour on the 16th
+const FORECAST: &str = r#"{"coord":{"lon":13.4,"lat":52.52},"list":[
+  {"main":{"aqi":2},"components":{"co":230.31,"no":0.5,"no2":15.0,"o3":68.66,"so2":2.1,"pm2_5":22.1,"pm10":30.0,"nh3":1.2},"dt":1792044000},
+  {"main":{"aqi":3},"components":{"co":250.0,"no":0.8,"no2":20.0,"o3":60.0,"so2":2.5,"pm2_5":40.0,"pm10":48.0,"nh3":1.5},"dt":1792047600},
+  {"main":{"aqi":1},"components":{"co":200.0,"no":0.1,"no2":5.0,"o3":20.0,"so2":1.0,"pm2_5":5.0,"pm10":8.0,"nh3":0.4},"dt":1792108800}
+]}"#;
+
+fn air_server() -> MockServer {
+    MockServer::start(vec![geocoded("Berlin", "DE", 52.52, 13.4), http_response(200, &[], CURRENT), http_response(200, &[], FORECAST)])
+}
+
+#[test]
+#[cfg(feature = "openweathermap")]
+fn air_shows_pollutants_the_index_and_the_us_aqi() {
+    let server = air_server();
+    let home = temp_home("air", &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url));
+
+    let output = run_weather(&home, &["air", "--city", "Berlin", "--country", "DE", "--hourly"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let requests = server.requests();
+    assert!(requests[1].starts_with("GET /data/2.5/air_pollution?lat=52.52&lon=13.4"), "{}", requests[1]);
+    assert!(requests[2].starts_with("GET /data/2.5/air_pollution/forecast?lat=52.52&lon=13.4"), "{}", requests[2]);
+    assert!(stdout.contains("Air Quality for Berlin: Moderate"), "{}", stdout);
+    assert!(stdout.contains("> US AQI: 75, mostly PM2.5"), "{}", stdout);
+    assert!(stdout.contains("> Index: 2 of 5 (Fair)"), "{}", stdout);
+    assert!(stdout.contains("> PM2.5: 22.1 µg/m³") && stdout.contains("> O3: 68.7 µg/m³"), "{}", stdout);
+    let days: Vec<&str> = stdout.lines().filter(|line| line.starts_with("Thu ") || line.starts_with("Fri ")).collect();
+    assert_eq!(days.len(), 2, "{}", stdout);
+    assert!(days[0].contains("US AQI up to 112, Unhealthy for Sensitive Groups (mostly PM2.5), index up to 3 of 5 (Moderate)"), "{}", days[0]);
+    assert!(days[1].contains("Good"), "{}", days[1]);
+    assert!(stdout.contains("> 07:00 US AQI 112"), "{}", stdout);
+}
+
+#[test]
+#[cfg(all(feature = "openweathermap", feature = "json"))]
+fn air_as_json_carries_every_reading() {
+    let server = air_server();
+    let home = temp_home("air-json", &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url));
+
+    let output = run_weather(&home, &["--output", "json", "air", "--city", "Berlin"], &[]);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let air: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(air["location"], "Berlin");
+    assert_eq!(air["concentration_unit"], "µg/m³");
+    assert_eq!(air["current"]["us_aqi"], 75);
+    assert_eq!(air["current"]["category"], "moderate");
+    assert_eq!(air["current"]["dominant_pollutant"], "PM2.5");
+    assert_eq!(air["current"]["index_name"], "Fair");
+    assert_eq!(air["current"]["pm2_5"], 22.1);
+    assert_eq!(air["days"].as_array().unwrap().len(), 2);
+    assert_eq!(air["days"][0]["category"], "unhealthy_for_sensitive_groups");
+    assert_eq!(air["forecast"].as_array().unwrap().len(), 3);
+}
+
+#[test]
+#[cfg(feature = "open-meteo")]
+fn air_fails_on_providers_without_pollution_data() {
+    let home = temp_home("air-unsupported", "");
+
+    let output = run_weather(&home, &["--provider", "open-meteo", "air", "52.52,13.40"], &[]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("does not offer a pollution report"), "{}", String::from_utf8_lossy(&output.stderr));
+}
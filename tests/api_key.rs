@@ -0,0 +1,62 @@
+// Every test resolves an OpenWeatherMap API key
+#![cfg(feature = "openweathermap")]
+
+mod common;
+
+use std::fs;
+use common::{run_weather, temp_home};
+
+// The line of `weather config` telling where the key comes from
+fn key_source(home: &std::path::PathBuf, args: &[&str], env: &[(&str, &str)]) -> String {
+    let mut command = vec!["config"];
+    command.extend(args);
+    let output = run_weather(home, &command, env);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.lines().find(|line| line.starts_with("api key:")).unwrap_or_default().to_string()
+}
+
+#[test]
+fn sources_are_tried_in_order() {
+    let home = temp_home("key-order", "");
+    let key_file = home.join("key");
+    fs::write(&key_file, "from-file\n").unwrap();
+    let config = format!("api_key = \"from-config\"\nkey_file = \"{}\"\nkey_command = \"echo from-command\"\n", key_file.display());
+    fs::write(home.join("config").join("weather").join("config.toml"), config).unwrap();
+
+    assert_eq!(key_source(&home, &["--api-key", "from-flag"], &[("OPENWEATHER_API_KEY", "from-env")]), "api key: from --api-key flag");
+    assert_eq!(key_source(&home, &[], &[("OPENWEATHER_API_KEY", "from-env")]), "api key: from OPENWEATHER_API_KEY environment variable");
+    assert_eq!(key_source(&home, &[], &[]), format!("api key: from key_file {}", key_file.display()));
+
+    fs::remove_file(&key_file).unwrap();
+    let config = "api_key = \"from-config\"\nkey_command = \"echo from-command\"\n";
+    fs::write(home.join("config").join("weather").join("config.toml"), config).unwrap();
+    assert_eq!(key_source(&home, &[], &[]), "api key: from key_command `echo from-command`");
+
+    fs::write(home.join("config").join("weather").join("config.toml"), "api_key = \"from-config\"\n").unwrap();
+    let config_file = home.join("config").join("weather").join("config.toml");
+    assert_eq!(key_source(&home, &[], &[]), format!("api key: from api_key in {}", config_file.display()));
+}
+
+#[test]
+fn a_failing_key_command_is_reported() {
+    let home = temp_home("key-command-fails", "key_command = \"exit 3\"\n");
+
+    let output = run_weather(&home, &["now", "--city", "Berlin", "--country", "DE"], &[]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("key_command `exit 3` failed"), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn a_missing_key_lists_where_it_was_looked_for() {
+    let home = temp_home("key-missing", "");
+
+    let output = run_weather(&home, &["now", "--city", "Berlin", "--country", "DE"], &[]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+    for place in ["--api-key flag (not given)", "OPENWEATHER_API_KEY environment variable (not set)", "config.toml"] {
+        assert!(stderr.contains(place), "no {:?} in:\n{}", place, stderr);
+    }
+}
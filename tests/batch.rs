@@ -0,0 +1,227 @@
+// Every test talks to the mock server as OpenWeatherMap
+#![cfg(feature = "openweathermap")]
+
+mod common;
+
+use common::{geocoded, http_response, run_weather, temp_home, MockServer};
+
+// Current-weather reply for a city, with the readings the comparison shows
+fn current(name: &str, country: &str, temp: f64, humidity: u32, main: &str) -> String {
+    let body = format!(
+        r#"{{"weather":[{{"id":800,"main":"{main}","description":"{description}"}}],"main":{{"temp":{temp},"feels_like":{temp},"pressure":1012,"humidity":{humidity}}},"wind":{{"speed":3.5}},"clouds":{{"all":20}},"dt":1792058400,"sys":{{"country":"{country}"}},"timezone":0,"name":"{name}","cod":200}}"#,
+        main = main,
+        description = main.to_lowercase(),
+        temp = temp,
+        humidity = humidity,
+        country = country,
+        name = name,
+    );
+    http_response(200, &[], &body)
+}
+
+// Direct-geocoding reply finding nothing
+fn not_found() -> String {
+    http_response(200, &[], "[]")
+}
+
+#[test]
+fn several_locations_share_one_table_in_the_given_order() {
+    let server = MockServer::start(vec![
+        geocoded("Berlin", "DE", 52.52, 13.40),
+        current("Berlin", "DE", 11.2, 87, "Rain"),
+        geocoded("Madrid", "ES", 40.42, -3.70),
+        current("Madrid", "ES", 24.5, 30, "Clear"),
+        geocoded("Oslo", "NO", 59.91, 10.75),
+        current("Oslo", "NO", 4.0, 70, "Clouds"),
+    ]);
+    let home = temp_home("batch-table", &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url));
+
+    let output = run_weather(&home, &["now", "Berlin", "Madrid", "Oslo", "--jobs", "1"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 4, "{}", stdout);
+    assert!(lines[0].starts_with("Location"), "{}", stdout);
+    assert!(lines[0].contains("Temp") && lines[0].contains("Humidity") && lines[0].ends_with("Conditions"), "{}", stdout);
+    assert!(lines[1].starts_with("Berlin, DE") && lines[1].contains("11.2°C") && lines[1].ends_with("rain"), "{}", stdout);
+    assert!(lines[2].starts_with("Madrid, ES") && lines[2].contains("24.5°C"), "{}", stdout);
+    assert!(lines[3].starts_with("Oslo, NO") && lines[3].contains("4.0°C"), "{}", stdout);
+    // Readings are right-aligned under their heading
+    let temp_end = lines[0].find("Temp").unwrap() + "Temp".len();
+    for line in &lines[1..] {
+        assert_eq!(line.chars().nth(temp_end - 1), Some('C'), "{}", stdout);
+    }
+}
+
+#[test]
+fn a_failed_location_keeps_its_row_and_fails_the_run() {
+    let server = MockServer::start(vec![
+        geocoded("Berlin", "DE", 52.52, 13.40),
+        current("Berlin", "DE", 11.2, 87, "Rain"),
+        not_found(),
+        geocoded("Oslo", "NO", 59.91, 10.75),
+        current("Oslo", "NO", 4.0, 70, "Clouds"),
+    ]);
+    let home = temp_home("batch-failure", &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url));
+
+    let output = run_weather(&home, &["now", "Berlin", "Atlantis", "Oslo", "--jobs", "1"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(output.status.code(), Some(1), "{}{}", stdout, stderr);
+    assert!(stdout.contains("Berlin, DE") && stdout.contains("Oslo, NO"), "{}", stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    let position = |name: &str| lines.iter().position(|line| line.starts_with(name)).unwrap();
+    assert!(lines[position("Atlantis")].contains("failed:"), "{}", stdout);
+    assert!(position("Berlin") < position("Atlantis") && position("Atlantis") < position("Oslo"), "{}", stdout);
+    assert!(stderr.contains("1 of 3 locations could not be fetched."), "{}", stderr);
+}
+
+#[test]
+fn sorting_by_a_field_descending() {
+    let server = MockServer::start(vec![
+        geocoded("Berlin", "DE", 52.52, 13.40),
+        current("Berlin", "DE", 11.2, 87, "Rain"),
+        geocoded("Madrid", "ES", 40.42, -3.70),
+        current("Madrid", "ES", 24.5, 30, "Clear"),
+        geocoded("Oslo", "NO", 59.91, 10.75),
+        current("Oslo", "NO", 4.0, 70, "Clouds"),
+    ]);
+    let home = temp_home("batch-sort", &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url));
+
+    let output = run_weather(&home, &["now", "Berlin", "Madrid", "Oslo", "--jobs", "1", "--sort", "-temp"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let order: Vec<&str> = stdout.lines().skip(1).map(|line| line.split(',').next().unwrap()).collect();
+    assert_eq!(order, ["Madrid", "Berlin", "Oslo"], "{}", stdout);
+}
+
+#[test]
+fn negative_coordinates_mix_with_options() {
+    let server = MockServer::start(vec![current("Sydney", "AU", 18.0, 60, "Clear"), current("Cape Town", "ZA", 16.0, 75, "Clouds")]);
+    let home = temp_home("batch-negative", &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url));
+
+    let output = run_weather(&home, &["now", "-33.87,151.21", "--jobs", "1", "-33.92,18.42", "--sort", "temp"], &[]);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let requests = server.requests();
+    assert!(requests[0].contains("lat=-33.87") && requests[1].contains("lat=-33.92"), "{:?}", requests);
+}
+
+#[test]
+fn unknown_sort_field_is_rejected() {
+    let home = temp_home("batch-bad-sort", "api_key = \"test\"\n");
+
+    let output = run_weather(&home, &["now", "52.52,13.40", "40.42,-3.70", "--sort", "altitude"], &[]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("altitude"));
+}
+
+#[test]
+#[cfg(feature = "csv")]
+fn locations_are_read_from_a_csv_file() {
+    let server = MockServer::start(vec![
+        geocoded("Berlin", "DE", 52.52, 13.40),
+        current("Berlin", "DE", 11.2, 87, "Rain"),
+        geocoded("Madrid", "ES", 40.42, -3.70),
+        current("Madrid", "ES", 24.5, 30, "Clear"),
+    ]);
+    let home = temp_home("batch-file", &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url));
+    let file = home.join("cities.csv");
+    std::fs::write(&file, "Label, City, Country\n# capitals\nhome, Berlin, DE\n\naway, Madrid, ES\n").unwrap();
+
+    let output = run_weather(&home, &["now", "--from-file", file.to_str().unwrap(), "--jobs", "1"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.lines().nth(1).unwrap().starts_with("Berlin, DE"), "{}", stdout);
+    assert!(stdout.lines().nth(2).unwrap().starts_with("Madrid, ES"), "{}", stdout);
+    let requests = server.requests();
+    assert!(requests[0].contains("q=Berlin%2CDE") && requests[2].contains("q=Madrid%2CES"), "{:?}", requests);
+}
+
+#[test]
+#[cfg(all(feature = "csv", feature = "json"))]
+fn a_bad_csv_row_is_a_setup_error() {
+    let home = temp_home("batch-bad-file", "api_key = \"test\"\n");
+    let file = home.join("cities.csv");
+    std::fs::write(&file, "city,lat\nBerlin,north\n").unwrap();
+
+    let output = run_weather(&home, &["now", "--from-file", file.to_str().unwrap(), "--output", "json"], &[]);
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr).expect("JSON error");
+
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(error["error"]["code"], "locations_file");
+    assert!(error["error"]["message"].as_str().unwrap().contains("line 2"), "{}", error);
+}
+
+#[test]
+#[cfg(feature = "json")]
+fn json_keeps_a_row_for_each_failure_and_reports_it_on_stderr() {
+    let server = MockServer::start(vec![
+        geocoded("Berlin", "DE", 52.52, 13.40),
+        current("Berlin", "DE", 11.2, 87, "Rain"),
+        not_found(),
+        geocoded("Oslo", "NO", 59.91, 10.75),
+        current("Oslo", "NO", 4.0, 70, "Clouds"),
+    ]);
+    let home = temp_home("batch-json", &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url));
+
+    let output = run_weather(&home, &["now", "Berlin", "Atlantis", "Oslo", "--jobs", "1", "--output", "json"], &[]);
+    let readings: serde_json::Value = serde_json::from_slice(&output.stdout).expect("JSON array");
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr).expect("JSON error");
+
+    assert_eq!(output.status.code(), Some(1));
+    let queries: Vec<&str> = readings.as_array().unwrap().iter().map(|reading| reading["query"].as_str().unwrap()).collect();
+    assert_eq!(queries, ["Berlin", "Atlantis", "Oslo"]);
+    assert_eq!(readings[0]["location"]["name"], "Berlin");
+    assert_eq!(readings[1]["error_code"], "not_found");
+    assert!(readings[1]["temperature"].is_null() && readings[1]["location"]["name"].is_null(), "{}", readings[1]);
+    assert!(readings[2]["error_code"].is_null());
+    assert_eq!(error["error"]["location"], "Atlantis");
+}
+
+#[test]
+#[cfg(feature = "csv")]
+fn csv_gives_a_failed_location_a_row_with_empty_readings() {
+    let server = MockServer::start(vec![
+        not_found(),
+        geocoded("Oslo", "NO", 59.91, 10.75),
+        current("Oslo", "NO", 4.0, 70, "Clouds"),
+    ]);
+    let home = temp_home("batch-csv-failure", &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url));
+
+    let output = run_weather(&home, &["now", "Atlantis", "Oslo", "--jobs", "1", "--output", "csv"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(output.status.code(), Some(1));
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3, "{}", stdout);
+    let columns: Vec<&str> = lines[0].split(',').collect();
+    let cells: Vec<&str> = lines[1].split(',').collect();
+    let cell = |name: &str| cells[columns.iter().position(|column| *column == name).unwrap()];
+    assert_eq!(cell("query"), "Atlantis");
+    assert_eq!(cell("error_code"), "not_found");
+    assert_eq!(cell("temperature"), "");
+    assert!(lines[2].starts_with("Oslo,,,"), "{}", stdout);
+}
+
+#[test]
+#[cfg(feature = "ndjson")]
+fn concurrent_fetches_return_every_location() {
+    let responses = (0..6).map(|_| current("Berlin", "DE", 11.2, 87, "Rain")).collect();
+    let server = MockServer::start(responses);
+    let home = temp_home("batch-concurrent", &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url));
+
+    let locations = ["1,1", "2,2", "3,3", "4,4", "5,5", "6,6"];
+    let mut args = vec!["now", "--jobs", "3", "--output", "ndjson"];
+    args.extend(locations);
+    let output = run_weather(&home, &args, &[]);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 6);
+    assert_eq!(server.requests().len(), 6);
+}
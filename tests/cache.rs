@@ -0,0 +1,117 @@
+// Builds without some providers or formats leave the helpers of their tests unused
+#![cfg_attr(not(all(feature = "openweathermap", feature = "json")), allow(dead_code, unused_imports))]
+
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+use common::{closed_port_url, geocoded, http_response, run_weather, temp_home, MockServer};
+use weather::cache::Cache;
+
+const CURRENT: &str = r#"{
+  "coord": {"lon": 13.4105, "lat": 52.5244},
+  "weather": [{"id": 800, "description": "clear sky", "icon": "01d"}],
+  "main": {"temp": 20.0, "pressure": 1013, "humidity": 50},
+  "wind": {"speed": 10.0},
+  "dt": 1792058400,
+  "sys": {"country": "DE"},
+  "timezone": 7200,
+  "name": "Berlin"
+}"#;
+
+fn write_config(home: &std::path::Path, config: &str) {
+    std::fs::write(home.join("config").join("weather").join("config.toml"), config).unwrap();
+}
+
+#[test]
+#[cfg(feature = "openweathermap")]
+fn repeated_lookups_are_served_from_the_cache() {
+    // Only one lookup's worth of responses; a second fetch would fail
+    let server = MockServer::start(vec![geocoded("Berlin", "DE", 52.5244, 13.4105), http_response(200, &[], CURRENT)]);
+    let home = temp_home("cache-hit", &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url));
+
+    let first = run_weather(&home, &["now", "Berlin, DE"], &[]);
+    let second = run_weather(&home, &["now", "Berlin, DE", "--units", "imperial"], &[]);
+
+    assert!(first.status.success(), "{}", String::from_utf8_lossy(&first.stderr));
+    assert!(second.status.success(), "{}", String::from_utf8_lossy(&second.stderr));
+    assert_eq!(server.requests().len(), 2);
+    // Cached readings are converted to any unit without fetching again
+    assert!(String::from_utf8_lossy(&second.stdout).contains("> Temperature: 68.0°F"));
+    assert!(home.join("cache").join("weather").join("openweathermap").join("current-52.5244,13.4105-metric.json").exists());
+}
+
+#[test]
+#[cfg(all(feature = "openweathermap", feature = "json"))]
+fn offline_serves_the_cache_with_its_age() {
+    let server = MockServer::start(vec![geocoded("Berlin", "DE", 52.5244, 13.4105), http_response(200, &[], CURRENT)]);
+    let home = temp_home("cache-offline", &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url));
+    assert!(run_weather(&home, &["now", "Berlin, DE"], &[]).status.success());
+
+    write_config(&home, &format!("api_key = \"test\"\nbase_url = \"{}\"\n", closed_port_url()));
+    let output = run_weather(&home, &["now", "Berlin, DE", "--offline", "--output", "json"], &[]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(output.status.success(), "{}", stderr);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\"temperature\": 20.0"));
+    assert!(stderr.contains(r#"{"warning":{"code":"offline","message":"Offline: showing the report cached "#), "{}", stderr);
+    assert!(stderr.contains(r#""age_seconds":"#), "{}", stderr);
+}
+
+#[test]
+#[cfg(feature = "openweathermap")]
+fn offline_without_a_cache_entry_fails() {
+    let home = temp_home("cache-offline-miss", &format!("api_key = \"test\"\nbase_url = \"{}\"\n", closed_port_url()));
+
+    let output = run_weather(&home, &["now", "52.52,13.40", "--offline"], &[]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("There is no cached report for 52.5200, 13.4000"));
+}
+
+#[test]
+#[cfg(feature = "openweathermap")]
+fn network_failures_fall_back_to_stale_entries() {
+    let server = MockServer::start(vec![http_response(200, &[], CURRENT)]);
+    // A zero TTL makes every entry stale straight away
+    let home = temp_home("cache-stale", &format!("api_key = \"test\"\nbase_url = \"{}\"\ncache_ttl = \"0s\"\n", server.url));
+    assert!(run_weather(&home, &["now", "52.5244,13.4105"], &[]).status.success());
+
+    write_config(&home, &format!("api_key = \"test\"\nbase_url = \"{}\"\ncache_ttl = \"0s\"\n", closed_port_url()));
+    let output = run_weather(&home, &["now", "52.5244,13.4105"], &[]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(output.status.success(), "{}", stderr);
+    assert!(stderr.contains("OpenWeatherMap could not be reached (network error:"), "{}", stderr);
+    assert!(stderr.contains("showing the report cached"), "{}", stderr);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("> Temperature: 20.0°C"));
+}
+
+#[test]
+fn concurrent_writes_of_one_key_do_not_collide() {
+    let dir = temp_home("cache-concurrent", "").join("cache");
+    let cache = Cache { dir: dir.clone(), ttl: Duration::from_secs(60) };
+    cache.put("shared", &vec![0usize; 1000]);
+
+    // Values of different lengths, so a write moved into place half-done is invalid JSON
+    let done = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for writer in 0..8 {
+            let (cache, done) = (&cache, &done);
+            scope.spawn(move || {
+                for _ in 0..100 {
+                    cache.put("shared", &vec![writer; 1000 * (writer + 1)]);
+                }
+                done.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        while done.load(Ordering::SeqCst) < 8 {
+            let entry = cache.get::<Vec<usize>>("shared").expect("readers only ever see complete entries");
+            assert_eq!(entry.value.len(), 1000 * (entry.value[0] + 1));
+        }
+    });
+
+    let files: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|file| file.unwrap().file_name()).collect();
+    assert_eq!(files, ["shared.json"], "partial files were left behind");
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use common::{run_weather, temp_home};
+
+#[test]
+fn help_lists_the_subcommands() {
+    let home = temp_home("cli-help", "");
+
+    let output = run_weather(&home, &["--help"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success());
+    for command in ["now", "forecast", "interactive", "config"] {
+        assert!(stdout.lines().any(|line| line.trim_start().starts_with(command)), "no {} in:\n{}", command, stdout);
+    }
+}
+
+#[test]
+fn one_shot_commands_need_a_location() {
+    let home = temp_home("cli-no-location", "");
+
+    for command in ["now", "forecast"] {
+        let output = run_weather(&home, &[command], &[]);
+        assert_eq!(output.status.code(), Some(2));
+        assert!(output.stdout.is_empty());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains(&format!("Usage: weather {}", command)), "{}", stderr);
+    }
+}
+
+#[test]
+fn unknown_subcommands_are_usage_errors() {
+    let home = temp_home("cli-unknown", "");
+
+    let output = run_weather(&home, &["tomorrow"], &[]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unrecognized subcommand 'tomorrow'"));
+}
@@ -0,0 +1,134 @@
+#![allow(dead_code)]
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// Local HTTP server that answers each connection with the next canned response
+pub struct MockServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockServer {
+    pub fn start(responses: Vec<String>) -> Self {
+        Self::with_delay(responses, Duration::ZERO)
+    }
+
+    // Like `start`, but waits before answering, as a slow upstream would
+    pub fn with_delay(responses: Vec<String>, delay: Duration) -> Self {
+        Self::linking(|_| responses, delay)
+    }
+
+    // Like `start`, for responses that link back to the server, given its URL
+    pub fn linking(responses: impl FnOnce(&str) -> Vec<String>, delay: Duration) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind mock server");
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let responses = responses(&url);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let seen = Arc::clone(&requests);
+        thread::spawn(move || {
+            for response in responses {
+                let Ok((mut stream, _)) = listener.accept() else { return };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap_or(0) > 2 {
+                    header.clear();
+                }
+                seen.lock().unwrap().push(request_line.trim_end().to_string());
+                thread::sleep(delay);
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+
+        MockServer { url, requests }
+    }
+
+    // Request lines received so far, e.g. "GET /data/2.5/weather?q=... HTTP/1.1"
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+// Direct-geocoding reply naming a single place
+pub fn geocoded(name: &str, country: &str, lat: f64, lon: f64) -> String {
+    let body = format!(r#"[{{"name":"{}","lat":{},"lon":{},"country":"{}"}}]"#, name, lat, lon, country);
+    http_response(200, &[], &body)
+}
+
+// Builds a raw HTTP/1.1 response
+pub fn http_response(status: u16, headers: &[(&str, &str)], body: &str) -> String {
+    let mut response = format!("HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n", status, body.len());
+    for (name, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str("\r\n");
+    response.push_str(body);
+    response
+}
+
+// An isolated XDG config/cache/state home holding the given config.toml
+pub fn temp_home(name: &str, config: &str) -> PathBuf {
+    let home = std::env::temp_dir().join(format!("weather-test-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&home);
+    std::fs::create_dir_all(home.join("config").join("weather")).unwrap();
+    std::fs::write(home.join("config").join("weather").join("config.toml"), config).unwrap();
+    home
+}
+
+// Runs the weather binary against an isolated home directory
+pub fn run_weather(home: &PathBuf, args: &[&str], env: &[(&str, &str)]) -> Output {
+    let mut command = weather_command(home, args);
+    for (name, value) in env {
+        command.env(name, value);
+    }
+    command.output().expect("run weather binary")
+}
+
+// The weather binary set up to run against an isolated home directory, for tests that spawn it
+pub fn weather_command(home: &PathBuf, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_weather"));
+    command
+        .args(args)
+        .env_remove("OPENWEATHER_API_KEY")
+        .env_remove("WEATHER_LOG")
+        .env_remove("HTTP_PROXY")
+        .env_remove("http_proxy")
+        .env_remove("HTTPS_PROXY")
+        .env_remove("https_proxy")
+        .env_remove("ALL_PROXY")
+        .env_remove("all_proxy")
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("XDG_CACHE_HOME", home.join("cache"))
+        .env("XDG_STATE_HOME", home.join("state"))
+        .env("NO_COLOR", "1");
+    command
+}
+
+// Combined stdout and stderr of a run
+pub fn all_output(output: &Output) -> String {
+    format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr))
+}
+
+// Base URL of a local port with nothing listening on it
+pub fn closed_port_url() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+    url
+}
+
+// A port that accepts connections but never answers; keep the listener alive while it is used
+pub fn stalled_server() -> (TcpListener, String) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    (listener, url)
+}
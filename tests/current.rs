@@ -0,0 +1,87 @@
+// Every test talks to the mock server as OpenWeatherMap
+#![cfg(feature = "openweathermap")]
+
+mod common;
+
+use common::{geocoded, http_response, run_weather, temp_home, MockServer};
+
+// Sample response of /data/2.5/weather with every optional section present
+const CURRENT: &str = r#"{
+  "coord": {"lon": 13.4105, "lat": 52.5244},
+  "weather": [{"id": 501, "main": "Rain", "description": "moderate rain", "icon": "10d"}],
+  "base": "stations",
+  "main": {"temp": 11.2, "feels_like": 10.4, "temp_min": 9.8, "temp_max": 12.6, "pressure": 1008, "humidity": 87, "sea_level": 1008, "grnd_level": 1003},
+  "visibility": 8000,
+  "wind": {"speed": 5.1, "deg": 230, "gust": 9.3},
+  "rain": {"1h": 1.2},
+  "clouds": {"all": 90},
+  "dt": 1792058400,
+  "sys": {"type": 2, "id": 2011538, "country": "DE", "sunrise": 1792043760, "sunset": 1792081980},
+  "timezone": 7200,
+  "id": 2950159,
+  "name": "Berlin",
+  "cod": 200
+}"#;
+
+#[test]
+fn detailed_view_shows_the_full_payload() {
+    let server = MockServer::start(vec![geocoded("Berlin", "DE", 52.5244, 13.4105), http_response(200, &[], CURRENT)]);
+    let home = temp_home("current-detailed", &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url));
+
+    let output = run_weather(&home, &["now", "--city", "Berlin", "--country", "DE", "--detailed"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    for expected in [
+        "Weather Update for Berlin: moderate rain",
+        "> Location: Berlin, DE (52.5244, 13.4105)",
+        "> Observed: 2026-10-15 12:00",
+        "> Feels Like: 10.4°C",
+        "> Min/Max: 9.8°C / 12.6°C",
+        "> Ground-Level Pressure: 1003.0 hPa",
+        "> Visibility: 8.0 km",
+        "> Cloud Cover: 90.0%",
+        "> Wind Direction: 230° (SW)",
+        "> Wind Gusts: 9.3 m/s",
+        "> Rain (1h): 1.2 mm",
+        "> Sunrise: 07:56",
+        "> Sunset: 18:33",
+        "> Condition Code: 501 (icon 10d)",
+    ] {
+        assert!(stdout.contains(expected), "missing {:?} in:\n{}", expected, stdout);
+    }
+    assert!(!stdout.contains("Snow"), "{}", stdout);
+}
+
+#[test]
+fn plain_view_keeps_the_short_report() {
+    let server = MockServer::start(vec![geocoded("Berlin", "DE", 52.5244, 13.4105), http_response(200, &[], CURRENT)]);
+    let home = temp_home("current-plain", &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url));
+
+    let output = run_weather(&home, &["now", "--city", "Berlin", "--country", "DE"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success());
+    assert!(stdout.contains("> Wind Speed: 5.1 m/s"), "{}", stdout);
+    assert!(!stdout.contains("Feels Like"), "{}", stdout);
+}
+
+#[test]
+fn ambiguous_names_fail_with_the_candidates() {
+    let matches = r#"[
+        {"name": "Springfield", "lat": 39.7990, "lon": -89.6440, "country": "US", "state": "Illinois"},
+        {"name": "Springfield", "lat": 37.2153, "lon": -93.2983, "country": "US", "state": "Missouri"},
+        {"name": "Springfield", "lat": 37.2100, "lon": -93.2900, "country": "US", "state": "Missouri"}
+    ]"#;
+    let server = MockServer::start(vec![http_response(200, &[], matches)]);
+    let home = temp_home("current-ambiguous", &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url));
+
+    let output = run_weather(&home, &["now", "Springfield, US"], &[]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(!output.status.success());
+    assert!(server.requests()[0].starts_with("GET /geo/1.0/direct?q=Springfield%2CUS&limit=5"), "{:?}", server.requests());
+    assert!(stderr.contains("\"Springfield, US\" matches 2 places"), "{}", stderr);
+    assert!(stderr.contains("- Springfield, Illinois, US (39.7990, -89.6440)"), "{}", stderr);
+    assert!(stderr.contains("- Springfield, Missouri, US (37.2153, -93.2983)"), "{}", stderr);
+}
@@ -0,0 +1,58 @@
+// Every test reads OpenWeatherMap's error replies
+#![cfg(feature = "openweathermap")]
+
+mod common;
+
+use common::{http_response, run_weather, temp_home, MockServer};
+
+// Runs `weather now` against a server sending one reply and returns the exit code and stderr
+fn fail_with(name: &str, reply: String) -> (Option<i32>, String) {
+    let server = MockServer::start(vec![reply]);
+    let home = temp_home(name, &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url));
+
+    let output = run_weather(&home, &["now", "--city", "Berlin", "--country", "DE"], &[]);
+
+    assert!(output.stdout.is_empty());
+    (output.status.code(), String::from_utf8_lossy(&output.stderr).to_string())
+}
+
+#[test]
+fn a_rejected_key_says_so() {
+    let (code, stderr) = fail_with("error-auth", http_response(401, &[], r#"{"cod":401,"message":"Invalid API key."}"#));
+
+    assert_eq!(code, Some(1));
+    assert!(stderr.contains("OpenWeatherMap rejected the API key (Invalid API key.)"), "{}", stderr);
+}
+
+#[test]
+fn an_unknown_city_is_not_found() {
+    let (code, stderr) = fail_with("error-not-found", http_response(404, &[], r#"{"cod":"404","message":"city not found"}"#));
+
+    assert_eq!(code, Some(1));
+    assert!(stderr.contains("No weather found for that location"), "{}", stderr);
+}
+
+#[test]
+fn rate_limits_give_the_wait() {
+    let body = r#"{"cod":429,"message":"Your account is temporary blocked"}"#;
+    let (code, stderr) = fail_with("error-rate-limit", http_response(429, &[("Retry-After", "7200")], body));
+
+    assert_eq!(code, Some(1));
+    assert!(stderr.contains("Try again in 7200 seconds"), "{}", stderr);
+}
+
+#[test]
+fn provider_messages_are_shown() {
+    let (code, stderr) = fail_with("error-provider", http_response(400, &[], r#"{"cod":"400","message":"wrong latitude"}"#));
+
+    assert_eq!(code, Some(1));
+    assert!(stderr.contains("returned error 400: wrong latitude"), "{}", stderr);
+}
+
+#[test]
+fn unreadable_bodies_are_decode_errors() {
+    let (code, stderr) = fail_with("error-decode", http_response(200, &[], "<html>maintenance</html>"));
+
+    assert_eq!(code, Some(1));
+    assert!(stderr.contains("sent a response this version cannot read"), "{}", stderr);
+}
@@ -0,0 +1,174 @@
+// Every test talks to the mock server as OpenWeatherMap
+#![cfg(feature = "openweathermap")]
+
+mod common;
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::{Child, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use common::{all_output, geocoded, http_response, run_weather, temp_home, weather_command, MockServer};
+
+const CURRENT: &str = r#"{
+  "coord": {"lon": 13.4105, "lat": 52.5244},
+  "weather": [{"id": 800, "description": "clear sky", "icon": "01d"}],
+  "main": {"temp": 20.5, "pressure": 1013, "humidity": 50},
+  "wind": {"speed": 10.0, "gust": 14.5},
+  "dt": 1792058400,
+  "sys": {"country": "DE"},
+  "timezone": 7200,
+  "name": "Berlin"
+}"#;
+
+// A running `weather exporter`, stopped when dropped
+struct Exporter {
+    child: Child,
+    address: String,
+}
+
+impl Exporter {
+    fn start(home: &std::path::PathBuf, args: &[&str]) -> Self {
+        let args: Vec<&str> = ["exporter", "--bind", "127.0.0.1:0", "--every", "1h"].iter().chain(args).copied().collect();
+        let mut child = weather_command(home, &args).stdout(Stdio::piped()).spawn().unwrap();
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap()).read_line(&mut line).unwrap();
+        let address = line.trim().strip_prefix("Listening on http://").and_then(|rest| rest.strip_suffix("/metrics")).expect("listening message");
+        Exporter { child, address: address.to_string() }
+    }
+
+    // Sends a GET request and returns the status and the body
+    fn get(&self, path: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(&self.address).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", path, self.address).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        (head.split(' ').nth(1).unwrap().parse().unwrap(), body.to_string())
+    }
+
+    // Scrapes /metrics until the first poll of every location has finished
+    fn metrics_after_poll(&self, locations: usize) -> String {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            let (status, body) = self.get("/metrics");
+            assert_eq!(status, 200);
+            if body.lines().filter(|line| line.starts_with("weather_polls_total{") && line.ends_with(" 1")).count() == locations {
+                return body;
+            }
+            assert!(Instant::now() < deadline, "no poll finished:\n{}", body);
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+}
+
+impl Drop for Exporter {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn home_with(name: &str, server: &MockServer, exporter: &str) -> std::path::PathBuf {
+    let config = format!("api_key = \"test\"\nbase_url = \"{}\"\n\n[http]\nretries = 0\n\n[exporter]\n{}", server.url, exporter);
+    temp_home(name, &config)
+}
+
+#[test]
+fn publishes_readings_of_configured_locations() {
+    let upstream = MockServer::start(vec![geocoded("Berlin", "DE", 52.5244, 13.4105), http_response(200, &[], CURRENT)]);
+    let home = home_with("exporter-readings", &upstream, "locations = [\"Berlin, DE\"]\n");
+    let exporter = Exporter::start(&home, &[]);
+
+    let metrics = exporter.metrics_after_poll(1);
+
+    for expected in [
+        "# TYPE weather_temperature_celsius gauge",
+        "weather_temperature_celsius{city=\"Berlin\",country=\"DE\"} 20.5",
+        "weather_humidity_percent{city=\"Berlin\",country=\"DE\"} 50",
+        "weather_pressure_hpa{city=\"Berlin\",country=\"DE\"} 1013",
+        "weather_wind_speed_meters_per_second{city=\"Berlin\",country=\"DE\"} 10",
+        "weather_wind_gust_meters_per_second{city=\"Berlin\",country=\"DE\"} 14.5",
+        "weather_observation_timestamp_seconds{city=\"Berlin\",country=\"DE\"} 1792058400",
+        "weather_up{city=\"Berlin\",country=\"DE\"} 1",
+        "# TYPE weather_api_errors_total counter",
+    ] {
+        assert!(metrics.lines().any(|line| line == expected), "missing {:?} in:\n{}", expected, metrics);
+    }
+    assert!(!metrics.contains("weather_api_errors_total{"), "{}", metrics);
+    assert!(!metrics.contains("weather_feels_like_celsius{"), "{}", metrics);
+}
+
+#[test]
+fn labels_stay_as_configured_after_a_lookup() {
+    let upstream = MockServer::start(vec![geocoded("Frankfurt", "DE", 50.1106, 8.6821), http_response(200, &[], CURRENT)]);
+    let home = home_with("exporter-labels", &upstream, "locations = [\"Frankfurt am Main, de\"]\n");
+    let exporter = Exporter::start(&home, &[]);
+
+    let metrics = exporter.metrics_after_poll(1);
+
+    for expected in [
+        "weather_temperature_celsius{city=\"Frankfurt am Main\",country=\"DE\"} 20.5",
+        "weather_up{city=\"Frankfurt am Main\",country=\"DE\"} 1",
+        "weather_polls_total{city=\"Frankfurt am Main\",country=\"DE\"} 1",
+    ] {
+        assert!(metrics.lines().any(|line| line == expected), "missing {:?} in:\n{}", expected, metrics);
+    }
+    assert!(!metrics.contains("city=\"Frankfurt\""), "{}", metrics);
+}
+
+#[test]
+fn failed_polls_are_counted_by_code() {
+    let upstream = MockServer::start(vec![http_response(401, &[], r#"{"cod":401,"message":"Invalid API key"}"#)]);
+    let home = home_with("exporter-errors", &upstream, "");
+    let exporter = Exporter::start(&home, &["--location", "Paris, FR"]);
+
+    let metrics = exporter.metrics_after_poll(1);
+
+    assert!(metrics.contains("weather_up{city=\"Paris\",country=\"FR\"} 0"), "{}", metrics);
+    assert!(metrics.contains("weather_api_errors_total{city=\"Paris\",country=\"FR\",code=\"auth\"} 1"), "{}", metrics);
+    assert!(!metrics.contains("weather_temperature_celsius{"), "{}", metrics);
+    assert_eq!(exporter.get("/nothing").0, 404);
+}
+
+#[test]
+fn health_is_left_out_until_the_first_poll_finishes() {
+    let upstream = MockServer::with_delay(vec![geocoded("Berlin", "DE", 52.5244, 13.4105), http_response(200, &[], CURRENT)], Duration::from_millis(500));
+    let home = home_with("exporter-first-poll", &upstream, "locations = [\"Berlin, DE\"]\n");
+    let exporter = Exporter::start(&home, &[]);
+
+    let (_, metrics) = exporter.get("/metrics");
+
+    assert!(metrics.contains("weather_polls_total{city=\"Berlin\",country=\"DE\"} 0"), "{}", metrics);
+    assert!(!metrics.contains("weather_up{"), "{}", metrics);
+    assert!(!metrics.contains("weather_poll_duration_seconds{"), "{}", metrics);
+    assert!(exporter.metrics_after_poll(1).contains("weather_up{city=\"Berlin\",country=\"DE\"} 1"));
+}
+
+#[test]
+fn refuses_locations_with_the_same_labels() {
+    let upstream = MockServer::start(vec![]);
+    let home = home_with("exporter-duplicates", &upstream, "locations = [\"Paris, FR\", \"Oslo, NO\", \"Paris, fr\"]\n");
+
+    let output = run_weather(&home, &["exporter"], &[]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(all_output(&output).contains("city=\"Paris\",country=\"FR\""), "{}", all_output(&output));
+    assert!(upstream.requests().is_empty());
+}
+
+#[test]
+fn needs_locations() {
+    let upstream = MockServer::start(vec![]);
+    let home = home_with("exporter-none", &upstream, "");
+
+    let output = run_weather(&home, &["exporter"], &[]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(all_output(&output).contains("no locations to poll"), "{}", all_output(&output));
+
+    let home = home_with("exporter-invalid", &upstream, "locations = [\"zip:\"]\n");
+    let output = run_weather(&home, &["exporter"], &[]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(all_output(&output).contains("invalid location \"zip:\""), "{}", all_output(&output));
+}
@@ -0,0 +1,67 @@
+// Builds without some providers or formats leave the helpers of their tests unused
+#![cfg_attr(not(all(feature = "openweathermap", feature = "met-no")), allow(dead_code, unused_imports))]
+
+mod common;
+
+use common::{geocoded, http_response, run_weather, temp_home, MockServer};
+
+// Three steps on 2026-10-15 and one on the 16th, local time at UTC+2
+const FORECAST: &str = r#"{
+  "cod": "200",
+  "list": [
+    {"dt": 1792044000, "main": {"temp": 9.0, "humidity": 80, "pressure": 1012}, "weather": [{"id": 500, "description": "light rain"}], "wind": {"speed": 3.0}, "rain": {"3h": 1.5}},
+    {"dt": 1792054800, "main": {"temp": 14.0, "humidity": 70, "pressure": 1012}, "weather": [{"id": 500, "description": "light rain"}], "wind": {"speed": 4.0}, "rain": {"3h": 0.5}},
+    {"dt": 1792065600, "main": {"temp": 11.0, "humidity": 75, "pressure": 1013}, "weather": [{"id": 800, "description": "clear sky"}], "wind": {"speed": 2.0}},
+    {"dt": 1792108800, "main": {"temp": 6.0, "humidity": 90, "pressure": 1015}, "weather": [{"id": 804, "description": "overcast clouds"}], "wind": {"speed": 1.0}}
+  ],
+  "city": {"name": "Berlin", "timezone": 7200}
+}"#;
+
+#[test]
+#[cfg(feature = "openweathermap")]
+fn forecast_groups_steps_into_local_days() {
+    let server = MockServer::start(vec![geocoded("Berlin", "DE", 52.5244, 13.4105), http_response(200, &[], FORECAST)]);
+    let home = temp_home("forecast", &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url));
+
+    let output = run_weather(&home, &["forecast", "--city", "Berlin", "--country", "DE", "--hourly"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(server.requests()[1].starts_with("GET /data/2.5/forecast?lat=52.5244&lon=13.4105&units=metric"));
+    let days: Vec<&str> = stdout.lines().filter(|line| line.starts_with("Thu ") || line.starts_with("Fri ")).collect();
+    assert_eq!(days.len(), 2, "{}", stdout);
+    assert!(days[0].contains("9.0°C to 14.0°C, light rain, precipitation 2.0 mm"), "{}", days[0]);
+    assert!(days[1].contains("6.0°C to 6.0°C, overcast clouds"), "{}", days[1]);
+    assert!(stdout.contains("> 14:00 11.0°C"), "{}", stdout);
+}
+
+// Hourly steps, then one six-hourly step, and a last step with no period to describe
+const MET_NO: &str = r#"{"properties": {"timeseries": [
+  {"time": "2026-10-15T21:00:00Z", "data": {"instant": {"details": {"air_temperature": 7.0, "relative_humidity": 80.0, "wind_speed": 3.0}},
+    "next_1_hours": {"summary": {"symbol_code": "lightrain_night"}, "details": {"precipitation_amount": 0.4}}}},
+  {"time": "2026-10-15T23:00:00Z", "data": {"instant": {"details": {"air_temperature": 6.0, "relative_humidity": 85.0, "wind_speed": 2.5}},
+    "next_1_hours": {"summary": {"symbol_code": "lightrain_night"}, "details": {"precipitation_amount": 0.6}}}},
+  {"time": "2026-10-16T06:00:00Z", "data": {"instant": {"details": {"air_temperature": 4.0}},
+    "next_6_hours": {"summary": {"symbol_code": "cloudy"}, "details": {"precipitation_amount": 0.0}}}},
+  {"time": "2026-10-16T12:00:00Z", "data": {"instant": {"details": {"air_temperature": 9.0}}}}
+]}}"#;
+
+#[test]
+#[cfg(feature = "met-no")]
+fn met_no_forecasts_come_from_the_time_series() {
+    let server = MockServer::start(vec![http_response(200, &[], MET_NO)]);
+    let home = temp_home("forecast-met-no", &format!("provider = \"met-no\"\n\n[endpoints]\nmet-no = \"{}\"\n", server.url));
+
+    let output = run_weather(&home, &["forecast", "59.91,10.75", "--hourly"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(server.requests()[0].starts_with("GET /weatherapi/locationforecast/2.0/compact?lat=59.9100&lon=10.7500"));
+    // Oslo's longitude puts the days at UTC+1, so midnight there is 23:00 UTC
+    let days: Vec<&str> = stdout.lines().filter(|line| line.starts_with("Thu ") || line.starts_with("Fri ")).collect();
+    assert_eq!(days.len(), 2, "{}", stdout);
+    assert!(days[0].contains("7.0°C to 7.0°C, light rain, precipitation 0.4 mm"), "{}", days[0]);
+    assert!(days[1].contains("4.0°C to 6.0°C"), "{}", days[1]);
+    assert!(stdout.contains("> 00:00 6.0°C") && stdout.contains("> 07:00 4.0°C"), "{}", stdout);
+    assert!(!stdout.contains("9.0°C"), "{}", stdout);
+}
@@ -0,0 +1,182 @@
+// Builds without some providers or formats leave the helpers of their tests unused
+#![cfg_attr(not(all(feature = "openweathermap", feature = "open-meteo", feature = "json")), allow(dead_code, unused_imports))]
+
+mod common;
+
+use std::time::{Duration, Instant};
+use common::{all_output, closed_port_url, http_response, run_weather, stalled_server, temp_home, MockServer};
+use serde_json::Value;
+
+const CURRENT: &str = r#"{
+  "coord": {"lon": 13.4105, "lat": 52.5244},
+  "weather": [{"id": 800, "description": "clear sky", "icon": "01d"}],
+  "main": {"temp": 20.0, "pressure": 1013, "humidity": 50},
+  "wind": {"speed": 10.0},
+  "dt": 1792058400,
+  "sys": {"country": "DE"},
+  "timezone": 7200,
+  "name": "Berlin"
+}"#;
+
+fn home_with(name: &str, base_url: &str, http: &str) -> std::path::PathBuf {
+    temp_home(name, &format!("api_key = \"test\"\nbase_url = \"{}\"\n\n[http]\n{}", base_url, http))
+}
+
+// Current conditions as Open-Meteo reports them
+const OPEN_METEO_CURRENT: &str = r#"{"utc_offset_seconds": 7200, "current": {"time": 1792058400, "temperature_2m": 18.5, "weather_code": 0}}"#;
+
+// Open-Meteo and its geocoder both reached at `url`
+fn open_meteo_home(name: &str, url: &str, http: &str) -> std::path::PathBuf {
+    temp_home(name, &format!("provider = \"open-meteo\"\n\n[endpoints]\nopen-meteo = \"{0}\"\ngeocoding = \"{0}\"\n\n[http]\n{1}", url, http))
+}
+
+#[test]
+#[cfg(feature = "openweathermap")]
+fn server_errors_are_retried() {
+    let server = MockServer::start(vec![
+        http_response(503, &[], "unavailable"),
+        http_response(500, &[], "oops"),
+        http_response(200, &[], CURRENT),
+    ]);
+    let home = home_with("http-5xx", &server.url, "backoff = \"10ms\"\n");
+
+    let output = run_weather(&home, &["now", "52.52,13.40"], &[]);
+
+    assert!(output.status.success(), "{}", all_output(&output));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("clear sky"));
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[test]
+#[cfg(all(feature = "openweathermap", feature = "json"))]
+fn retries_give_up_after_the_limit() {
+    let responses = (0..3).map(|_| http_response(502, &[], "bad gateway")).collect();
+    let server = MockServer::start(responses);
+    let home = home_with("http-give-up", &server.url, "retries = 2\nbackoff = \"10ms\"\n");
+
+    let output = run_weather(&home, &["now", "52.52,13.40", "--output", "json"], &[]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let error: Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["error"]["code"], "provider");
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[test]
+#[cfg(feature = "openweathermap")]
+fn retry_after_is_honoured() {
+    let server = MockServer::start(vec![http_response(429, &[("Retry-After", "1")], ""), http_response(200, &[], CURRENT)]);
+    let home = home_with("http-retry-after", &server.url, "backoff = \"10ms\"\n");
+
+    let started = Instant::now();
+    let output = run_weather(&home, &["now", "52.52,13.40"], &[]);
+
+    assert!(output.status.success(), "{}", all_output(&output));
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[test]
+#[cfg(all(feature = "openweathermap", feature = "json"))]
+fn long_retry_after_is_reported_instead_of_waited_for() {
+    let server = MockServer::start(vec![http_response(429, &[("Retry-After", "120")], "")]);
+    let home = home_with("http-long-retry-after", &server.url, "max_backoff = \"5s\"\n");
+
+    let output = run_weather(&home, &["now", "52.52,13.40", "--output", "json"], &[]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let error: Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["error"]["code"], "rate_limited");
+    assert_eq!(error["error"]["retry_after_seconds"], 120);
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+#[cfg(all(feature = "openweathermap", feature = "json"))]
+fn refused_connections_are_retried() {
+    let home = home_with("http-connect", &closed_port_url(), "retries = 2\nbackoff = \"10ms\"\n");
+
+    let output = run_weather(&home, &["now", "52.52,13.40", "--output", "json"], &[("WEATHER_LOG", "weather=info")]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(stderr.matches("retrying request").count(), 2, "{}", stderr);
+    assert!(stderr.contains(r#""code":"network""#), "{}", stderr);
+}
+
+#[test]
+#[cfg(all(feature = "openweathermap", feature = "json"))]
+fn stalled_servers_time_out() {
+    let (_listener, url) = stalled_server();
+    let home = home_with("http-timeout", &url, "timeout = \"1s\"\n");
+
+    let started = Instant::now();
+    let output = run_weather(&home, &["now", "52.52,13.40", "--output", "json"], &[]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let error: Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["error"]["code"], "timeout");
+    assert!(started.elapsed() < Duration::from_secs(10), "timeouts should not be retried");
+}
+
+#[test]
+#[cfg(feature = "openweathermap")]
+fn requests_go_through_the_configured_proxy() {
+    let proxy = MockServer::start(vec![http_response(200, &[], CURRENT)]);
+    let home = home_with("http-proxy", "http://weather.example", &format!("proxy = \"{}\"\n", proxy.url));
+
+    let output = run_weather(&home, &["now", "52.52,13.40"], &[]);
+
+    assert!(output.status.success(), "{}", all_output(&output));
+    assert!(proxy.requests()[0].starts_with("GET http://weather.example/data/2.5/weather?"), "{:?}", proxy.requests());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("base_url uses plain HTTP"));
+}
+
+#[test]
+#[cfg(feature = "openweathermap")]
+fn proxy_environment_variables_are_honoured() {
+    let proxy = MockServer::start(vec![http_response(200, &[], CURRENT)]);
+    let home = home_with("http-proxy-env", "http://weather.example", "");
+
+    let output = run_weather(&home, &["now", "52.52,13.40"], &[("HTTP_PROXY", &proxy.url)]);
+
+    assert!(output.status.success(), "{}", all_output(&output));
+    assert!(proxy.requests()[0].starts_with("GET http://weather.example/data/2.5/weather?"), "{:?}", proxy.requests());
+}
+
+#[test]
+#[cfg(feature = "open-meteo")]
+fn other_providers_use_their_configured_endpoints() {
+    let geocoded = r#"{"results": [{"name": "Berlin", "latitude": 52.52, "longitude": 13.41, "country_code": "DE"}]}"#;
+    let server = MockServer::start(vec![
+        http_response(200, &[], geocoded),
+        http_response(503, &[], "unavailable"),
+        http_response(200, &[], OPEN_METEO_CURRENT),
+    ]);
+    let home = open_meteo_home("http-open-meteo-retry", &server.url, "backoff = \"10ms\"\n");
+
+    let output = run_weather(&home, &["now", "Berlin"], &[]);
+
+    assert!(output.status.success(), "{}", all_output(&output));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("clear sky"));
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    assert!(requests[0].starts_with("GET /v1/search?name=Berlin"), "{}", requests[0]);
+    assert!(requests[1].starts_with("GET /v1/forecast?latitude=52.52&longitude=13.41"), "{}", requests[1]);
+    assert_eq!(requests[1], requests[2]);
+}
+
+#[test]
+#[cfg(all(feature = "open-meteo", feature = "json"))]
+fn other_providers_time_out() {
+    let (_listener, url) = stalled_server();
+    let home = open_meteo_home("http-open-meteo-timeout", &url, "timeout = \"1s\"\n");
+
+    let started = Instant::now();
+    let output = run_weather(&home, &["now", "52.52,13.40", "--output", "json"], &[]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let error: Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["error"]["code"], "timeout");
+    assert!(started.elapsed() < Duration::from_secs(10), "timeouts should not be retried");
+}
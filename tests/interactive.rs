@@ -0,0 +1,34 @@
+// The prompt is checked against OpenWeatherMap
+#![cfg(feature = "openweathermap")]
+
+mod common;
+
+use std::io::Read;
+use std::process::Stdio;
+use std::thread;
+use std::time::{Duration, Instant};
+use common::{temp_home, weather_command};
+
+#[test]
+fn the_prompt_quits_when_stdin_is_closed() {
+    let home = temp_home("interactive-eof", "api_key = \"test\"\n");
+
+    let mut child = weather_command(&home, &[]).stdin(Stdio::null()).stdout(Stdio::piped()).spawn().unwrap();
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        if started.elapsed() > Duration::from_secs(10) {
+            child.kill().unwrap();
+            panic!("the prompt kept waiting for input after stdin was closed");
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    assert!(status.success());
+    let mut stdout = String::new();
+    child.stdout.take().unwrap().read_to_string(&mut stdout).unwrap();
+    assert_eq!(stdout.matches("Enter a city").count(), 1, "{}", stdout);
+    assert!(!stdout.contains("That is not a location"), "{}", stdout);
+}
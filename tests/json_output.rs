@@ -0,0 +1,147 @@
+// Every test reads JSON from OpenWeatherMap reports
+#![cfg(all(feature = "openweathermap", feature = "json"))]
+
+mod common;
+
+use common::{geocoded, http_response, run_weather, temp_home, MockServer};
+use serde_json::Value;
+
+// Sample response of /data/2.5/weather without the optional sections
+const CURRENT: &str = r#"{
+  "coord": {"lon": 13.4105, "lat": 52.5244},
+  "weather": [{"id": 800, "description": "clear sky", "icon": "01d"}],
+  "main": {"temp": 20.0, "feels_like": 19.5, "pressure": 1013, "humidity": 50},
+  "wind": {"speed": 10.0, "deg": 90},
+  "dt": 1792058400,
+  "sys": {"country": "DE", "sunrise": 1792043760, "sunset": 1792081980},
+  "timezone": 7200,
+  "name": "Berlin"
+}"#;
+
+const FORECAST: &str = r#"{
+  "list": [
+    {"dt": 1792044000, "main": {"temp": 9.0, "humidity": 80, "pressure": 1012}, "weather": [{"id": 500, "description": "light rain"}], "wind": {"speed": 3.0}, "rain": {"3h": 1.5}},
+    {"dt": 1792108800, "main": {"temp": 6.0, "humidity": 90, "pressure": 1015}, "weather": [{"id": 804, "description": "overcast clouds"}], "wind": {"speed": 1.0}}
+  ],
+  "city": {"name": "Berlin", "timezone": 7200}
+}"#;
+
+fn config_for(server: &MockServer) -> String {
+    format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url)
+}
+
+#[test]
+fn now_prints_one_json_object_without_colors() {
+    let server = MockServer::start(vec![geocoded("Berlin", "DE", 52.5244, 13.4105), http_response(200, &[], CURRENT)]);
+    let home = temp_home("json-now", &config_for(&server));
+
+    // Force colors on to prove the JSON path never uses them
+    let output = run_weather(&home, &["now", "Berlin, DE", "--output", "json", "--units", "imperial"], &[("CLICOLOR_FORCE", "1")]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(!stdout.contains('\x1b'), "{}", stdout);
+    let report: Value = serde_json::from_str(&stdout).expect("stdout is a single JSON document");
+    assert_eq!(report["location"]["name"], "Berlin");
+    assert_eq!(report["location"]["country"], "DE");
+    assert_eq!(report["location"]["latitude"], 52.5244);
+    assert_eq!(report["observed_at"], "2026-10-15T10:00:00Z");
+    assert_eq!(report["utc_offset_seconds"], 7200);
+    assert_eq!(report["units"]["temperature"], "°F");
+    assert_eq!(report["units"]["wind_speed"], "mph");
+    assert_eq!(report["condition"], "clear");
+    assert_eq!(report["condition_code"], 800);
+    assert_eq!(report["temperature"], 68.0);
+    assert_eq!(report["wind_speed"], 22.37);
+    assert_eq!(report["pressure"], 29.91);
+    assert_eq!(report["sunset"], "2026-10-15T16:33:00Z");
+    // Readings the provider left out are present as null
+    assert!(report["visibility"].is_null());
+    assert!(report.as_object().unwrap().contains_key("snow_3h"));
+}
+
+#[test]
+fn forecast_lists_days_and_steps() {
+    let server = MockServer::start(vec![geocoded("Berlin", "DE", 52.5244, 13.4105), http_response(200, &[], FORECAST)]);
+    let home = temp_home("json-forecast", &config_for(&server));
+
+    let output = run_weather(&home, &["forecast", "Berlin, DE", "--output", "json"], &[]);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let forecast: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(forecast["days"][0]["date"], "2026-10-15");
+    assert_eq!(forecast["days"][0]["precipitation"], 1.5);
+    assert_eq!(forecast["days"][1]["condition"], "overcast");
+    assert_eq!(forecast["entries"][0]["time"], "2026-10-15T08:00:00+02:00");
+    assert_eq!(forecast["entries"].as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn errors_are_json_on_stderr() {
+    let matches = r#"[
+        {"name": "Springfield", "lat": 39.7990, "lon": -89.6440, "country": "US", "state": "Illinois"},
+        {"name": "Springfield", "lat": 37.2153, "lon": -93.2983, "country": "US", "state": "Missouri"}
+    ]"#;
+    let server = MockServer::start(vec![http_response(200, &[], matches)]);
+    let home = temp_home("json-ambiguous", &config_for(&server));
+
+    let output = run_weather(&home, &["now", "Springfield, US", "--output", "json"], &[]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    let error: Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["error"]["code"], "ambiguous");
+    assert_eq!(error["error"]["candidates"][1]["state"], "Missouri");
+    assert_eq!(error["error"]["candidates"][1]["coordinates"]["latitude"], 37.2153);
+}
+
+#[test]
+fn setup_errors_are_json_too() {
+    let home = temp_home("json-missing-key", "");
+
+    let output = run_weather(&home, &["now", "Berlin", "--output", "json"], &[]);
+
+    assert_eq!(output.status.code(), Some(2));
+    let error: Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["error"]["code"], "missing_key");
+}
+
+#[test]
+fn interactive_mode_refuses_structured_output() {
+    let home = temp_home("json-interactive", "api_key = \"test\"\n");
+
+    let output = run_weather(&home, &["--output", "json"], &[]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(output.stdout.is_empty());
+    let error: Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["error"]["code"], "unsupported_output");
+}
+
+#[test]
+fn config_prints_the_settings_as_one_object() {
+    let home = temp_home("json-config", "base_url = \"http://127.0.0.1:9\"\n");
+
+    let output = run_weather(&home, &["config", "--output", "json"], &[("OPENWEATHER_API_KEY", "test")]);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let settings: Value = serde_json::from_slice(&output.stdout).expect("one JSON object");
+    assert_eq!(settings["provider"], "openweathermap");
+    assert_eq!(settings["config_file_exists"], true);
+    assert_eq!(settings["endpoint"], "http://127.0.0.1:9");
+    assert_eq!(settings["api_key_needed"], true);
+    assert!(settings["api_key_source"].as_str().unwrap().contains("OPENWEATHER_API_KEY"), "{}", settings);
+}
+
+#[test]
+fn config_without_a_key_prints_the_settings_and_a_json_error() {
+    let home = temp_home("json-config-no-key", "");
+
+    let output = run_weather(&home, &["config", "--output", "json"], &[]);
+
+    assert_eq!(output.status.code(), Some(2));
+    let settings: Value = serde_json::from_slice(&output.stdout).expect("one JSON object");
+    assert!(settings["api_key_source"].is_null());
+    let error: Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["error"]["code"], "missing_key");
+}
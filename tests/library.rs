@@ -0,0 +1,70 @@
+// Every test talks to the mock server as OpenWeatherMap
+#![cfg(feature = "openweathermap")]
+
+mod common;
+
+use std::collections::BTreeMap;
+use common::{geocoded, http_response, MockServer};
+use weather::http::HttpSettings;
+use weather::providers::Endpoints;
+use weather::redact::ApiKey;
+use weather::units::UnitSystem;
+use weather::{render, BlockingClient, Condition, Location, Origin, ProviderKind, Units, WeatherClient, WeatherError};
+
+const CURRENT: &str = r#"{"weather":[{"id":500,"main":"Rain","description":"light rain"}],"main":{"temp":11.2,"feels_like":10.1,"pressure":1012,"humidity":87},"wind":{"speed":3.5},"dt":1792058400,"sys":{"country":"DE"},"timezone":7200,"name":"Berlin","cod":200}"#;
+
+// A client without cache or rate limit, talking to the mock server as OpenWeatherMap
+fn client(server: &MockServer) -> BlockingClient {
+    let endpoints = Endpoints { openweathermap: Some(server.url.clone()), ..Endpoints::default() };
+    let provider = ProviderKind::OpenWeatherMap.build(Some(ApiKey::new("test")), &endpoints, &HttpSettings::default(), None).unwrap();
+    BlockingClient::new(WeatherClient::new(provider, None, false, None, BTreeMap::new())).unwrap()
+}
+
+#[test]
+fn the_library_fetches_and_renders_a_report() {
+    let server = MockServer::start(vec![geocoded("Berlin", "DE", 52.52, 13.40), http_response(200, &[], CURRENT)]);
+    let client = client(&server);
+
+    let location: Location = "Berlin, DE".parse().unwrap();
+    let mut places = client.locate(&location).unwrap().value;
+    assert_eq!(places.len(), 1);
+    let fetched = client.current(&places.remove(0)).unwrap();
+
+    assert!(matches!(fetched.origin, Origin::Fetched));
+    let report = fetched.value;
+    assert_eq!(report.location, "Berlin");
+    assert_eq!(report.condition, Condition::Rain);
+    let text = render::weather_text(&Units::from(UnitSystem::Imperial), &report, false, None).to_string();
+    assert!(text.contains("Weather Update for Berlin: light rain") && text.contains("52.2°F"), "{}", text);
+}
+
+#[test]
+fn the_library_reports_lookup_failures_as_errors() {
+    let server = MockServer::start(vec![http_response(200, &[], "[]")]);
+    let client = client(&server);
+
+    let error = client.locate(&"Atlantis".parse().unwrap()).unwrap_err();
+
+    assert!(matches!(error, WeatherError::NotFound { .. }), "{:?}", error);
+    assert_eq!(error.code(), "not_found");
+}
+
+#[test]
+fn building_openweathermap_without_a_key_is_an_error() {
+    let error = ProviderKind::OpenWeatherMap.build(None, &Endpoints::default(), &HttpSettings::default(), None).err().unwrap();
+
+    assert!(matches!(error, WeatherError::Auth { .. }), "{:?}", error);
+}
+
+#[test]
+fn providers_do_not_look_up_saved_names() {
+    let server = MockServer::start(Vec::new());
+    let endpoints = Endpoints { openweathermap: Some(server.url.clone()), ..Endpoints::default() };
+    let provider = ProviderKind::OpenWeatherMap.build(Some(ApiKey::new("test")), &endpoints, &HttpSettings::default(), None).unwrap();
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let error = runtime.block_on(provider.locate(&"@home".parse().unwrap())).unwrap_err();
+
+    assert!(matches!(error, WeatherError::NotFound { .. }), "{:?}", error);
+    assert!(server.requests().is_empty());
+}
@@ -0,0 +1,195 @@
+// Every test resolves locations through OpenWeatherMap
+#![cfg(feature = "openweathermap")]
+
+mod common;
+
+use common::{http_response, run_weather, temp_home, MockServer};
+
+// Minimal response of /data/2.5/weather
+const CURRENT: &str = r#"{
+  "coord": {"lon": 13.4105, "lat": 52.5244},
+  "weather": [{"id": 800, "description": "clear sky", "icon": "01d"}],
+  "main": {"temp": 18.0, "pressure": 1015, "humidity": 40},
+  "wind": {"speed": 2.0},
+  "dt": 1792058400,
+  "sys": {"country": "DE"},
+  "timezone": 7200,
+  "name": "Mitte"
+}"#;
+
+fn home_for(name: &str, server: &MockServer) -> std::path::PathBuf {
+    temp_home(name, &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url))
+}
+
+#[test]
+fn coordinates_skip_geocoding() {
+    let server = MockServer::start(vec![http_response(200, &[], CURRENT)]);
+    let home = home_for("locations-coordinates", &server);
+
+    let output = run_weather(&home, &["now", "52.52,13.405"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(server.requests().len(), 1);
+    assert!(server.requests()[0].starts_with("GET /data/2.5/weather?lat=52.52&lon=13.405&units=metric"), "{:?}", server.requests());
+    assert!(stdout.contains("Weather Update for Mitte"), "{}", stdout);
+}
+
+#[test]
+fn negative_coordinates_are_not_taken_for_flags() {
+    let server = MockServer::start(vec![http_response(200, &[], CURRENT)]);
+    let home = home_for("locations-negative", &server);
+
+    let output = run_weather(&home, &["now", "-33.87,151.21"], &[]);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(server.requests()[0].starts_with("GET /data/2.5/weather?lat=-33.87&lon=151.21"), "{:?}", server.requests());
+}
+
+#[test]
+fn postal_codes_use_the_zip_endpoint() {
+    let zip = r#"{"zip": "10115", "name": "Berlin", "lat": 52.532, "lon": 13.3849, "country": "DE"}"#;
+    let server = MockServer::start(vec![http_response(200, &[], zip), http_response(200, &[], CURRENT)]);
+    let home = home_for("locations-postal", &server);
+
+    let output = run_weather(&home, &["now", "zip:10115,DE"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(server.requests()[0].starts_with("GET /geo/1.0/zip?zip=10115%2CDE"), "{:?}", server.requests());
+    assert!(server.requests()[1].starts_with("GET /data/2.5/weather?lat=52.532&lon=13.3849"), "{:?}", server.requests());
+    assert!(stdout.contains("Weather Update for Berlin"), "{}", stdout);
+}
+
+#[test]
+fn city_ids_are_looked_up_by_id() {
+    let server = MockServer::start(vec![http_response(200, &[], CURRENT), http_response(200, &[], CURRENT)]);
+    let home = home_for("locations-city-id", &server);
+
+    let output = run_weather(&home, &["now", "id:2950159"], &[]);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(server.requests()[0].starts_with("GET /data/2.5/weather?id=2950159&units=metric"), "{:?}", server.requests());
+}
+
+#[test]
+fn out_of_range_coordinates_are_rejected() {
+    let home = temp_home("locations-out-of-range", "api_key = \"test\"\n");
+
+    let output = run_weather(&home, &["now", "95,13"], &[]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("coordinates out of range"));
+}
+
+#[test]
+fn saved_locations_are_used_as_at_names() {
+    let server = MockServer::start(vec![http_response(200, &[], CURRENT)]);
+    let config = format!("api_key = \"test\"\nbase_url = \"{}\"\n\n[locations]\ndepot = {{ lat = 52.52, lon = 13.405 }}\n", server.url);
+    let home = temp_home("locations-saved", &config);
+
+    let output = run_weather(&home, &["now", "@depot"], &[]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(server.requests().len(), 1);
+    assert!(server.requests()[0].starts_with("GET /data/2.5/weather?lat=52.52&lon=13.405"), "{:?}", server.requests());
+
+    let output = run_weather(&home, &["now", "@office"], &[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no saved location named \"office\""), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn bare_weather_shows_the_default_location() {
+    let server = MockServer::start(vec![http_response(200, &[], CURRENT)]);
+    let config = format!(
+        "api_key = \"test\"\nbase_url = \"{}\"\ndefault_location = \"depot\"\n\n[locations]\ndepot = {{ lat = 52.52, lon = 13.405 }}\n",
+        server.url
+    );
+    let home = temp_home("locations-default", &config);
+
+    let output = run_weather(&home, &[], &[]);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Weather Update for Mitte"), "{}", String::from_utf8_lossy(&output.stdout));
+}
+
+#[test]
+fn locations_are_added_and_removed_in_the_config_file() {
+    let home = temp_home("locations-edit", "# written by hand\napi_key = \"test\"\n");
+    let config_file = home.join("config").join("weather").join("config.toml");
+
+    let output = run_weather(&home, &["locations", "add", "home", "Amsterdam, NL", "--default"], &[]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let output = run_weather(&home, &["locations", "add", "depot", "--city", "Utrecht", "--country", "NL"], &[]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let config = std::fs::read_to_string(&config_file).unwrap();
+    assert!(config.starts_with("# written by hand\n"), "{}", config);
+    assert!(config.contains("default_location = \"home\""), "{}", config);
+    assert!(config.contains("home = { city = \"Amsterdam\", country = \"NL\" }"), "{}", config);
+
+    #[cfg(feature = "json")]
+    {
+        let output = run_weather(&home, &["--output", "json", "locations", "ls"], &[]);
+        let listed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(listed[0]["name"], "depot");
+        assert_eq!(listed[1]["location"], "Amsterdam, NL");
+        assert_eq!(listed[1]["default"], true);
+    }
+
+    let output = run_weather(&home, &["locations", "rm", "@home"], &[]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let config = std::fs::read_to_string(&config_file).unwrap();
+    assert!(!config.contains("home"), "{}", config);
+    assert!(config.contains("depot = { city = \"Utrecht\", country = \"NL\" }"), "{}", config);
+    assert_eq!(run_weather(&home, &["locations", "rm", "home"], &[]).status.code(), Some(1));
+}
+
+#[test]
+#[cfg(any(feature = "json", feature = "ndjson", feature = "csv"))]
+fn location_changes_are_records_in_structured_output() {
+    let home = temp_home("locations-structured", "api_key = \"test\"\n\n[locations]\nhome = { city = \"Amsterdam\", country = \"NL\" }\n");
+
+    #[cfg(feature = "json")]
+    {
+        let output = run_weather(&home, &["--output", "json", "locations", "add", "office", "Utrecht, NL", "--default"], &[]);
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let added: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(added["action"], "saved");
+        assert_eq!(added["name"], "office");
+        assert_eq!(added["location"], "Utrecht, NL");
+        assert_eq!(added["default"], true);
+    }
+
+    #[cfg(feature = "ndjson")]
+    {
+        let output = run_weather(&home, &["--output", "ndjson", "locations", "rm", "home"], &[]);
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let removed: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(removed["action"], "removed");
+        assert_eq!(removed["location"], "Amsterdam, NL");
+    }
+
+    #[cfg(feature = "csv")]
+    {
+        let output = run_weather(&home, &["--output", "csv", "locations", "add", "depot", "52.37,4.89"], &[]);
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "action,name,location,default\nsaved,depot,\"52.37,4.89\",false\n");
+    }
+}
+
+#[test]
+#[cfg(feature = "open-meteo")]
+fn the_open_meteo_geocoder_takes_abbreviated_states() {
+    let geocoded = r#"{"results": [
+      {"name": "Springfield", "latitude": 39.80, "longitude": -89.64, "country_code": "US", "admin1": "Illinois"},
+      {"name": "Springfield", "latitude": 37.22, "longitude": -93.30, "country_code": "US", "admin1": "Missouri"}
+    ]}"#;
+    let current = r#"{"utc_offset_seconds": -18000, "current": {"time": 1792058400, "temperature_2m": 18.5, "weather_code": 0}}"#;
+    let server = MockServer::start(vec![http_response(200, &[], geocoded), http_response(200, &[], current)]);
+    let home = temp_home("locations-state", &format!("provider = \"open-meteo\"\n\n[endpoints]\nopen-meteo = \"{0}\"\ngeocoding = \"{0}\"\n", server.url));
+
+    let output = run_weather(&home, &["now", "Springfield, IL, US"], &[]);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let requests = server.requests();
+    assert!(requests[1].starts_with("GET /v1/forecast?latitude=39.8&longitude=-89.64"), "{}", requests[1]);
+}
@@ -0,0 +1,90 @@
+// Builds without some providers leave the helpers of their tests unused
+#![cfg_attr(not(all(feature = "openweathermap", feature = "open-meteo", feature = "met-no", feature = "nws")), allow(dead_code, unused_imports))]
+
+mod common;
+
+use std::time::Duration;
+use common::{http_response, run_weather, temp_home, MockServer};
+
+// The first step of a MET Norway Locationforecast, with a code whose words run together
+const MET_NO: &str = r#"{"properties": {"timeseries": [
+  {"time": "2026-10-15T12:00:00Z", "data": {"instant": {"details": {"air_temperature": 1.5, "relative_humidity": 90.0, "wind_speed": 6.0}},
+    "next_1_hours": {"summary": {"symbol_code": "lightssleetshowers_day"}, "details": {"precipitation_amount": 0.3}}}}
+]}}"#;
+
+// The latest observation of an NWS station, in SI units with pressures in Pa and speeds in km/h
+const NWS_LATEST: &str = r#"{"properties": {"timestamp": "2026-10-15T12:00:00Z", "textDescription": "Light Rain", "icon": null,
+  "temperature": {"value": 12.0}, "relativeHumidity": {"value": 81.0}, "barometricPressure": {"value": 101000},
+  "seaLevelPressure": {"value": 101500}, "windSpeed": {"value": 18.0}, "windDirection": {"value": 200},
+  "windGust": {"value": null}, "visibility": {"value": 16000}, "heatIndex": {"value": null}, "windChill": {"value": null}}}"#;
+
+// The `provider:` line of `weather config`
+fn provider_shown(home: &std::path::PathBuf, args: &[&str]) -> String {
+    let mut command = vec!["config"];
+    command.extend(args);
+    let output = run_weather(home, &command, &[]);
+    String::from_utf8_lossy(&output.stdout).lines().find(|line| line.starts_with("provider:")).unwrap_or_default().to_string()
+}
+
+#[test]
+#[cfg(all(feature = "openweathermap", feature = "open-meteo", feature = "met-no"))]
+fn the_flag_overrides_the_configured_provider() {
+    let home = temp_home("providers-default", "api_key = \"test\"\n");
+    assert_eq!(provider_shown(&home, &[]), "provider: openweathermap");
+
+    let home = temp_home("providers-config", "provider = \"met-no\"\n");
+    assert_eq!(provider_shown(&home, &[]), "provider: met-no");
+    assert_eq!(provider_shown(&home, &["--provider", "open-meteo"]), "provider: open-meteo");
+}
+
+#[test]
+#[cfg(feature = "met-no")]
+fn keyless_providers_need_no_key() {
+    let home = temp_home("providers-keyless", "provider = \"met-no\"\n");
+
+    let output = run_weather(&home, &["config"], &[]);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("api key: not needed"));
+}
+
+#[test]
+#[cfg(feature = "met-no")]
+fn met_no_symbol_codes_are_spelled_out() {
+    let server = MockServer::start(vec![http_response(200, &[], MET_NO)]);
+    let home = temp_home("providers-met-no", &format!("provider = \"met-no\"\n\n[endpoints]\nmet-no = \"{}\"\n", server.url));
+
+    let output = run_weather(&home, &["now", "59.91,10.75"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(server.requests()[0].starts_with("GET /weatherapi/locationforecast/2.0/compact?lat=59.9100&lon=10.7500"));
+    assert!(stdout.contains("light sleet showers"), "{}", stdout);
+    assert!(stdout.contains("1.5°C"), "{}", stdout);
+}
+
+#[test]
+#[cfg(feature = "nws")]
+fn nws_reads_the_first_station_of_the_grid_point() {
+    let server = MockServer::linking(
+        |url| {
+            let point = format!(r#"{{"properties": {{"observationStations": "{0}/gridpoints/LOT/75,72/stations", "forecastHourly": "{0}/gridpoints/LOT/75,72/forecast/hourly"}}}}"#, url);
+            let stations = format!(r#"{{"features": [{{"id": "{0}/stations/KMDW"}}, {{"id": "{0}/stations/KORD"}}]}}"#, url);
+            vec![http_response(200, &[], &point), http_response(200, &[], &stations), http_response(200, &[], NWS_LATEST)]
+        },
+        Duration::ZERO,
+    );
+    let home = temp_home("providers-nws", &format!("provider = \"nws\"\n\n[endpoints]\nnws = \"{}\"\n", server.url));
+
+    let output = run_weather(&home, &["now", "41.88,-87.63"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let requests = server.requests();
+    assert!(requests[0].starts_with("GET /points/41.8800,-87.6300 "), "{}", requests[0]);
+    assert!(requests[1].starts_with("GET /gridpoints/LOT/75,72/stations "), "{}", requests[1]);
+    assert!(requests[2].starts_with("GET /stations/KMDW/observations/latest "), "{}", requests[2]);
+    assert!(stdout.contains("light rain") && stdout.contains("12.0°C"), "{}", stdout);
+    // 101500 Pa at sea level and 18 km/h
+    assert!(stdout.contains("1015") && stdout.contains("5.0 m/s"), "{}", stdout);
+}
@@ -0,0 +1,151 @@
+// Every test talks to the mock server as OpenWeatherMap
+#![cfg(feature = "openweathermap")]
+
+mod common;
+
+#[cfg(feature = "json")]
+use common::geocoded;
+use common::{http_response, run_weather, temp_home, MockServer};
+#[cfg(feature = "json")]
+use serde_json::Value;
+
+const CURRENT: &str = r#"{
+  "coord": {"lon": 13.4105, "lat": 52.5244},
+  "weather": [{"id": 800, "description": "clear sky", "icon": "01d"}],
+  "main": {"temp": 20.0, "pressure": 1013, "humidity": 50},
+  "wind": {"speed": 10.0},
+  "dt": 1792058400,
+  "sys": {"country": "DE"},
+  "timezone": 7200,
+  "name": "Berlin"
+}"#;
+
+fn home_with(name: &str, server: &MockServer, quota: &str) -> std::path::PathBuf {
+    temp_home(name, &format!("api_key = \"test\"\nbase_url = \"{}\"\n\n[quota]\n{}", server.url, quota))
+}
+
+#[cfg(feature = "json")]
+fn quota_status(home: &std::path::PathBuf) -> Value {
+    let output = run_weather(home, &["quota", "--output", "json"], &[]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+#[cfg(feature = "json")]
+fn calls_are_counted_per_day_and_month() {
+    let server = MockServer::start(vec![http_response(200, &[], CURRENT), http_response(200, &[], CURRENT)]);
+    let home = home_with("quota-count", &server, "per_minute = 60\n");
+
+    assert!(run_weather(&home, &["now", "52.52,13.40"], &[]).status.success());
+    assert!(run_weather(&home, &["now", "48.85,2.35"], &[]).status.success());
+    // Served from the cache, so not counted
+    assert!(run_weather(&home, &["now", "52.52,13.40"], &[]).status.success());
+
+    let status = quota_status(&home);
+    assert_eq!(status["provider"], "openweathermap");
+    assert_eq!(status["calls_today"], 2);
+    assert_eq!(status["calls_this_month"], 2);
+    assert_eq!(status["monthly_limit"], 1_000_000);
+    assert_eq!(status["available_now"], 58);
+
+    let text = run_weather(&home, &["quota"], &[]);
+    assert!(String::from_utf8_lossy(&text.stdout).contains("this month (UTC): 2 of 1000000 calls (0.0%)"));
+}
+
+#[test]
+#[cfg(feature = "json")]
+fn calls_beyond_the_daily_limit_are_refused() {
+    let server = MockServer::start(vec![http_response(200, &[], CURRENT)]);
+    let home = home_with("quota-refuse", &server, "daily_limit = 1\n");
+
+    assert!(run_weather(&home, &["now", "52.52,13.40"], &[]).status.success());
+    let output = run_weather(&home, &["now", "48.85,2.35", "--output", "json"], &[]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let error: Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["error"]["code"], "quota_exceeded");
+    assert_eq!(error["error"]["message"], "daily quota used up: 1 of 1 calls");
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn nearing_a_limit_warns() {
+    let server = MockServer::start(vec![http_response(200, &[], CURRENT)]);
+    let home = home_with("quota-warn", &server, "daily_limit = 10\nwarn_at = 0.1\n");
+
+    let output = run_weather(&home, &["now", "52.52,13.40"], &[]);
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("1 of the daily limit of 10 calls have been used."));
+}
+
+#[test]
+fn an_empty_bucket_refuses_rather_than_waiting_too_long() {
+    let server = MockServer::start(vec![http_response(200, &[], CURRENT)]);
+    let home = home_with("quota-bucket", &server, "per_minute = 1\nmax_wait = \"1s\"\n");
+
+    assert!(run_weather(&home, &["now", "52.52,13.40"], &[]).status.success());
+    let output = run_weather(&home, &["now", "48.85,2.35"], &[]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Too many requests to OpenWeatherMap. Try again in 5"), "{}", stderr);
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+#[cfg(feature = "json")]
+fn every_request_of_a_lookup_is_counted() {
+    let server = MockServer::start(vec![geocoded("Berlin", "DE", 52.52, 13.40), http_response(200, &[], CURRENT)]);
+    let home = temp_home("quota-lookup", &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url));
+
+    assert!(run_weather(&home, &["now", "Berlin, DE"], &[]).status.success());
+
+    assert_eq!(server.requests().len(), 2);
+    let status = quota_status(&home);
+    assert_eq!(status["calls_today"], 2);
+    assert_eq!(status["available_now"], 58);
+}
+
+#[test]
+#[cfg(feature = "json")]
+fn retried_requests_are_counted() {
+    let server = MockServer::start(vec![http_response(503, &[], "{}"), http_response(200, &[], CURRENT)]);
+    let home = temp_home("quota-retries", &format!("api_key = \"test\"\nbase_url = \"{}\"\n\n[http]\nbackoff = \"10ms\"\n", server.url));
+
+    assert!(run_weather(&home, &["now", "52.52,13.40"], &[]).status.success());
+
+    assert_eq!(server.requests().len(), 2);
+    let status = quota_status(&home);
+    assert_eq!(status["calls_today"], 2);
+    assert_eq!(status["available_now"], 58);
+}
+
+#[test]
+#[cfg(feature = "json")]
+fn a_429_empties_the_shared_bucket() {
+    let server = MockServer::start(vec![http_response(429, &[("Retry-After", "1")], "{}"), http_response(200, &[], CURRENT)]);
+    let home = home_with("quota-429", &server, "per_minute = 60\n");
+
+    assert!(run_weather(&home, &["now", "52.52,13.40"], &[]).status.success());
+
+    assert_eq!(server.requests().len(), 2);
+    let status = quota_status(&home);
+    assert_eq!(status["calls_today"], 2);
+    // The retry waited for the bucket to refill after the pause, which left it empty for everyone
+    assert_eq!(status["available_now"], 0);
+}
+
+#[test]
+#[cfg(all(feature = "open-meteo", feature = "json"))]
+fn keyless_providers_have_no_made_up_limits() {
+    let home = temp_home("quota-keyless", "provider = \"open-meteo\"\n");
+
+    let status = quota_status(&home);
+
+    assert_eq!(status["provider"], "open-meteo");
+    assert_eq!(status["per_minute"], Value::Null);
+    assert_eq!(status["available_now"], Value::Null);
+    assert_eq!(status["monthly_limit"], Value::Null);
+}
@@ -0,0 +1,84 @@
+// Every test reads CSV or NDJSON records of OpenWeatherMap reports
+#![cfg(all(feature = "openweathermap", any(feature = "csv", feature = "ndjson")))]
+
+mod common;
+
+use common::{geocoded, http_response, run_weather, temp_home, MockServer};
+#[cfg(feature = "ndjson")]
+use serde_json::Value;
+
+const CURRENT: &str = r#"{
+  "coord": {"lon": 13.4105, "lat": 52.5244},
+  "weather": [{"id": 800, "description": "clear sky, calm", "icon": "01d"}],
+  "main": {"temp": 20.0, "pressure": 1013, "humidity": 50},
+  "wind": {"speed": 10.0},
+  "dt": 1792058400,
+  "sys": {"country": "DE"},
+  "timezone": 7200,
+  "name": "Berlin"
+}"#;
+
+const FORECAST: &str = r#"{
+  "list": [
+    {"dt": 1792044000, "main": {"temp": 9.0, "humidity": 80, "pressure": 1012}, "weather": [{"id": 500, "description": "light rain"}], "wind": {"speed": 3.0}, "rain": {"3h": 1.5}},
+    {"dt": 1792054800, "main": {"temp": 14.0, "humidity": 70, "pressure": 1012}, "weather": [{"id": 500, "description": "light rain"}], "wind": {"speed": 4.0}},
+    {"dt": 1792108800, "main": {"temp": 6.0, "humidity": 90, "pressure": 1015}, "weather": [{"id": 804, "description": "overcast clouds"}], "wind": {"speed": 1.0}}
+  ],
+  "city": {"name": "Berlin", "timezone": 7200}
+}"#;
+
+fn run(name: &str, response: &str, args: &[&str]) -> String {
+    let server = MockServer::start(vec![geocoded("Berlin", "DE", 52.5244, 13.4105), http_response(200, &[], response)]);
+    let home = temp_home(name, &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url));
+    let output = run_weather(&home, args, &[]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+#[cfg(feature = "csv")]
+fn csv_has_a_header_and_flattened_columns() {
+    let stdout = run("records-csv-now", CURRENT, &["now", "Berlin, DE", "--output", "csv"]);
+    let lines: Vec<&str> = stdout.lines().collect();
+
+    assert_eq!(lines.len(), 2, "{}", stdout);
+    assert!(lines[0].starts_with("location_name,location_country,location_latitude,location_longitude,observed_at,utc_offset_seconds,units_temperature,"), "{}", lines[0]);
+    assert!(lines[0].ends_with(",sunrise,sunset"), "{}", lines[0]);
+    assert!(lines[1].starts_with("Berlin,DE,52.5244,13.4105,2026-10-15T10:00:00Z,7200,°C,m/s,hPa,mm,km,\"clear sky, calm\",clear,800,01d,20.0,,"), "{}", lines[1]);
+    // Every row has as many cells as the header
+    assert_eq!(lines[0].split(',').count(), lines[1].replace("\"clear sky, calm\"", "x").split(',').count());
+}
+
+#[test]
+#[cfg(feature = "csv")]
+fn csv_forecast_rows_are_days_or_steps() {
+    let daily = run("records-csv-daily", FORECAST, &["forecast", "Berlin, DE", "--output", "csv"]);
+    assert_eq!(daily.lines().collect::<Vec<_>>(), [
+        "location,date,min_temperature,max_temperature,description,condition,precipitation,units_temperature,units_wind_speed,units_pressure,units_precipitation,units_visibility",
+        "Berlin,2026-10-15,9.0,14.0,light rain,rain,1.5,°C,m/s,hPa,mm,km",
+        "Berlin,2026-10-16,6.0,6.0,overcast clouds,overcast,0.0,°C,m/s,hPa,mm,km",
+    ]);
+
+    let hourly = run("records-csv-hourly", FORECAST, &["forecast", "Berlin, DE", "--output", "csv", "--hourly", "--units", "imperial"]);
+    let lines: Vec<&str> = hourly.lines().collect();
+    assert_eq!(lines.len(), 4, "{}", hourly);
+    assert!(lines[0].starts_with("location,time,description,condition,temperature,humidity,wind_speed,precipitation,"), "{}", lines[0]);
+    assert!(lines[1].starts_with("Berlin,2026-10-15T08:00:00+02:00,light rain,rain,48.2,80.0,6.71,0.06,°F,mph"), "{}", lines[1]);
+}
+
+#[test]
+#[cfg(feature = "ndjson")]
+fn ndjson_prints_one_record_per_line() {
+    let now = run("records-ndjson-now", CURRENT, &["now", "Berlin, DE", "--output", "ndjson"]);
+    assert_eq!(now.lines().count(), 1);
+    let record: Value = serde_json::from_str(now.trim_end()).unwrap();
+    assert_eq!(record["location"]["name"], "Berlin");
+    assert_eq!(record["temperature"], 20.0);
+
+    let hourly = run("records-ndjson-hourly", FORECAST, &["forecast", "Berlin, DE", "--output", "ndjson", "--hourly"]);
+    let records: Vec<Value> = hourly.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[2]["location"], "Berlin");
+    assert_eq!(records[2]["condition"], "overcast");
+    assert_eq!(records[2]["units"]["temperature"], "°C");
+}
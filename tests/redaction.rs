@@ -0,0 +1,47 @@
+// Every test uses an OpenWeatherMap API key
+#![cfg(feature = "openweathermap")]
+
+mod common;
+
+use common::{all_output, closed_port_url, http_response, run_weather, temp_home, MockServer};
+
+const SECRET: &str = "0123456789abcdefSECRETKEY";
+
+#[test]
+fn network_errors_and_debug_logs_hide_the_key() {
+    let home = temp_home("network", &format!("base_url = \"{}\"\n", closed_port_url()));
+
+    let output = run_weather(&home, &["-vv", "--api-key", SECRET, "now", "--city", "Berlin", "--country", "DE"], &[]);
+    let text = all_output(&output);
+
+    assert!(!output.status.success());
+    assert!(!text.contains(SECRET), "key leaked:\n{}", text);
+    assert!(text.contains("appid=[REDACTED]"), "expected redacted URL in:\n{}", text);
+}
+
+#[test]
+fn provider_messages_echoing_the_key_are_redacted() {
+    let body = format!("{{\"cod\":500,\"message\":\"internal error for appid={}\"}}", SECRET);
+    let server = MockServer::start(vec![http_response(500, &[], &body)]);
+    let home = temp_home("provider", &format!("base_url = \"{}\"\n", server.url));
+
+    let output = run_weather(&home, &["-v", "now", "--city", "Berlin", "--country", "DE"], &[("OPENWEATHER_API_KEY", SECRET)]);
+    let text = all_output(&output);
+
+    assert!(!output.status.success());
+    assert!(server.requests()[0].contains(SECRET), "the key should still be sent upstream");
+    assert!(!text.contains(SECRET), "key leaked:\n{}", text);
+    assert!(text.contains("[REDACTED]"), "expected redacted message in:\n{}", text);
+}
+
+#[test]
+fn keys_from_the_config_file_are_redacted_too() {
+    let config = format!("api_key = \"{}\"\nbase_url = \"{}\"\n", SECRET, closed_port_url());
+    let home = temp_home("config-key", &config);
+
+    for args in [&["config"][..], &["-vv", "now", "--city", "Oslo", "--country", "NO"][..]] {
+        let output = run_weather(&home, args, &[]);
+        let text = all_output(&output);
+        assert!(!text.contains(SECRET), "key leaked by {:?}:\n{}", args, text);
+    }
+}
@@ -0,0 +1,142 @@
+// Every test talks to the mock server as OpenWeatherMap
+#![cfg(feature = "openweathermap")]
+
+mod common;
+
+use std::fs;
+use common::{http_response, run_weather, temp_home, MockServer};
+#[cfg(feature = "json")]
+use serde_json::Value;
+
+// Freezing, light rain and a moderate wind
+const CURRENT: &str = r#"{
+  "weather": [{"id": 500, "description": "light rain"}],
+  "main": {"temp": -3.0, "pressure": 1013, "humidity": 90},
+  "wind": {"speed": 5.0},
+  "dt": 1792058400,
+  "sys": {"country": "DE"},
+  "timezone": 7200,
+  "name": "Berlin"
+}"#;
+
+const RULES: &str = r#"
+[[rule]]
+name = "frost"
+when = "temp < 0"
+
+[[rule]]
+name = "gale"
+when = "wind.speed > 15"
+
+[[rule]]
+name = "wet"
+when = "description contains 'Rain' and not (humidity < 80 or snow.1h > 0)"
+"#;
+
+fn home_with(name: &str, server: &MockServer, rules: &str) -> std::path::PathBuf {
+    let home = temp_home(name, &format!("api_key = \"test\"\nbase_url = \"{}\"\ncache_ttl = \"0s\"\n\n[http]\nretries = 0\n", server.url));
+    fs::write(home.join("config").join("weather").join("rules.toml"), rules).unwrap();
+    home
+}
+
+#[test]
+fn check_exits_with_3_when_a_rule_fires() {
+    let server = MockServer::start(vec![http_response(200, &[], CURRENT)]);
+    let home = home_with("rules-fired", &server, RULES);
+
+    let output = run_weather(&home, &["check", "52.52,13.40"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert_eq!(output.status.code(), Some(3), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.contains("FIRED frost: temp < 0"), "{}", stdout);
+    assert!(stdout.contains("ok    gale: wind.speed > 15"), "{}", stdout);
+    assert!(stdout.contains("FIRED wet: "), "{}", stdout);
+    assert!(stdout.contains("2 of 3 rules fired."), "{}", stdout);
+}
+
+#[test]
+fn check_succeeds_when_no_rule_fires() {
+    let server = MockServer::start(vec![http_response(200, &[], CURRENT)]);
+    let home = home_with("rules-quiet", &server, "[[rule]]\nname = \"heat\"\nwhen = \"temp >= 30 || condition == 'clear'\"\n");
+
+    let output = run_weather(&home, &["check", "52.52,13.40"], &[]);
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("0 of 1 rules fired."));
+}
+
+#[test]
+#[cfg(feature = "json")]
+fn check_reports_rules_as_json() {
+    let server = MockServer::start(vec![http_response(200, &[], CURRENT)]);
+    let home = home_with("rules-json", &server, RULES);
+
+    let output = run_weather(&home, &["check", "52.52,13.40", "--output", "json"], &[]);
+
+    assert_eq!(output.status.code(), Some(3));
+    let report: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["observation"]["temperature"], -3.0);
+    let fired: Vec<&Value> = report["rules"].as_array().unwrap().iter().map(|rule| &rule["fired"]).collect();
+    assert_eq!(fired, [&Value::Bool(true), &Value::Bool(false), &Value::Bool(true)]);
+    assert_eq!(report["rules"][1]["when"], "wind.speed > 15");
+}
+
+#[test]
+#[cfg(feature = "json")]
+fn invalid_rules_are_rejected_before_fetching() {
+    let server = MockServer::start(vec![]);
+    let home = home_with("rules-invalid", &server, "[[rule]]\nname = \"typo\"\nwhen = \"tmp < 0\"\n");
+
+    let output = run_weather(&home, &["check", "52.52,13.40", "--output", "json"], &[]);
+
+    assert_eq!(output.status.code(), Some(2));
+    let error: Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(error["error"]["code"], "rules_parse");
+    assert!(error["error"]["message"].as_str().unwrap().contains("unknown field `tmp`"));
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn check_needs_a_rules_file() {
+    let server = MockServer::start(vec![]);
+    let home = temp_home("rules-missing", &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url));
+
+    let output = run_weather(&home, &["check", "52.52,13.40"], &[]);
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("could not read rules file"));
+}
+
+#[test]
+fn watch_runs_rule_commands_with_the_reading_and_a_cooldown() {
+    let responses = (0..3).map(|_| http_response(200, &[], CURRENT)).collect();
+    let server = MockServer::start(responses);
+    let home = temp_home("rules-watch", "");
+    let log = home.join("hooks.log");
+    let rules = format!(
+        r#"
+[[rule]]
+name = "frost"
+when = "temp < 0"
+command = 'echo "$WEATHER_RULE $WEATHER_TEMPERATURE $WEATHER_UNITS_TEMPERATURE $WEATHER_LOCATION_NAME" >> {log}'
+
+[[rule]]
+name = "rain"
+when = "condition == 'rain'"
+command = 'echo "$WEATHER_RULE" >> {log}'
+cooldown = "0s"
+"#,
+        log = log.display()
+    );
+    let home = home_with("rules-watch", &server, &rules);
+
+    let output = run_weather(&home, &["watch", "52.52,13.40", "--every", "1s", "--count", "3"], &[]);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Rules fired: frost, rain"));
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    let lines: Vec<String> = fs::read_to_string(&log).unwrap().lines().map(str::to_string).collect();
+    let frost: Vec<&String> = lines.iter().filter(|line| line.starts_with("frost")).collect();
+    assert_eq!(frost, ["frost -3.0 °C Berlin"], "the cooldown allows one run: {:?}", lines);
+    assert_eq!(lines.iter().filter(|line| *line == "rain").count(), 3, "{:?}", lines);
+}
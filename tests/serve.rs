@@ -0,0 +1,136 @@
+// Every test talks to the mock server as OpenWeatherMap
+#![cfg(feature = "openweathermap")]
+
+mod common;
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::{Child, Stdio};
+use std::thread;
+use std::time::Duration;
+use common::{geocoded, http_response, temp_home, weather_command, MockServer};
+use serde_json::Value;
+
+const CURRENT: &str = r#"{
+  "coord": {"lon": 13.4105, "lat": 52.5244},
+  "weather": [{"id": 800, "description": "clear sky", "icon": "01d"}],
+  "main": {"temp": 20.0, "pressure": 1013, "humidity": 50},
+  "wind": {"speed": 10.0},
+  "dt": 1792058400,
+  "sys": {"country": "DE"},
+  "timezone": 7200,
+  "name": "Berlin"
+}"#;
+
+// A running `weather serve`, stopped when dropped
+struct Gateway {
+    child: Child,
+    address: String,
+}
+
+impl Gateway {
+    fn start(home: &std::path::PathBuf) -> Self {
+        let mut child = weather_command(home, &["serve", "--bind", "127.0.0.1:0"]).stdout(Stdio::piped()).spawn().unwrap();
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap()).read_line(&mut line).unwrap();
+        let address = line.trim().strip_prefix("Listening on http://").expect("listening message").to_string();
+        Gateway { child, address }
+    }
+
+    // Sends a GET request and returns the status, the raw head and the JSON body
+    fn get(&self, path: &str) -> (u16, String, Value) {
+        let mut stream = TcpStream::connect(&self.address).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", path, self.address).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, head.to_string(), serde_json::from_str(body).unwrap())
+    }
+}
+
+impl Drop for Gateway {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn home_with(name: &str, server: &MockServer) -> std::path::PathBuf {
+    temp_home(name, &format!("api_key = \"test\"\nbase_url = \"{}\"\ncache_ttl = \"0s\"\n\n[http]\nretries = 0\n", server.url))
+}
+
+#[test]
+fn current_conditions_by_city() {
+    let upstream = MockServer::start(vec![geocoded("Berlin", "DE", 52.5244, 13.4105), http_response(200, &[], CURRENT)]);
+    let home = home_with("serve-current", &upstream);
+    let gateway = Gateway::start(&home);
+
+    let (status, head, body) = gateway.get("/v1/current?city=Berlin&country=DE&units=imperial");
+
+    assert_eq!(status, 200);
+    assert!(head.to_lowercase().contains("content-type: application/json"), "{}", head);
+    assert_eq!(body["location"]["name"], "Berlin");
+    assert_eq!(body["temperature"], 68.0);
+    assert_eq!(body["units"]["temperature"], "°F");
+    assert!(upstream.requests()[0].contains("q=Berlin%2CDE"), "{:?}", upstream.requests());
+}
+
+#[test]
+fn health_and_bad_requests() {
+    let upstream = MockServer::start(vec![]);
+    let home = home_with("serve-health", &upstream);
+    let gateway = Gateway::start(&home);
+
+    let (status, _, body) = gateway.get("/v1/health");
+    assert_eq!(status, 200);
+    assert_eq!(body["status"], "ok");
+    assert_eq!(body["provider"], "OpenWeatherMap");
+
+    let (status, _, body) = gateway.get("/v1/current");
+    assert_eq!(status, 400);
+    assert_eq!(body["error"]["code"], "bad_request");
+
+    let (status, _, body) = gateway.get("/v2/anything");
+    assert_eq!(status, 404);
+    assert_eq!(body["error"]["code"], "unknown_endpoint");
+    assert!(upstream.requests().is_empty());
+}
+
+#[test]
+fn upstream_errors_map_to_http_statuses() {
+    let upstream = MockServer::start(vec![http_response(429, &[("Retry-After", "120")], r#"{"cod":429,"message":"slow down"}"#)]);
+    let home = home_with("serve-errors", &upstream);
+    let gateway = Gateway::start(&home);
+
+    let (status, head, body) = gateway.get("/v1/forecast?lat=52.52&lon=13.40");
+
+    assert_eq!(status, 429);
+    assert!(head.contains("Retry-After: 120"), "{}", head);
+    assert_eq!(body["error"]["code"], "rate_limited");
+}
+
+#[test]
+fn identical_concurrent_requests_share_one_upstream_call() {
+    let upstream = MockServer::with_delay(vec![http_response(200, &[], CURRENT)], Duration::from_millis(500));
+    let home = home_with("serve-merge", &upstream);
+    let gateway = Gateway::start(&home);
+
+    let replies: Vec<(u16, Value)> = thread::scope(|scope| {
+        let clients: Vec<_> = (0..5)
+            .map(|_| {
+                scope.spawn(|| {
+                    let (status, _, body) = gateway.get("/v1/current?location=52.52,13.40");
+                    (status, body)
+                })
+            })
+            .collect();
+        clients.into_iter().map(|client| client.join().unwrap()).collect()
+    });
+
+    assert_eq!(upstream.requests().len(), 1);
+    for (status, body) in replies {
+        assert_eq!(status, 200, "{}", body);
+        assert_eq!(body["temperature"], 20.0);
+    }
+}
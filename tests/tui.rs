@@ -0,0 +1,63 @@
+// Every test configures OpenWeatherMap
+#![cfg(feature = "openweathermap")]
+
+mod common;
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use common::{all_output, run_weather, temp_home, weather_command};
+
+#[test]
+fn needs_a_terminal() {
+    let home = temp_home("tui-terminal", "api_key = \"test\"\nbase_url = \"http://127.0.0.1:9\"\n");
+
+    let output = run_weather(&home, &["tui", "Berlin"], &[]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(all_output(&output).contains("the dashboard needs a terminal"), "{}", all_output(&output));
+
+    #[cfg(feature = "json")]
+    {
+        let output = run_weather(&home, &["tui", "--output", "json"], &[]);
+        assert_eq!(output.status.code(), Some(2));
+        let error: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+        assert_eq!(error["error"]["code"], "unsupported_output");
+    }
+    let config = std::fs::read_to_string(home.join("config").join("weather").join("config.toml")).unwrap();
+    assert!(!config.contains("[locations]"), "{}", config);
+}
+
+#[test]
+fn the_sidebar_is_the_config_files_saved_locations() {
+    let home = temp_home("tui-locations", "api_key = \"test\"\nbase_url = \"http://127.0.0.1:9\"\n\n[locations]\nhome = { city = \"Amsterdam\", country = \"NL\" }\n");
+
+    // The dashboard needs a terminal, so it runs under script(1), which gives it a pty fed from stdin
+    let weather = weather_command(&home, &[]);
+    let mut command = Command::new("script");
+    command.args(["-qec", &format!("{} tui 'Oslo, NO'", env!("CARGO_BIN_EXE_weather")), "/dev/null"]);
+    for (name, value) in weather.get_envs() {
+        match value {
+            Some(value) => command.env(name, value),
+            None => command.env_remove(name),
+        };
+    }
+    let Ok(mut child) = command.stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::null()).spawn() else {
+        eprintln!("script(1) is not available; skipping");
+        return;
+    };
+    thread::sleep(Duration::from_millis(1500));
+    child.stdin.take().unwrap().write_all(b"acabin=Bergen, NO\rq").unwrap();
+    assert!(child.wait().unwrap().success());
+
+    // Only the location added with `a` is saved; the one from the command line was just shown
+    let config = std::fs::read_to_string(home.join("config").join("weather").join("config.toml")).unwrap();
+    assert!(config.contains("home = { city = \"Amsterdam\", country = \"NL\" }"), "{}", config);
+    assert!(config.contains("cabin = { city = \"Bergen\", country = \"NO\" }"), "{}", config);
+    assert!(!config.contains("Oslo"), "{}", config);
+
+    let output = run_weather(&home, &["locations", "list"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("@home") && stdout.contains("@cabin"), "{}", stdout);
+}
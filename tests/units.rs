@@ -0,0 +1,82 @@
+// Every test talks to the mock server as OpenWeatherMap
+#![cfg(feature = "openweathermap")]
+
+mod common;
+
+use common::{geocoded, http_response, run_weather, temp_home, MockServer};
+
+// Minimal response of /data/2.5/weather; 11.2 °C, 5.1 m/s, 1008 hPa
+const CURRENT: &str = r#"{
+  "coord": {"lon": 13.4105, "lat": 52.5244},
+  "weather": [{"id": 500, "description": "light rain", "icon": "10d"}],
+  "main": {"temp": 11.2, "feels_like": 10.4, "pressure": 1008, "humidity": 87},
+  "visibility": 8000,
+  "wind": {"speed": 5.1, "gust": 9.3},
+  "rain": {"1h": 2.54},
+  "dt": 1792058400,
+  "sys": {"country": "DE"},
+  "timezone": 7200,
+  "name": "Berlin"
+}"#;
+
+fn report(name: &str, config: &str, args: &[&str]) -> (MockServer, String) {
+    let server = MockServer::start(vec![geocoded("Berlin", "DE", 52.5244, 13.4105), http_response(200, &[], CURRENT)]);
+    let home = temp_home(name, &format!("api_key = \"test\"\nbase_url = \"{}\"\n{}", server.url, config));
+
+    let mut all_args = vec!["now", "--city", "Berlin", "--country", "DE", "--detailed"];
+    all_args.extend_from_slice(args);
+    let output = run_weather(&home, &all_args, &[]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    (server, String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn assert_contains(stdout: &str, expected: &[&str]) {
+    for expected in expected {
+        assert!(stdout.contains(expected), "missing {:?} in:\n{}", expected, stdout);
+    }
+}
+
+#[test]
+fn imperial_converts_locally() {
+    let (server, stdout) = report("units-imperial", "", &["--units", "imperial"]);
+
+    // Readings are always fetched in metric and converted for display
+    assert!(server.requests()[1].contains("units=metric"), "{:?}", server.requests());
+    assert_contains(&stdout, &[
+        "Weather Update for Berlin: light rain ⛅",
+        "> Temperature: 52.2°F",
+        "> Pressure: 29.77 inHg",
+        "> Wind Speed: 11.4 mph",
+        "> Feels Like: 50.7°F",
+        "> Visibility: 5.0 mi",
+        "> Rain (1h): 0.10 in",
+    ]);
+}
+
+#[test]
+fn standard_uses_kelvin() {
+    let (_server, stdout) = report("units-standard", "", &["--units", "standard"]);
+
+    assert_contains(&stdout, &["light rain ⛅", "> Temperature: 284.3 K", "> Wind Speed: 5.1 m/s", "> Pressure: 1008.0 hPa"]);
+}
+
+#[test]
+fn overrides_replace_single_quantities() {
+    let (_server, stdout) = report("units-overrides", "", &["--wind-unit", "knots", "--pressure-unit", "mmhg"]);
+
+    assert_contains(&stdout, &[
+        "> Temperature: 11.2°C",
+        "> Wind Speed: 9.9 kn",
+        "> Wind Gusts: 18.1 kn",
+        "> Pressure: 756.1 mmHg",
+        "> Rain (1h): 2.5 mm",
+    ]);
+}
+
+#[test]
+fn config_file_sets_units_and_flags_win() {
+    let config = "units = \"imperial\"\nwind_unit = \"km/h\"\n";
+    let (_server, stdout) = report("units-config", config, &["--temperature-unit", "celsius"]);
+
+    assert_contains(&stdout, &["> Temperature: 11.2°C", "> Wind Speed: 18.4 km/h", "> Pressure: 29.77 inHg"]);
+}
@@ -0,0 +1,109 @@
+// Every test talks to the mock server as OpenWeatherMap
+#![cfg(feature = "openweathermap")]
+
+mod common;
+
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use common::{http_response, run_weather, temp_home, weather_command, MockServer};
+#[cfg(feature = "ndjson")]
+use serde_json::Value;
+
+fn current(temp: f64, humidity: u32, dt: u64) -> String {
+    let body = format!(
+        r#"{{"weather": [{{"id": 800, "description": "clear sky"}}], "main": {{"temp": {}, "pressure": 1013, "humidity": {}}},
+            "wind": {{"speed": 3.0}}, "dt": {}, "sys": {{"country": "DE"}}, "timezone": 7200, "name": "Berlin"}}"#,
+        temp, humidity, dt
+    );
+    http_response(200, &[], &body)
+}
+
+fn home_with(name: &str, server: &MockServer) -> std::path::PathBuf {
+    let config = format!("api_key = \"test\"\nbase_url = \"{}\"\ncache_ttl = \"0s\"\n\n[http]\nretries = 0\n", server.url);
+    temp_home(name, &config)
+}
+
+#[test]
+fn updates_show_trends_against_the_previous_reading() {
+    let server = MockServer::start(vec![current(20.0, 50, 1792058400), current(21.5, 40, 1792059000)]);
+    let home = home_with("watch-trends", &server);
+
+    let output = run_weather(&home, &["watch", "52.52,13.40", "--every", "1s", "--count", "2"], &[]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let updates: Vec<&str> = stdout.split("Weather Update for").skip(1).collect();
+    assert_eq!(updates.len(), 2, "{}", stdout);
+    assert!(updates[0].contains("> Temperature: 20.0°C\n"), "{}", updates[0]);
+    assert!(updates[1].contains("> Temperature: 21.5°C ↑"), "{}", updates[1]);
+    assert!(updates[1].contains("> Humidity: 40.0% ↓"), "{}", updates[1]);
+    assert!(updates[1].contains("> Pressure: 1013.0 hPa →"), "{}", updates[1]);
+    assert!(stdout.contains("Last updated "));
+    assert!(stdout.contains("next update in 1s."));
+    assert!(!stdout.contains('\x1b'), "redraws only happen on a terminal");
+}
+
+#[test]
+fn failed_updates_back_off_and_keep_going() {
+    let server = MockServer::start(vec![http_response(503, &[], "unavailable"), current(20.0, 50, 1792058400)]);
+    let home = home_with("watch-backoff", &server);
+
+    let started = Instant::now();
+    let output = run_weather(&home, &["watch", "52.52,13.40", "--every", "1s", "--count", "2"], &[]);
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Last updated never; update failed at"), "{}", stderr);
+    assert!(stderr.contains("Retrying in 1s."), "{}", stderr);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Weather Update for "));
+    assert!(started.elapsed() >= Duration::from_secs(1));
+}
+
+#[test]
+#[cfg(feature = "ndjson")]
+fn ndjson_writes_one_record_per_update() {
+    let server = MockServer::start(vec![current(20.0, 50, 1792058400), current(21.0, 50, 1792059000)]);
+    let home = home_with("watch-ndjson", &server);
+
+    let output = run_weather(&home, &["watch", "52.52,13.40", "--every", "1s", "--count", "2", "--output", "ndjson"], &[]);
+
+    assert!(output.status.success());
+    let records: Vec<Value> = String::from_utf8_lossy(&output.stdout).lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[1]["temperature"], 21.0);
+}
+
+#[test]
+fn readings_cached_longer_than_the_interval_are_reused_with_a_warning() {
+    let server = MockServer::start(vec![current(20.0, 50, 1792058400)]);
+    let home = temp_home("watch-cache", &format!("api_key = \"test\"\nbase_url = \"{}\"\n", server.url));
+
+    let output = run_weather(&home, &["watch", "52.52,13.40", "--every", "1s", "--count", "2"], &[]);
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Reports are cached for 10m"));
+    assert_eq!(server.requests().len(), 1);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).matches("Weather Update for").count(), 2);
+}
+
+#[test]
+fn ctrl_c_stops_watching_cleanly() {
+    let server = MockServer::start(vec![current(20.0, 50, 1792058400)]);
+    let home = home_with("watch-ctrl-c", &server);
+
+    let mut child = weather_command(&home, &["watch", "52.52,13.40", "--every", "1h"]).stdout(Stdio::piped()).spawn().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    while !line.contains("Last updated") {
+        line.clear();
+        assert!(stdout.read_line(&mut line).unwrap() > 0, "watch exited early");
+    }
+
+    let started = Instant::now();
+    Command::new("kill").args(["-INT", &child.id().to_string()]).status().unwrap();
+    let status = child.wait().unwrap();
+
+    assert!(status.success(), "{:?}", status);
+    assert!(started.elapsed() < Duration::from_secs(5));
+}